@@ -0,0 +1,68 @@
+use crate::graph::GfaError;
+use std::fmt;
+use std::io;
+
+//Errors returned by the public entry points of the library
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Gfa(GfaError),
+    //malformed marker table or markers for nodes absent from the graph
+    Markers(String),
+    //malformed auxiliary input table (e.g. coverage or node annotation)
+    Table(String),
+    Settings(String),
+    //node assignments inconsistent with each other or with the graph
+    Assignments(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    //Exit codes used by the CLI (1 is reserved for problems found by validation, 101 for panics)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io(_) => 2,
+            Error::Gfa(_) => 3,
+            Error::Markers(_) => 4,
+            Error::Table(_) => 5,
+            Error::Settings(_) => 6,
+            Error::Assignments(_) => 7,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            Error::Gfa(e) => write!(f, "{e}"),
+            Error::Markers(msg) => write!(f, "Invalid markers: {msg}"),
+            Error::Table(msg) => write!(f, "Invalid table: {msg}"),
+            Error::Settings(msg) => write!(f, "Invalid settings: {msg}"),
+            Error::Assignments(msg) => write!(f, "Inconsistent assignments: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Gfa(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<GfaError> for Error {
+    fn from(e: GfaError) -> Self {
+        Error::Gfa(e)
+    }
+}
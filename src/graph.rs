@@ -1,7 +1,122 @@
-use log::warn;
-use std::collections::HashMap;
+use crate::graph_algos::only_or_none;
+use log::{debug, warn};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Write};
 use std::str;
 
+mod binary;
+pub use binary::{BinaryHeader, BinarySource, BINARY_VERSION};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GfaErrorKind {
+    //minimal expected number of columns
+    TooFewColumns(usize),
+    InvalidTag,
+    InvalidDirection,
+    InvalidOverlap,
+    MissingLength,
+    ZeroLength,
+    UnknownSegment,
+    DuplicateSegment,
+    //maximal allowed overlap
+    OverlapTooLong(usize),
+    InvalidPosition,
+    UnknownReference,
+    //consecutive path segments are not connected by a link (or a jump)
+    MissingLink,
+    //overlap of the existing link
+    OverlapMismatch(usize),
+    ConflictingOverlap(usize),
+    //sequence of the segment is required, but wasn't provided
+    MissingSequence,
+    Io(String),
+}
+
+//Problem with a particular record of GFA input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GfaError {
+    //1-based line number, 0 if the problem isn't tied to a particular line
+    pub line: usize,
+    //record type, e.g. "S" or "L"
+    pub record: String,
+    //offending field
+    pub field: String,
+    pub kind: GfaErrorKind,
+}
+
+impl GfaError {
+    pub fn new(line: usize, record: &str, field: &str, kind: GfaErrorKind) -> GfaError {
+        GfaError {
+            line,
+            record: String::from(record),
+            field: String::from(field),
+            kind,
+        }
+    }
+}
+
+impl GfaError {
+    //problem description (without the position)
+    pub fn description(&self) -> String {
+        match self.kind {
+            GfaErrorKind::TooFewColumns(cnt) => format!("expected at least {cnt} columns"),
+            GfaErrorKind::InvalidTag => String::from("couldn't parse tag"),
+            GfaErrorKind::InvalidDirection => String::from("unknown direction"),
+            GfaErrorKind::InvalidOverlap => String::from("invalid overlap"),
+            GfaErrorKind::MissingLength => {
+                String::from("neither sequence nor LN tag provided for segment")
+            }
+            GfaErrorKind::ZeroLength => String::from("zero length segment"),
+            GfaErrorKind::UnknownSegment => String::from("segment is not in the graph"),
+            GfaErrorKind::DuplicateSegment => String::from("duplicate segment name"),
+            GfaErrorKind::InvalidPosition => String::from("invalid position or distance"),
+            GfaErrorKind::UnknownReference => String::from("unknown reference"),
+            GfaErrorKind::MissingLink => String::from("no link or jump between path segments"),
+            GfaErrorKind::OverlapMismatch(ovl) => {
+                format!("overlap is inconsistent with the link overlap {ovl}")
+            }
+            GfaErrorKind::OverlapTooLong(max_ovl) => {
+                format!("invalid (too long) overlap, maximal allowed is {max_ovl}")
+            }
+            GfaErrorKind::ConflictingOverlap(ovl) => {
+                format!("overlap conflicts with overlap {ovl} of another link between the same segments")
+            }
+            GfaErrorKind::MissingSequence => String::from("segment sequence is missing"),
+            GfaErrorKind::Io(ref e) => e.clone(),
+        }
+    }
+}
+
+impl fmt::Display for GfaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let GfaErrorKind::Io(ref e) = self.kind {
+            return write!(f, "Failed to read GFA line {}: {}", self.line, e);
+        }
+        if self.line == 0 {
+            return write!(
+                f,
+                "GFA {} record: {} '{}'",
+                self.record,
+                self.description(),
+                self.field
+            );
+        }
+        write!(
+            f,
+            "GFA line {} ({} record): {} '{}'",
+            self.line,
+            self.record,
+            self.description(),
+            self.field
+        )
+    }
+}
+
+impl std::error::Error for GfaError {}
+
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub enum Direction {
     FORWARD,
@@ -16,17 +131,20 @@ impl Direction {
         }
     }
 
-    fn parse_char(c: char) -> Direction {
+    fn parse_char(c: char) -> Option<Direction> {
         match c {
-            '+' => Self::FORWARD,
-            '-' => Self::REVERSE,
-            _ => panic!("Unknown direction {c}"),
+            '+' => Some(Self::FORWARD),
+            '-' => Some(Self::REVERSE),
+            _ => None,
         }
     }
 
-    fn parse(s: &str) -> Direction {
-        assert!(s.len() == 1, "Unknown direction {s}");
-        Self::parse_char(s.chars().next().unwrap())
+    fn parse(s: &str) -> Option<Direction> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Self::parse_char(c),
+            _ => None,
+        }
     }
 
     pub fn str(d: Direction) -> &'static str {
@@ -92,7 +210,7 @@ impl Vertex {
     }
 }
 
-//TODO support link coverage!
+//link attributes (e.g. read support) are kept by the graph, see Graph::link_support
 //TODO separate 'links' and 'edges'
 //links will have overlap size, CIGAR, etc
 //edges will represent a Vertex pair
@@ -125,74 +243,192 @@ impl Link {
     //}
 }
 
+//Outgoing connection of some vertex
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Neighbor {
+    pub end: Vertex,
+    pub overlap: usize,
+}
+
+//Outgoing neighbors of every vertex (indexed by 2 * node_id + [0 for forward, 1 for reverse]).
+//Every link is stored twice: as outgoing for its start and (reverse-complemented) for rc of its end.
+//Self-conjugate links are stored once.
+#[derive(Clone)]
+enum Adjacency {
+    //used while the graph is being built
+    Lists(Vec<Vec<Neighbor>>),
+    //compressed sparse row representation,
+    //neighbors of vertex with index i are entries[offsets[i]..offsets[i + 1]]
+    Compact {
+        offsets: Vec<usize>,
+        entries: Vec<Neighbor>,
+    },
+}
+
+impl Adjacency {
+    fn idx(v: Vertex) -> usize {
+        match v.direction {
+            Direction::FORWARD => 2 * v.node_id,
+            Direction::REVERSE => 2 * v.node_id + 1,
+        }
+    }
+
+    fn vertex_cnt(&self) -> usize {
+        match self {
+            Self::Lists(lists) => lists.len(),
+            Self::Compact { offsets, .. } => offsets.len() - 1,
+        }
+    }
+
+    fn neighbors(&self, v: Vertex) -> &[Neighbor] {
+        let i = Self::idx(v);
+        match self {
+            Self::Lists(lists) => &lists[i],
+            Self::Compact { offsets, entries } => &entries[offsets[i]..offsets[i + 1]],
+        }
+    }
+
+    fn add_node(&mut self) {
+        match self {
+            Self::Lists(lists) => {
+                lists.push(Vec::new());
+                lists.push(Vec::new());
+            }
+            Self::Compact { offsets, entries } => {
+                offsets.push(entries.len());
+                offsets.push(entries.len());
+            }
+        }
+    }
+
+    fn lists(&mut self) -> &mut Vec<Vec<Neighbor>> {
+        if let Self::Compact { .. } = self {
+            let lists = (0..self.vertex_cnt())
+                .map(|i| {
+                    let v = Vertex {
+                        node_id: i / 2,
+                        direction: if i % 2 == 0 {
+                            Direction::FORWARD
+                        } else {
+                            Direction::REVERSE
+                        },
+                    };
+                    self.neighbors(v).to_vec()
+                })
+                .collect();
+            *self = Self::Lists(lists);
+        }
+        match self {
+            Self::Lists(lists) => lists,
+            Self::Compact { .. } => unreachable!(),
+        }
+    }
+
+    fn compact(&mut self) {
+        if let Self::Lists(lists) = self {
+            let mut offsets = Vec::with_capacity(lists.len() + 1);
+            let mut entries = Vec::with_capacity(lists.iter().map(|l| l.len()).sum());
+            offsets.push(0);
+            for l in lists.iter() {
+                entries.extend_from_slice(l);
+                offsets.push(entries.len());
+            }
+            *self = Self::Compact { offsets, entries };
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Graph {
     nodes: Vec<Node>,
-    //TODO storage is excessive, should only store neighbor
-    //incoming & outgoing links for every node
-    incoming_links: Vec<Vec<Link>>,
-    outgoing_links: Vec<Vec<Link>>,
+    adjacency: Adjacency,
     //TODO switch to &str and figure out how to work with lifetimes
     name2ids: HashMap<String, usize>,
+    //only filled if requested while reading the graph
+    sequences: Vec<Option<String>>,
+    //gaps between vertices, e.g. provided by G records of GFA2
+    gaps: Vec<GapInfo>,
+    //vertex to outgoing gaps (index and flag if the gap should be reverse-complemented)
+    gap_index: HashMap<Vertex, Vec<(usize, bool)>>,
+    node_groups: Vec<NodeGroup>,
+    //named paths, provided by P and W records
+    paths: Vec<(String, Path)>,
+    //canonical (start, end) pairs of links with unknown overlap (stored as zero overlap)
+    unknown_overlaps: HashSet<(Vertex, Vertex)>,
+    //canonical (start, end) pairs of gaps with unknown size (stored as zero size)
+    unknown_gap_sizes: HashSet<(Vertex, Vertex)>,
+    //read support of links, keyed by canonical (start, end) pairs
+    link_support: HashMap<(Vertex, Vertex), usize>,
 }
 
-//TODO think about useful iterators and reimplement this one via composition
-//FIXME improve when learn how to store iterator as a field :)
-struct AllLinkIter<'a> {
-    g: &'a Graph,
-    curr_node: usize,
-    incoming_flag: bool,
-    pos: usize,
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GfaReadSettings {
+    pub collapse_multi_edges: bool,
+    pub normalize_overlaps: bool,
+    //store segment sequences (skipped otherwise)
+    pub keep_sequences: bool,
 }
 
-impl<'a> AllLinkIter<'a> {
-    fn new(g: &'a Graph) -> AllLinkIter<'a> {
-        AllLinkIter {
-            g,
-            curr_node: 0,
-            incoming_flag: true,
-            pos: 0,
+impl GfaReadSettings {
+    pub fn sanitize() -> GfaReadSettings {
+        GfaReadSettings {
+            collapse_multi_edges: true,
+            normalize_overlaps: true,
+            keep_sequences: false,
         }
     }
 }
 
-impl<'a> Iterator for AllLinkIter<'a> {
-    type Item = Link;
+//L (or dovetail E) record referencing segments by names
+struct LinkRecord {
+    line_num: usize,
+    record: &'static str,
+    start: (String, Direction),
+    end: (String, Direction),
+    cigar: String,
+    //None if unknown ('*' CIGAR)
+    overlap: Option<usize>,
+    //number of supporting reads (RC:i: or ex:i: tags)
+    support: Option<usize>,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        while self.curr_node < self.g.node_cnt() {
-            if self.incoming_flag {
-                let links = &self.g.incoming_links[self.curr_node];
-                assert!(self.pos <= links.len());
-                if self.pos < links.len() {
-                    let link = links[self.pos];
-                    assert!(link.end.node_id == self.curr_node);
-                    self.pos += 1;
-                    if link.end < link.start {
-                        return Some(link);
-                    }
-                } else {
-                    self.incoming_flag = false;
-                    self.pos = 0;
-                }
-            } else {
-                let links = &self.g.outgoing_links[self.curr_node];
-                assert!(self.pos <= links.len());
-                if self.pos < links.len() {
-                    let link = links[self.pos];
-                    assert!(link.start.node_id == self.curr_node);
-                    self.pos += 1;
-                    if link.start <= link.end {
-                        return Some(link);
-                    }
-                } else {
-                    self.incoming_flag = true;
-                    self.pos = 0;
-                    self.curr_node += 1;
-                }
-            }
-        }
-        None
-    }
+//GFA2 G record referencing segments by names
+struct GapRecord {
+    line_num: usize,
+    record: &'static str,
+    name: String,
+    start: (String, Direction),
+    end: (String, Direction),
+    //None if unknown
+    gap_size: Option<i64>,
+}
+
+//P or W record referencing segments by names
+struct WalkRecord {
+    line_num: usize,
+    record: &'static str,
+    name: String,
+    //oriented segments with flags if they are preceded by a jump (';' separator of P record)
+    steps: Vec<((String, Direction), bool)>,
+    //CIGARs of consecutive step overlaps, if provided
+    overlaps: Option<Vec<String>>,
+}
+
+//GFA2 O or U record
+struct GroupRecord {
+    line_num: usize,
+    record: &'static str,
+    name: String,
+    ordered: bool,
+    refs: Vec<String>,
+}
+
+//Named set of nodes, provided by O (ordered) and U (unordered) GFA2 groups
+#[derive(Clone, Debug)]
+pub struct NodeGroup {
+    pub name: String,
+    pub ordered: bool,
+    pub node_ids: Vec<usize>,
 }
 
 struct VertexIter<'a> {
@@ -240,9 +476,16 @@ impl Graph {
     pub fn new() -> Graph {
         Graph {
             nodes: Vec::new(),
-            incoming_links: Vec::new(),
-            outgoing_links: Vec::new(),
+            adjacency: Adjacency::Lists(Vec::new()),
             name2ids: HashMap::new(),
+            sequences: Vec::new(),
+            gaps: Vec::new(),
+            gap_index: HashMap::new(),
+            node_groups: Vec::new(),
+            paths: Vec::new(),
+            unknown_overlaps: HashSet::new(),
+            unknown_gap_sizes: HashSet::new(),
+            link_support: HashMap::new(),
         }
     }
 
@@ -250,7 +493,7 @@ impl Graph {
         self.nodes.len()
     }
 
-    pub fn node_iter(&self) -> std::slice::Iter<Node> {
+    pub fn node_iter(&self) -> std::slice::Iter<'_, Node> {
         self.nodes.iter()
     }
 
@@ -259,185 +502,1088 @@ impl Graph {
         let node_id = self.nodes.len();
         self.name2ids.insert(node.name.clone(), node_id);
         self.nodes.push(node);
-        self.incoming_links.push(Vec::new());
-        self.outgoing_links.push(Vec::new());
+        self.adjacency.add_node();
+        self.sequences.push(None);
         node_id
     }
 
+    //NB: adding links to compacted graph switches it back to (less efficient) list representation
     pub fn add_link(&mut self, link: Link) {
         //FIXME Currently doesn't check that every link is represented only once
-        //TODO Think of some nice 'views' for vectors that will reverse complement everything put
-        //there
-        match link.start.direction {
-            Direction::FORWARD => self.outgoing_links[link.start.node_id].push(link),
-            Direction::REVERSE => self.incoming_links[link.start.node_id].push(link.rc()),
-        };
+        let lists = self.adjacency.lists();
+        lists[Adjacency::idx(link.start)].push(Neighbor {
+            end: link.end,
+            overlap: link.overlap,
+        });
 
         if link == link.rc() {
             return;
         };
 
-        match link.end.direction {
-            Direction::FORWARD => self.incoming_links[link.end.node_id].push(link),
-            Direction::REVERSE => self.outgoing_links[link.end.node_id].push(link.rc()),
-        };
+        lists[Adjacency::idx(link.end.rc())].push(Neighbor {
+            end: link.start.rc(),
+            overlap: link.overlap,
+        });
+    }
+
+    //Switches to compressed sparse row adjacency representation (done after reading the graph)
+    pub fn compact(&mut self) {
+        self.adjacency.compact();
+    }
+
+    pub fn add_gap(&mut self, gap: GapInfo) {
+        let gap_id = self.gaps.len();
+        self.gap_index
+            .entry(gap.start)
+            .or_default()
+            .push((gap_id, false));
+        if gap.start != gap.end.rc() {
+            self.gap_index
+                .entry(gap.end.rc())
+                .or_default()
+                .push((gap_id, true));
+        }
+        self.gaps.push(gap);
+    }
+
+    //gap with unknown size, e.g. J record with '*' distance
+    pub fn add_gap_unknown_size(&mut self, gap: GapInfo) {
+        self.unknown_gap_sizes
+            .insert(Self::link_key(gap.start, gap.end));
+        self.add_gap(GapInfo { gap_size: 0, ..gap });
+    }
+
+    //false for gaps provided with '*' distance,
+    //consumers should use their own estimate (e.g. default gap size of haplo-path search)
+    pub fn gap_size_known(&self, gap: &GapInfo) -> bool {
+        !self
+            .unknown_gap_sizes
+            .contains(&Self::link_key(gap.start, gap.end))
+    }
+
+    pub fn gaps(&self) -> &[GapInfo] {
+        &self.gaps
+    }
+
+    pub fn outgoing_gaps(&self, v: Vertex) -> Vec<GapInfo> {
+        match self.gap_index.get(&v) {
+            None => Vec::new(),
+            Some(ids) => ids
+                .iter()
+                .map(|&(gap_id, rc)| match rc {
+                    false => self.gaps[gap_id].clone(),
+                    true => self.gaps[gap_id].rc(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn node_groups(&self) -> &[NodeGroup] {
+        &self.node_groups
+    }
+
+    pub fn node_group(&self, name: &str) -> Option<&NodeGroup> {
+        self.node_groups.iter().find(|g| g.name == name)
+    }
+
+    pub fn paths(&self) -> &[(String, Path)] {
+        &self.paths
+    }
+
+    pub fn add_path(&mut self, name: &str, path: Path) {
+        self.paths.push((String::from(name), path));
     }
 
     //FIXME add this check within add_link function
     fn check_links(&self) {
-        assert!(self.nodes.len() == self.incoming_links.len());
-        assert!(self.nodes.len() == self.outgoing_links.len());
-        for (node_id, _) in self.all_nodes().enumerate() {
-            let v = Vertex::forward(node_id);
-            assert!(
-                self.incoming_links[node_id]
-                    .iter()
-                    .filter(|l| l.end != v)
-                    .count()
-                    == 0,
-                "Problem with incoming links for node {}",
-                self.nodes[node_id].name
-            );
-            assert!(
-                self.outgoing_links[node_id]
-                    .iter()
-                    .filter(|l| l.start != v)
-                    .count()
-                    == 0,
-                "Problem with incoming links for node {}",
-                self.nodes[node_id].name
-            );
+        assert!(2 * self.nodes.len() == self.adjacency.vertex_cnt());
+        for v in self.all_vertices() {
+            for l in self.outgoing_edge_iter(v) {
+                assert!(
+                    self.outgoing_edge_iter(l.end.rc()).any(|x| x == l.rc()),
+                    "Problem with links of node {}",
+                    self.nodes[v.node_id].name
+                );
+            }
         }
     }
 
     //TODO switch to iterator?
-    fn parse_tag<T: str::FromStr>(fields: &[&str], prefix: &str) -> Option<T> {
-        fields
-            .iter()
-            .filter(|s| s.starts_with(prefix))
-            .map(|s| match s[prefix.len()..].parse::<T>() {
-                Ok(t) => t,
-                Err(_) => panic!("Couldn't parse tag {s}"),
-            })
-            .next()
+    //returns offending field if the value can't be parsed
+    fn parse_tag<'a, T: str::FromStr>(
+        fields: &[&'a str],
+        prefix: &str,
+    ) -> Result<Option<T>, &'a str> {
+        match fields.iter().find(|s| s.starts_with(prefix)) {
+            None => Ok(None),
+            Some(s) => s[prefix.len()..].parse::<T>().map(Some).map_err(|_| *s),
+        }
     }
 
-    fn parse_overlap(cigar: &str) -> usize {
-        assert!(cigar.ends_with('M'), "Invalid overlap {cigar}");
-        let ovl = &cigar[..(cigar.len() - 1)];
-        ovl.trim().parse().expect("Invalid overlap")
+    //Overlap size is the maximal number of bases consumed by the CIGAR on either of the sequences
+    fn parse_overlap(cigar: &str) -> Option<usize> {
+        let cigar = cigar.trim();
+        if cigar.is_empty() {
+            return None;
+        }
+        let mut query_len = 0;
+        let mut target_len = 0;
+        let mut len_start = 0;
+        for (i, op) in cigar.char_indices() {
+            if op.is_ascii_digit() {
+                continue;
+            }
+            let len: usize = cigar[len_start..i].parse().ok()?;
+            len_start = i + 1;
+            match op {
+                'M' | '=' | 'X' => {
+                    query_len += len;
+                    target_len += len;
+                }
+                'I' | 'S' => query_len += len,
+                'D' | 'N' => target_len += len,
+                'H' | 'P' => {}
+                _ => return None,
+            }
+        }
+        if len_start != cigar.len() {
+            //trailing length without operation
+            return None;
+        }
+        Some(std::cmp::max(query_len, target_len))
     }
 
-    //TODO switch to something iterable
-    pub fn custom_read(
-        graph_str: &str,
-        collapse_multi_edges: bool,
-        normalize_overlaps: bool,
-    ) -> Graph {
-        let mut g = Self::new();
+    fn link_key(start: Vertex, end: Vertex) -> (Vertex, Vertex) {
+        std::cmp::min((start, end), (end.rc(), start.rc()))
+    }
 
-        for line in graph_str.lines() {
-            if line.starts_with("S\t") {
-                let split: Vec<&str> = line.split('\t').collect();
-                //println!("Node line {:?}", split);
-                let name = String::from(split[1]);
-                let tags = &split[3..split.len()];
-                let length = if split[2] != "*" {
-                    split[2].trim().len()
+    //None if support information is not available for the link
+    pub fn link_support(&self, l: &Link) -> Option<usize> {
+        self.link_support
+            .get(&Self::link_key(l.start, l.end))
+            .copied()
+    }
+
+    pub fn set_link_support(&mut self, l: &Link, support: usize) {
+        self.link_support
+            .insert(Self::link_key(l.start, l.end), support);
+    }
+
+    //false for links provided with '*' overlap
+    pub fn overlap_known(&self, l: &Link) -> bool {
+        !self
+            .unknown_overlaps
+            .contains(&Self::link_key(l.start, l.end))
+    }
+
+    fn field<'a>(split: &[&'a str], idx: usize, line_num: usize) -> Result<&'a str, GfaError> {
+        split.get(idx).copied().ok_or_else(|| {
+            GfaError::new(
+                line_num,
+                split[0],
+                split.join("\t").as_str(),
+                GfaErrorKind::TooFewColumns(idx + 1),
+            )
+        })
+    }
+
+    fn parse_direction(split: &[&str], idx: usize, line_num: usize) -> Result<Direction, GfaError> {
+        let dir_s = Self::field(split, idx, line_num)?;
+        Direction::parse(dir_s)
+            .ok_or_else(|| GfaError::new(line_num, split[0], dir_s, GfaErrorKind::InvalidDirection))
+    }
+
+    fn parse_link(split: &[&str], line_num: usize) -> Result<LinkRecord, GfaError> {
+        let cigar = Self::field(split, 5, line_num)?;
+        let tags = &split[6..];
+        let support = match Self::parse_tag::<usize>(tags, "RC:i:") {
+            Ok(None) => Self::parse_tag::<usize>(tags, "ex:i:"),
+            r => r,
+        }
+        .map_err(|f| GfaError::new(line_num, split[0], f, GfaErrorKind::InvalidTag))?;
+        Ok(LinkRecord {
+            line_num,
+            record: "L",
+            start: (
+                String::from(Self::field(split, 1, line_num)?),
+                Self::parse_direction(split, 2, line_num)?,
+            ),
+            end: (
+                String::from(Self::field(split, 3, line_num)?),
+                Self::parse_direction(split, 4, line_num)?,
+            ),
+            overlap: match cigar.trim() {
+                "*" => None,
+                _ => Some(Self::parse_overlap(cigar).ok_or_else(|| {
+                    GfaError::new(line_num, split[0], cigar, GfaErrorKind::InvalidOverlap)
+                })?),
+            },
+            cigar: String::from(cigar),
+            support,
+        })
+    }
+
+    fn parse_segment(
+        split: &[&str],
+        line_num: usize,
+        keep_sequence: bool,
+    ) -> Result<(Node, Option<String>), GfaError> {
+        let err = |field: &str, kind| GfaError::new(line_num, split[0], field, kind);
+        let name = String::from(Self::field(split, 1, line_num)?);
+        //GFA2 segments have an additional length field before the sequence
+        let gfa2 =
+            split.len() > 3 && !split[2].is_empty() && split[2].bytes().all(|b| b.is_ascii_digit());
+        let (seq, tags) = if gfa2 {
+            (split[3].trim(), &split[4..split.len()])
+        } else {
+            (
+                Self::field(split, 2, line_num)?.trim(),
+                &split[3..split.len()],
+            )
+        };
+        let length = if gfa2 {
+            split[2]
+                .parse()
+                .map_err(|_| err(split[2], GfaErrorKind::MissingLength))?
+        } else if seq != "*" {
+            seq.len()
+        } else {
+            Self::parse_tag(tags, "LN:i:")
+                .map_err(|f| err(f, GfaErrorKind::InvalidTag))?
+                .ok_or_else(|| err(&name, GfaErrorKind::MissingLength))?
+        };
+        if length == 0 {
+            return Err(err(&name, GfaErrorKind::ZeroLength));
+        }
+        let raw_cnt = match Self::parse_tag::<usize>(tags, "RC:i:") {
+            Ok(None) => Self::parse_tag::<usize>(tags, "FC:i:"),
+            r => r,
+        }
+        .map_err(|f| err(f, GfaErrorKind::InvalidTag))?;
+        let coverage = match raw_cnt {
+            None => Self::parse_tag(tags, "ll:f:")
+                .map_err(|f| err(f, GfaErrorKind::InvalidTag))?
+                .unwrap_or(0.),
+            Some(raw_cnt) => raw_cnt as f64 / length as f64,
+        };
+        let sequence = if keep_sequence && seq != "*" {
+            Some(String::from(seq))
+        } else {
+            None
+        };
+        Ok((
+            Node {
+                name,
+                length,
+                coverage,
+            },
+            sequence,
+        ))
+    }
+
+    //GFA2 reference of the form <name>[+-]
+    fn parse_reference(
+        split: &[&str],
+        idx: usize,
+        line_num: usize,
+    ) -> Result<(String, Direction), GfaError> {
+        Self::parse_oriented(Self::field(split, idx, line_num)?, split[0], line_num)
+    }
+
+    fn parse_oriented(
+        r: &str,
+        record: &str,
+        line_num: usize,
+    ) -> Result<(String, Direction), GfaError> {
+        let invalid = || GfaError::new(line_num, record, r, GfaErrorKind::InvalidDirection);
+        if r.len() < 2 || !r.is_char_boundary(r.len() - 1) {
+            return Err(invalid());
+        }
+        let (name, dir_s) = r.split_at(r.len() - 1);
+        Ok((
+            String::from(name),
+            Direction::parse(dir_s).ok_or_else(invalid)?,
+        ))
+    }
+
+    //GFA2 position, second value is true if position is at the end of the segment ('$' suffix)
+    fn parse_position(
+        split: &[&str],
+        idx: usize,
+        line_num: usize,
+    ) -> Result<(usize, bool), GfaError> {
+        let p = Self::field(split, idx, line_num)?;
+        let (pos_s, at_end) = match p.strip_suffix('$') {
+            Some(pos_s) => (pos_s, true),
+            None => (p, false),
+        };
+        let pos = pos_s
+            .parse()
+            .map_err(|_| GfaError::new(line_num, split[0], p, GfaErrorKind::InvalidPosition))?;
+        Ok((pos, at_end))
+    }
+
+    //Only dovetail overlaps are turned into links, None for containments and internal alignments
+    fn parse_edge(split: &[&str], line_num: usize) -> Result<Option<LinkRecord>, GfaError> {
+        let s1 = Self::parse_reference(split, 2, line_num)?;
+        let s2 = Self::parse_reference(split, 3, line_num)?;
+        let (beg1, beg1_end) = Self::parse_position(split, 4, line_num)?;
+        let (end1, end1_end) = Self::parse_position(split, 5, line_num)?;
+        let (beg2, beg2_end) = Self::parse_position(split, 6, line_num)?;
+        let (end2, end2_end) = Self::parse_position(split, 7, line_num)?;
+        let alignment = Self::field(split, 8, line_num)?;
+        if end1 < beg1 || end2 < beg2 {
+            return Err(GfaError::new(
+                line_num,
+                split[0],
+                split[1],
+                GfaErrorKind::InvalidPosition,
+            ));
+        }
+
+        //prefix/suffix flags of the aligned intervals on the oriented segments
+        let oriented = |d: Direction, beg: usize, beg_end: bool, end_end: bool| match d {
+            Direction::FORWARD => (beg == 0 && !beg_end, end_end),
+            Direction::REVERSE => (end_end, beg == 0 && !beg_end),
+        };
+        let (prefix1, suffix1) = oriented(s1.1, beg1, beg1_end, end1_end);
+        let (prefix2, suffix2) = oriented(s2.1, beg2, beg2_end, end2_end);
+
+        let overlap = std::cmp::max(end1 - beg1, end2 - beg2);
+        let (start, end) = if prefix1 && suffix1 || prefix2 && suffix2 {
+            //containment
+            return Ok(None);
+        } else if suffix1 && prefix2 {
+            (s1, s2)
+        } else if suffix2 && prefix1 {
+            (s2, s1)
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(LinkRecord {
+            line_num,
+            record: "E",
+            start,
+            end,
+            cigar: String::from(alignment),
+            overlap: Some(overlap),
+            support: None,
+        }))
+    }
+
+    fn parse_gap(split: &[&str], line_num: usize) -> Result<GapRecord, GfaError> {
+        let dist = Self::field(split, 4, line_num)?;
+        Ok(GapRecord {
+            line_num,
+            record: "G",
+            name: String::from(Self::field(split, 1, line_num)?),
+            start: Self::parse_reference(split, 2, line_num)?,
+            end: Self::parse_reference(split, 3, line_num)?,
+            gap_size: Some(dist.parse().map_err(|_| {
+                GfaError::new(line_num, split[0], dist, GfaErrorKind::InvalidPosition)
+            })?),
+        })
+    }
+
+    //GFA1.2 J record, distance can be unknown ('*')
+    fn parse_jump(split: &[&str], line_num: usize) -> Result<GapRecord, GfaError> {
+        let dist = Self::field(split, 5, line_num)?;
+        let gap_size = match dist {
+            "*" => None,
+            _ => Some(dist.parse().map_err(|_| {
+                GfaError::new(line_num, split[0], dist, GfaErrorKind::InvalidPosition)
+            })?),
+        };
+        Ok(GapRecord {
+            line_num,
+            record: "J",
+            name: Self::parse_tag(&split[6..], "ID:Z:")
+                .map_err(|f| GfaError::new(line_num, split[0], f, GfaErrorKind::InvalidTag))?
+                .unwrap_or_else(|| String::from("*")),
+            start: (
+                String::from(Self::field(split, 1, line_num)?),
+                Self::parse_direction(split, 2, line_num)?,
+            ),
+            end: (
+                String::from(Self::field(split, 3, line_num)?),
+                Self::parse_direction(split, 4, line_num)?,
+            ),
+            gap_size,
+        })
+    }
+
+    //P record, segments are separated by ',' (link) or ';' (jump)
+    fn parse_path(split: &[&str], line_num: usize) -> Result<WalkRecord, GfaError> {
+        let name = String::from(Self::field(split, 1, line_num)?);
+        let mut steps = Vec::new();
+        let mut jump = false;
+        for token in Self::field(split, 2, line_num)?.split_inclusive([',', ';']) {
+            let (r, next_jump) = match token.strip_suffix(';') {
+                Some(r) => (r, true),
+                None => (token.strip_suffix(',').unwrap_or(token), false),
+            };
+            steps.push((Self::parse_oriented(r, split[0], line_num)?, jump));
+            jump = next_jump;
+        }
+        let overlaps = match split.get(3).map(|s| s.trim()) {
+            None | Some("*") | Some("") => None,
+            Some(ovls) => {
+                let overlaps: Vec<String> = ovls.split(',').map(String::from).collect();
+                if overlaps.len() + 1 != steps.len() {
+                    return Err(GfaError::new(
+                        line_num,
+                        split[0],
+                        ovls,
+                        GfaErrorKind::InvalidOverlap,
+                    ));
+                }
+                Some(overlaps)
+            }
+        };
+        Ok(WalkRecord {
+            line_num,
+            record: "P",
+            name,
+            steps,
+            overlaps,
+        })
+    }
+
+    //W record, named as <sample>#<haplotype>#<sequence>[:<start>-<end>]
+    fn parse_walk(split: &[&str], line_num: usize) -> Result<WalkRecord, GfaError> {
+        let start = Self::field(split, 4, line_num)?;
+        let end = Self::field(split, 5, line_num)?;
+        let mut name = format!(
+            "{}#{}#{}",
+            Self::field(split, 1, line_num)?,
+            Self::field(split, 2, line_num)?,
+            Self::field(split, 3, line_num)?
+        );
+        if start != "*" && start != "0" {
+            name += &format!(":{start}-{end}");
+        }
+        let walk = Self::field(split, 6, line_num)?;
+        let invalid = || GfaError::new(line_num, split[0], walk, GfaErrorKind::InvalidDirection);
+        let mut steps = Vec::new();
+        for (pos, c) in walk.match_indices(['>', '<']) {
+            let seg_end = walk[pos + 1..]
+                .find(['>', '<'])
+                .map_or(walk.len(), |i| pos + 1 + i);
+            let direction = if c == ">" {
+                Direction::FORWARD
+            } else {
+                Direction::REVERSE
+            };
+            if seg_end == pos + 1 {
+                return Err(invalid());
+            }
+            steps.push(((String::from(&walk[pos + 1..seg_end]), direction), false));
+        }
+        if steps.is_empty() || !walk.starts_with(['>', '<']) {
+            return Err(invalid());
+        }
+        Ok(WalkRecord {
+            line_num,
+            record: "W",
+            name,
+            steps,
+            overlaps: None,
+        })
+    }
+
+    //Returns None if the path visits some node more than once (not supported by Path)
+    fn resolve_walk(
+        &self,
+        record: &WalkRecord,
+        settings: &GfaReadSettings,
+    ) -> Result<Option<Path>, GfaError> {
+        let err = |field: &str, kind| GfaError::new(record.line_num, record.record, field, kind);
+        let mut vertices = Vec::new();
+        for (r, _) in &record.steps {
+            vertices.push(
+                self.resolve(r)
+                    .ok_or_else(|| err(&r.0, GfaErrorKind::UnknownSegment))?,
+            );
+        }
+        let mut seen = HashSet::new();
+        if !vertices.iter().all(|v| seen.insert(v.node_id)) {
+            return Ok(None);
+        }
+
+        let mut path = Path::new(vertices[0]);
+        for (i, &w) in vertices.iter().enumerate().skip(1) {
+            let v = vertices[i - 1];
+            let step_str = || format!("{},{}", self.v_str(v), self.v_str(w));
+            if record.steps[i].1 {
+                let gap = self
+                    .outgoing_gaps(v)
+                    .into_iter()
+                    .find(|g| g.end == w)
+                    .ok_or_else(|| err(&step_str(), GfaErrorKind::MissingLink))?;
+                path.append_general(GeneralizedLink::GAP(gap));
+                continue;
+            }
+            let links: Vec<Link> = self
+                .outgoing_edges(v)
+                .into_iter()
+                .filter(|l| l.end == w)
+                .collect();
+            if links.is_empty() {
+                return Err(err(&step_str(), GfaErrorKind::MissingLink));
+            }
+            let cigar = record.overlaps.as_ref().map(|ovls| ovls[i - 1].as_str());
+            let link = match cigar.filter(|&c| c != "*") {
+                None => links[0],
+                Some(cigar) => {
+                    let overlap = Self::parse_overlap(cigar)
+                        .ok_or_else(|| err(cigar, GfaErrorKind::InvalidOverlap))?;
+                    match links
+                        .iter()
+                        .find(|l| l.overlap == overlap || !self.overlap_known(l))
+                    {
+                        Some(&l) => l,
+                        None if settings.normalize_overlaps => {
+                            warn!(
+                                "Path {} overlap {} between {} and {} inconsistent with the link, using {}",
+                                record.name,
+                                overlap,
+                                self.v_str(v),
+                                self.v_str(w),
+                                links[0].overlap
+                            );
+                            links[0]
+                        }
+                        None => {
+                            return Err(err(cigar, GfaErrorKind::OverlapMismatch(links[0].overlap)))
+                        }
+                    }
+                }
+            };
+            path.append(link);
+        }
+        Ok(Some(path))
+    }
+
+    fn parse_group(split: &[&str], line_num: usize) -> Result<GroupRecord, GfaError> {
+        let ordered = split[0] == "O";
+        let refs = Self::field(split, 2, line_num)?
+            .split(' ')
+            .filter(|r| !r.is_empty())
+            .map(|r| match r.strip_suffix(['+', '-']) {
+                Some(name) if ordered => String::from(name),
+                _ => String::from(r),
+            })
+            .collect();
+        Ok(GroupRecord {
+            line_num,
+            record: if ordered { "O" } else { "U" },
+            name: String::from(Self::field(split, 1, line_num)?),
+            ordered,
+            refs,
+        })
+    }
+
+    fn add_gap_record(&mut self, record: &GapRecord) -> Result<(), GfaError> {
+        let err = |field: &str| {
+            GfaError::new(
+                record.line_num,
+                record.record,
+                field,
+                GfaErrorKind::UnknownSegment,
+            )
+        };
+        let start = self
+            .resolve(&record.start)
+            .ok_or_else(|| err(&record.start.0))?;
+        let end = self
+            .resolve(&record.end)
+            .ok_or_else(|| err(&record.end.0))?;
+        let gap = GapInfo {
+            start,
+            end,
+            gap_size: record.gap_size.unwrap_or(0),
+            info: if record.name == "*" {
+                String::from("gfa_gap")
+            } else {
+                record.name.clone()
+            },
+        };
+        match record.gap_size {
+            Some(_) => self.add_gap(gap),
+            None => self.add_gap_unknown_size(gap),
+        }
+        Ok(())
+    }
+
+    //Expands group references into node ids.
+    //References can be segment names, edge/gap ids or ids of other groups.
+    fn resolve_group(
+        &self,
+        name: &str,
+        groups: &HashMap<&str, &GroupRecord>,
+        edge_ends: &HashMap<String, (String, String)>,
+        in_progress: &mut HashSet<String>,
+    ) -> Result<Vec<usize>, GfaError> {
+        let record = groups[name];
+        in_progress.insert(String::from(name));
+        let mut node_ids = Vec::new();
+        for r in &record.refs {
+            if let Some(&node_id) = self.name2ids.get(r) {
+                node_ids.push(node_id);
+            } else if let Some((s1, s2)) = edge_ends.get(r) {
+                //edge ends were checked to be present in the graph
+                node_ids.push(self.name2id(s1));
+                node_ids.push(self.name2id(s2));
+            } else if groups.contains_key(r.as_str()) {
+                if in_progress.contains(r) {
+                    warn!("Ignoring cyclic reference to group {r} in group {name}");
                 } else {
-                    Self::parse_tag(tags, "LN:i:").expect("Neither sequence nor LN tag provided")
-                };
-                assert!(length > 0);
-                let coverage = match Self::parse_tag::<usize>(tags, "RC:i:")
-                    .or_else(|| Self::parse_tag::<usize>(tags, "FC:i:"))
+                    node_ids.extend(self.resolve_group(r, groups, edge_ends, in_progress)?);
+                }
+            } else {
+                return Err(GfaError::new(
+                    record.line_num,
+                    record.record,
+                    r,
+                    GfaErrorKind::UnknownReference,
+                ));
+            }
+        }
+        in_progress.remove(name);
+        if !record.ordered {
+            let mut seen = HashSet::new();
+            node_ids.retain(|&n| seen.insert(n));
+        }
+        Ok(node_ids)
+    }
+
+    fn resolve(&self, (name, direction): &(String, Direction)) -> Option<Vertex> {
+        Some(Vertex {
+            node_id: *self.name2ids.get(name)?,
+            direction: *direction,
+        })
+    }
+
+    fn add_link_record(
+        &mut self,
+        record: &LinkRecord,
+        settings: &GfaReadSettings,
+    ) -> Result<(), GfaError> {
+        let err = |field: &str, kind| GfaError::new(record.line_num, record.record, field, kind);
+        let start = self
+            .resolve(&record.start)
+            .ok_or_else(|| err(&record.start.0, GfaErrorKind::UnknownSegment))?;
+        let end = self
+            .resolve(&record.end)
+            .ok_or_else(|| err(&record.end.0, GfaErrorKind::UnknownSegment))?;
+        let mut overlap = record.overlap.unwrap_or(0);
+        if settings.collapse_multi_edges {
+            if let Some(connect) = self.connector(start, end) {
+                if record.overlap.is_some()
+                    && self.overlap_known(&connect)
+                    && connect.overlap != overlap
                 {
-                    None => Self::parse_tag(tags, "ll:f:").unwrap_or(0.),
-                    Some(raw_cnt) => raw_cnt as f64 / length as f64,
-                };
-                g.add_node(Node {
-                    name,
-                    length,
-                    coverage,
-                });
+                    warn!(
+                        "Multiple links connecting {} and {} with different overlap sizes ({} and {})",
+                        self.v_str(start),
+                        self.v_str(end),
+                        overlap,
+                        connect.overlap
+                    )
+                }
+                //support of collapsed links is summed up
+                if let Some(support) = record.support {
+                    let total = self.link_support(&connect).unwrap_or(0) + support;
+                    self.set_link_support(&connect, total);
+                }
+                return Ok(());
+            }
+        }
+        let max_ovl = std::cmp::min(self.vertex_length(start), self.vertex_length(end)) - 1;
+        if overlap > max_ovl {
+            if !settings.normalize_overlaps {
+                return Err(err(&record.cigar, GfaErrorKind::OverlapTooLong(max_ovl)));
             }
+            warn!(
+                "Normalizing overlap between {} and {} ({} -> {})",
+                self.v_str(start),
+                self.v_str(end),
+                overlap,
+                max_ovl
+            );
+            overlap = max_ovl;
+        }
+        if record.overlap.is_none() {
+            self.unknown_overlaps.insert(Self::link_key(start, end));
+        }
+        let link = Link {
+            start,
+            end,
+            overlap,
+        };
+        if let Some(support) = record.support {
+            self.set_link_support(&link, support);
         }
+        self.add_link(link);
+        Ok(())
+    }
 
-        for line in graph_str.lines() {
-            if line.starts_with("L\t") {
+    //Parses records while reading, so that the whole file is never held in memory.
+    //Links referring to segments, which were not yet encountered, are postponed.
+    pub fn read_from<R: BufRead>(
+        mut reader: R,
+        settings: &GfaReadSettings,
+    ) -> Result<Graph, GfaError> {
+        let mut g = Self::new();
+        let mut postponed_links = Vec::new();
+        let mut gaps = Vec::new();
+        let mut groups = Vec::new();
+        let mut walks = Vec::new();
+        //GFA2 edge and gap ids (with line numbers), which can be referenced by groups
+        let mut edge_ends = HashMap::new();
+        let mut buf = String::new();
+
+        let mut line_num = 0;
+        loop {
+            buf.clear();
+            line_num += 1;
+            if reader
+                .read_line(&mut buf)
+                .map_err(|e| GfaError::new(line_num, "", "", GfaErrorKind::Io(e.to_string())))?
+                == 0
+            {
+                break;
+            }
+            let line = buf.trim_end_matches(['\n', '\r']);
+            if line.starts_with("S\t") {
+                let split: Vec<&str> = line.split('\t').collect();
+                //println!("Node line {:?}", split);
+                let (node, sequence) =
+                    Self::parse_segment(&split, line_num, settings.keep_sequences)?;
+                if g.name2ids.contains_key(&node.name) {
+                    return Err(GfaError::new(
+                        line_num,
+                        split[0],
+                        &node.name,
+                        GfaErrorKind::DuplicateSegment,
+                    ));
+                }
+                let node_id = g.add_node(node);
+                g.sequences[node_id] = sequence;
+            } else if line.starts_with("L\t") {
                 let split: Vec<&str> = line.trim().split('\t').collect();
                 //println!("Link line {:?}", split);
-                let start = Vertex {
-                    node_id: g.name2id(split[1]),
-                    direction: Direction::parse(split[2]),
-                };
-                let end = Vertex {
-                    node_id: g.name2id(split[3]),
-                    direction: Direction::parse(split[4]),
-                };
-                let mut overlap = Self::parse_overlap(split[5]);
-                if collapse_multi_edges {
-                    if let Some(connect) = g.connector(start, end) {
-                        if connect.overlap != overlap {
-                            warn!("Multiple links connecting {} and {} with different overlap sizes ({} and {})"
-                                    , g.v_str(start), g.v_str(end), overlap, connect.overlap)
+                let record = Self::parse_link(&split, line_num)?;
+                if g.resolve(&record.start).is_some() && g.resolve(&record.end).is_some() {
+                    g.add_link_record(&record, settings)?;
+                } else {
+                    postponed_links.push(record);
+                }
+            } else if line.starts_with("E\t") {
+                let split: Vec<&str> = line.trim().split('\t').collect();
+                match Self::parse_edge(&split, line_num)? {
+                    Some(record) => {
+                        if g.resolve(&record.start).is_some() && g.resolve(&record.end).is_some() {
+                            g.add_link_record(&record, settings)?;
+                        } else {
+                            postponed_links.push(record);
                         }
-                        continue;
                     }
+                    None => debug!(
+                        "Ignoring non-dovetail edge {} at line {}",
+                        split[1], line_num
+                    ),
                 }
-                let max_ovl = std::cmp::min(g.vertex_length(start), g.vertex_length(end)) - 1;
-                if overlap > max_ovl {
-                    assert!(
-                        normalize_overlaps,
-                        "Invalid (too long) overlap of size {} between {} and {}",
-                        overlap,
-                        g.v_str(start),
-                        g.v_str(end)
-                    );
-                    warn!(
-                        "Normalizing overlap between {} and {} ({} -> {})",
-                        g.v_str(start),
-                        g.v_str(end),
-                        overlap,
-                        max_ovl
+                if split[1] != "*" {
+                    let s1 = Self::parse_reference(&split, 2, line_num)?;
+                    let s2 = Self::parse_reference(&split, 3, line_num)?;
+                    edge_ends.insert(String::from(split[1]), (s1.0, s2.0, line_num));
+                }
+            } else if line.starts_with("G\t") {
+                let split: Vec<&str> = line.trim().split('\t').collect();
+                let record = Self::parse_gap(&split, line_num)?;
+                if record.name != "*" {
+                    edge_ends.insert(
+                        record.name.clone(),
+                        (record.start.0.clone(), record.end.0.clone(), line_num),
                     );
-                    overlap = max_ovl;
                 }
-                g.add_link(Link {
-                    start,
-                    end,
-                    overlap,
-                });
+                gaps.push(record);
+            } else if line.starts_with("J\t") {
+                let split: Vec<&str> = line.trim().split('\t').collect();
+                gaps.push(Self::parse_jump(&split, line_num)?);
+            } else if line.starts_with("O\t") || line.starts_with("U\t") {
+                let split: Vec<&str> = line.trim().split('\t').collect();
+                groups.push(Self::parse_group(&split, line_num)?);
+            } else if line.starts_with("P\t") {
+                let split: Vec<&str> = line.trim().split('\t').collect();
+                walks.push(Self::parse_path(&split, line_num)?);
+            } else if line.starts_with("W\t") {
+                let split: Vec<&str> = line.trim().split('\t').collect();
+                walks.push(Self::parse_walk(&split, line_num)?);
+            }
+        }
+
+        for record in &postponed_links {
+            g.add_link_record(record, settings)?;
+        }
+        for record in &gaps {
+            g.add_gap_record(record)?;
+        }
+        //non-dovetail edges are not checked otherwise
+        for (s1, s2, line_num) in edge_ends.values() {
+            for s in [s1, s2] {
+                if !g.name2ids.contains_key(s) {
+                    return Err(GfaError::new(
+                        *line_num,
+                        "E",
+                        s,
+                        GfaErrorKind::UnknownSegment,
+                    ));
+                }
+            }
+        }
+        let edge_ends = edge_ends
+            .into_iter()
+            .map(|(id, (s1, s2, _))| (id, (s1, s2)))
+            .collect();
+        let group_index: HashMap<&str, &GroupRecord> =
+            groups.iter().map(|r| (r.name.as_str(), r)).collect();
+        for record in &groups {
+            let node_ids =
+                g.resolve_group(&record.name, &group_index, &edge_ends, &mut HashSet::new())?;
+            g.node_groups.push(NodeGroup {
+                name: record.name.clone(),
+                ordered: record.ordered,
+                node_ids,
+            });
+        }
+        for record in &walks {
+            match g.resolve_walk(record, settings)? {
+                Some(path) => g.paths.push((record.name.clone(), path)),
+                None => warn!(
+                    "Skipping path {} (line {}) visiting some node multiple times",
+                    record.name, record.line_num
+                ),
             }
         }
         g.check_links();
-        g
+        g.compact();
+        Ok(g)
+    }
+
+    //Checks GFA records reporting all detected problems instead of stopping at the first one:
+    //malformed records, duplicate segments, links and gaps referring to unknown segments,
+    //overlaps longer than the joined segments and multi-links with conflicting overlaps.
+    //Also returns names of all segments (including malformed ones) for checking other inputs.
+    pub fn lint_gfa<R: BufRead>(mut reader: R) -> (Vec<GfaError>, HashSet<String>) {
+        let mut issues = Vec::new();
+        let mut names = HashSet::new();
+        let mut lengths: HashMap<String, usize> = HashMap::new();
+        let mut links = Vec::new();
+        let mut gaps = Vec::new();
+        let mut buf = String::new();
+        let mut line_num = 0;
+        loop {
+            buf.clear();
+            line_num += 1;
+            match reader.read_line(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    issues.push(GfaError::new(
+                        line_num,
+                        "",
+                        "",
+                        GfaErrorKind::Io(e.to_string()),
+                    ));
+                    break;
+                }
+            }
+            let line = buf.trim_end_matches(['\n', '\r']);
+            let split: Vec<&str> = line.split('\t').collect();
+            if split[0] == "S" && split.len() > 1 {
+                names.insert(String::from(split[1]));
+            }
+            let result = match split[0] {
+                "S" => Self::parse_segment(&split, line_num, false).map(|(node, _)| {
+                    if let Entry::Vacant(e) = lengths.entry(node.name.clone()) {
+                        e.insert(node.length);
+                    } else {
+                        issues.push(GfaError::new(
+                            line_num,
+                            "S",
+                            &node.name,
+                            GfaErrorKind::DuplicateSegment,
+                        ));
+                    }
+                }),
+                "L" => Self::parse_link(&split, line_num).map(|r| links.push(r)),
+                "E" => Self::parse_edge(&split, line_num).map(|r| links.extend(r)),
+                "G" => Self::parse_gap(&split, line_num).map(|r| gaps.push(r)),
+                "J" => Self::parse_jump(&split, line_num).map(|r| gaps.push(r)),
+                "O" | "U" => Self::parse_group(&split, line_num).map(|_| ()),
+                "P" => Self::parse_path(&split, line_num).map(|_| ()),
+                "W" => Self::parse_walk(&split, line_num).map(|_| ()),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                issues.push(e);
+            }
+        }
+
+        let oriented_key = |(name, d): &(String, Direction)| (name.clone(), *d);
+        let flipped_key = |(name, d): &(String, Direction)| (name.clone(), Direction::flip(*d));
+        //canonical link -> overlap of the first such link
+        let mut overlaps = HashMap::new();
+        for record in &links {
+            let err =
+                |field: &str, kind| GfaError::new(record.line_num, record.record, field, kind);
+            let mut known = true;
+            for (name, _) in [&record.start, &record.end] {
+                if !lengths.contains_key(name) {
+                    issues.push(err(name, GfaErrorKind::UnknownSegment));
+                    known = false;
+                }
+            }
+            let Some(overlap) = record.overlap else {
+                continue;
+            };
+            if !known {
+                continue;
+            }
+            let max_ovl = std::cmp::min(lengths[&record.start.0], lengths[&record.end.0]) - 1;
+            if overlap > max_ovl {
+                issues.push(err(&record.cigar, GfaErrorKind::OverlapTooLong(max_ovl)));
+            }
+            let key = std::cmp::min(
+                (oriented_key(&record.start), oriented_key(&record.end)),
+                (flipped_key(&record.end), flipped_key(&record.start)),
+            );
+            match overlaps.get(&key) {
+                None => {
+                    overlaps.insert(key, overlap);
+                }
+                Some(&prev) if prev != overlap => {
+                    issues.push(err(&record.cigar, GfaErrorKind::ConflictingOverlap(prev)));
+                }
+                _ => {}
+            }
+        }
+        for record in &gaps {
+            for (name, _) in [&record.start, &record.end] {
+                if !lengths.contains_key(name) {
+                    issues.push(GfaError::new(
+                        record.line_num,
+                        record.record,
+                        name,
+                        GfaErrorKind::UnknownSegment,
+                    ));
+                }
+            }
+        }
+        issues.sort_by_key(|e| e.line);
+        (issues, names)
     }
 
-    pub fn as_gfa(&self) -> String {
-        let mut gfa = String::new();
+    pub fn try_read(
+        graph_str: &str,
+        collapse_multi_edges: bool,
+        normalize_overlaps: bool,
+    ) -> Result<Graph, GfaError> {
+        Self::read_from(
+            graph_str.as_bytes(),
+            &GfaReadSettings {
+                collapse_multi_edges,
+                normalize_overlaps,
+                keep_sequences: false,
+            },
+        )
+    }
+
+    //panics on malformed input, see try_read
+    pub fn custom_read(
+        graph_str: &str,
+        collapse_multi_edges: bool,
+        normalize_overlaps: bool,
+    ) -> Graph {
+        Self::try_read(graph_str, collapse_multi_edges, normalize_overlaps)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
 
-        for n in self.all_nodes() {
-            gfa += &format!(
-                "S\t{}\t*\tLN:i:{}\tRC:i:{}\tll:f:{:.1}\n",
+    pub fn as_gfa(&self) -> String {
+        let mut gfa = Vec::new();
+        //writing to memory can't fail
+        self.write_gfa(&mut gfa, |_| Vec::new()).unwrap();
+        String::from_utf8(gfa).unwrap()
+    }
+
+    //Writes segments (with sequences if stored), links, gaps (as J records) and paths.
+    //Extra tags for every segment are provided by node_tags.
+    pub fn write_gfa<W: Write>(
+        &self,
+        output: &mut W,
+        node_tags: impl Fn(usize) -> Vec<String>,
+    ) -> io::Result<()> {
+        for (node_id, n) in self.all_nodes().enumerate() {
+            write!(
+                output,
+                "S\t{}\t{}\tLN:i:{}\tRC:i:{}\tll:f:{:.1}",
                 n.name,
+                self.sequence(node_id).unwrap_or("*"),
                 n.length,
                 (n.coverage * n.length as f64).round() as u64,
                 n.coverage
-            );
+            )?;
+            for tag in node_tags(node_id) {
+                write!(output, "\t{tag}")?;
+            }
+            writeln!(output)?;
         }
 
         for l in self.all_links() {
-            gfa += &format!(
-                "L\t{}\t{}\t{}\t{}\t{}M\n",
+            write!(
+                output,
+                "L\t{}\t{}\t{}\t{}\t{}",
                 self.node(l.start.node_id).name,
                 Direction::str(l.start.direction),
                 self.node(l.end.node_id).name,
                 Direction::str(l.end.direction),
-                l.overlap
-            );
+                match self.overlap_known(&l) {
+                    true => format!("{}M", l.overlap),
+                    false => String::from("*"),
+                }
+            )?;
+            if let Some(support) = self.link_support(&l) {
+                write!(output, "\tRC:i:{support}")?;
+            }
+            writeln!(output)?;
         }
 
-        gfa
+        for gap in &self.gaps {
+            writeln!(output, "{}", self.gap_gfa_line(gap))?;
+        }
+
+        for (name, path) in &self.paths {
+            writeln!(output, "{}", path.gfa_line(self, name))?;
+        }
+        Ok(())
+    }
+
+    pub fn gap_gfa_line(&self, gap: &GapInfo) -> String {
+        format!(
+            "J\t{}\t{}\t{}\t{}\t{}",
+            self.name(gap.start.node_id),
+            Direction::str(gap.start.direction),
+            self.name(gap.end.node_id),
+            Direction::str(gap.end.direction),
+            if self.gap_size_known(gap) {
+                gap.gap_size.to_string()
+            } else {
+                String::from("*")
+            }
+        )
     }
 
     pub fn read(graph_str: &str) -> Self {
@@ -453,14 +1599,37 @@ impl Graph {
     //    Vertex {node_id, direction}
     //}
 
-    fn rc(links: &[Link]) -> Vec<Link> {
-        links.iter().map(|x| x.rc()).collect()
-    }
-
     pub fn node(&self, node_id: usize) -> &Node {
         &self.nodes[node_id]
     }
 
+    pub fn set_coverage(&mut self, node_id: usize, coverage: f64) {
+        self.nodes[node_id].coverage = coverage;
+    }
+
+    pub fn sequence(&self, node_id: usize) -> Option<&str> {
+        self.sequences[node_id].as_deref()
+    }
+
+    //for operations requiring the sequence of the segment, which wasn't provided (or kept)
+    pub fn missing_sequence_error(&self, node_id: usize) -> GfaError {
+        GfaError::new(0, "S", self.name(node_id), GfaErrorKind::MissingSequence)
+    }
+
+    pub fn set_sequence(&mut self, node_id: usize, seq: String) {
+        assert!(seq.len() == self.node_length(node_id));
+        self.sequences[node_id] = Some(seq);
+    }
+
+    //reverse-complemented for reverse vertices
+    pub fn vertex_sequence(&self, v: Vertex) -> Option<String> {
+        let seq = self.sequence(v.node_id)?;
+        match v.direction {
+            Direction::FORWARD => Some(String::from(seq)),
+            Direction::REVERSE => Some(reverse_complement(seq)),
+        }
+    }
+
     pub fn node_length(&self, node_id: usize) -> usize {
         self.node(node_id).length
     }
@@ -477,32 +1646,36 @@ impl Graph {
         &self.node(node_id).name
     }
 
-    //TODO switch to iterators when learn enough Rust :)
+    pub fn outgoing_neighbors(&self, v: Vertex) -> &[Neighbor] {
+        self.adjacency.neighbors(v)
+    }
+
     pub fn outgoing_edge_cnt(&self, v: Vertex) -> usize {
-        match v.direction {
-            Direction::FORWARD => self.outgoing_links[v.node_id].len(),
-            Direction::REVERSE => self.incoming_links[v.node_id].len(),
-        }
+        self.outgoing_neighbors(v).len()
+    }
+
+    pub fn outgoing_edge_iter(&self, v: Vertex) -> impl Iterator<Item = Link> + '_ {
+        self.outgoing_neighbors(v).iter().map(move |n| Link {
+            start: v,
+            end: n.end,
+            overlap: n.overlap,
+        })
     }
 
-    //TODO switch to iterators when learn enough Rust :)
     pub fn outgoing_edges(&self, v: Vertex) -> Vec<Link> {
-        match v.direction {
-            Direction::FORWARD => self.outgoing_links[v.node_id].clone(),
-            Direction::REVERSE => Self::rc(&self.incoming_links[v.node_id]),
-        }
+        self.outgoing_edge_iter(v).collect()
     }
 
     pub fn incoming_edge_cnt(&self, v: Vertex) -> usize {
         self.outgoing_edge_cnt(v.rc())
     }
 
-    //TODO switch to iterators when learn enough Rust :)
+    pub fn incoming_edge_iter(&self, v: Vertex) -> impl Iterator<Item = Link> + '_ {
+        self.outgoing_edge_iter(v.rc()).map(|l| l.rc())
+    }
+
     pub fn incoming_edges(&self, v: Vertex) -> Vec<Link> {
-        match v.direction {
-            Direction::FORWARD => self.incoming_links[v.node_id].clone(),
-            Direction::REVERSE => Self::rc(&self.outgoing_links[v.node_id]),
-        }
+        self.incoming_edge_iter(v).collect()
     }
 
     pub fn name2id(&self, name: &str) -> usize {
@@ -512,9 +1685,22 @@ impl Graph {
         }
     }
 
-    //TODO iterate over references
+    pub fn try_name2id(&self, name: &str) -> Option<usize> {
+        self.name2ids.get(name).copied()
+    }
+
+    //every link is reported once, in the orientation where start <= end
+    //(or end < start if both orientations are 'incoming' for the forward vertex)
     pub fn all_links(&self) -> impl Iterator<Item = Link> + '_ {
-        AllLinkIter::new(self)
+        (0..self.node_cnt()).flat_map(move |node_id| self.node_links(node_id))
+    }
+
+    //links reported by all_links for the node
+    fn node_links(&self, node_id: usize) -> impl Iterator<Item = Link> + '_ {
+        let v = Vertex::forward(node_id);
+        self.incoming_edge_iter(v)
+            .filter(|l| l.end < l.start)
+            .chain(self.outgoing_edge_iter(v).filter(|l| l.start <= l.end))
     }
 
     pub fn all_nodes(&self) -> impl Iterator<Item = &Node> + '_ {
@@ -538,7 +1724,7 @@ impl Graph {
     //note that the graph supports multi-edges,
     // if they are present returns only the first one
     pub fn connector(&self, v: Vertex, w: Vertex) -> Option<Link> {
-        self.outgoing_edges(v).into_iter().find(|&l| l.end == w)
+        self.outgoing_edge_iter(v).find(|&l| l.end == w)
     }
 
     pub fn v_str_format(&self, v: Vertex, gaf: bool) -> String {
@@ -556,6 +1742,504 @@ impl Graph {
     pub fn l_str(&self, l: Link) -> String {
         format!("{}->{}", self.v_str(l.start), self.v_str(l.end))
     }
+
+    //Removes single copy of the link (if present), returns true if the link was found
+    pub fn remove_link(&mut self, l: &Link) -> bool {
+        let neighbor = Neighbor {
+            end: l.end,
+            overlap: l.overlap,
+        };
+        let lists = self.adjacency.lists();
+        let start_list = &mut lists[Adjacency::idx(l.start)];
+        match start_list.iter().position(|n| n == &neighbor) {
+            None => return false,
+            Some(pos) => start_list.remove(pos),
+        };
+        if *l != l.rc() {
+            let rc_neighbor = Neighbor {
+                end: l.start.rc(),
+                overlap: l.overlap,
+            };
+            let end_list = &mut lists[Adjacency::idx(l.end.rc())];
+            let pos = end_list.iter().position(|n| n == &rc_neighbor).unwrap();
+            end_list.remove(pos);
+        }
+        if self.connector(l.start, l.end).is_none() {
+            let key = Self::link_key(l.start, l.end);
+            self.unknown_overlaps.remove(&key);
+            self.link_support.remove(&key);
+        }
+        true
+    }
+
+    //Adds the gap between the specified vertices of another graph, keeping its attributes
+    fn copy_gap(&self, gap: &GapInfo, dst: &mut Graph, start: Vertex, end: Vertex) {
+        let new_gap = GapInfo {
+            start,
+            end,
+            ..gap.clone()
+        };
+        if self.gap_size_known(gap) {
+            dst.add_gap(new_gap);
+        } else {
+            dst.add_gap_unknown_size(new_gap);
+        }
+    }
+
+    //Transfers link attributes to the corresponding link of another graph
+    fn copy_link_attrs(&self, l: &Link, dst: &mut Graph, dst_l: &Link) {
+        if !self.overlap_known(l) {
+            dst.unknown_overlaps
+                .insert(Self::link_key(dst_l.start, dst_l.end));
+        }
+        if let Some(support) = self.link_support(l) {
+            dst.set_link_support(dst_l, support);
+        }
+    }
+
+    //Subgraph induced by the specified nodes, node_ids[i] becomes node i of the subgraph.
+    //Groups keep only the specified nodes (empty ones are dropped),
+    //only paths going through the specified nodes exclusively are kept.
+    pub fn induced_subgraph(&self, node_ids: &[usize]) -> Graph {
+        let mut g = Graph::new();
+        let mut id_map = HashMap::new();
+        for &node_id in node_ids {
+            let new_id = g.add_node(self.nodes[node_id].clone());
+            g.sequences[new_id] = self.sequences[node_id].clone();
+            id_map.insert(node_id, new_id);
+        }
+        let map = |v: Vertex| {
+            Some(Vertex {
+                node_id: *id_map.get(&v.node_id)?,
+                direction: v.direction,
+            })
+        };
+
+        let mut gap_ids = BTreeSet::new();
+        for &node_id in node_ids {
+            for l in self.node_links(node_id) {
+                if let (Some(start), Some(end)) = (map(l.start), map(l.end)) {
+                    let new_l = Link {
+                        start,
+                        end,
+                        overlap: l.overlap,
+                    };
+                    self.copy_link_attrs(&l, &mut g, &new_l);
+                    g.add_link(new_l);
+                }
+            }
+            for v in [Vertex::forward(node_id), Vertex::reverse(node_id)] {
+                if let Some(ids) = self.gap_index.get(&v) {
+                    gap_ids.extend(ids.iter().map(|&(gap_id, _)| gap_id));
+                }
+            }
+        }
+        for gap in gap_ids.into_iter().map(|gap_id| &self.gaps[gap_id]) {
+            if let (Some(start), Some(end)) = (map(gap.start), map(gap.end)) {
+                self.copy_gap(gap, &mut g, start, end);
+            }
+        }
+        for group in &self.node_groups {
+            let group_node_ids: Vec<usize> = group
+                .node_ids
+                .iter()
+                .filter_map(|n| id_map.get(n).copied())
+                .collect();
+            if !group_node_ids.is_empty() {
+                g.node_groups.push(NodeGroup {
+                    node_ids: group_node_ids,
+                    ..group.clone()
+                });
+            }
+        }
+        for (name, path) in &self.paths {
+            if let Some(path) = path.map_vertices(map) {
+                g.paths.push((name.clone(), path));
+            }
+        }
+        g.check_links();
+        g.compact();
+        g
+    }
+
+    //Removes the nodes for which the predicate is false, together with all incident links and gaps.
+    //Node ids are reassigned (order of remaining nodes is preserved), mapping from the old ids is returned.
+    //Groups lose the removed nodes, paths going through them are dropped.
+    pub fn retain_nodes(&mut self, keep: impl Fn(usize) -> bool) -> Vec<Option<usize>> {
+        let kept: Vec<usize> = (0..self.node_cnt()).filter(|&n| keep(n)).collect();
+        let mut id_map = vec![None; self.node_cnt()];
+        for (new_id, &node_id) in kept.iter().enumerate() {
+            id_map[node_id] = Some(new_id);
+        }
+        *self = self.induced_subgraph(&kept);
+        id_map
+    }
+
+    //Splits the graph into subgraphs induced by nodes with the same part id (part_ids[node_id]).
+    //Part ids are expected to be in 0..part_cnt, for every part returns the subgraph and
+    //the ids of its nodes in this graph (see induced_subgraph).
+    pub fn split_by(&self, part_ids: &[usize]) -> Vec<(Graph, Vec<usize>)> {
+        assert!(part_ids.len() == self.node_cnt());
+        let part_cnt = part_ids.iter().max().map_or(0, |&m| m + 1);
+        let mut part_nodes = vec![Vec::new(); part_cnt];
+        for (node_id, &part_id) in part_ids.iter().enumerate() {
+            part_nodes[part_id].push(node_id);
+        }
+        part_nodes
+            .into_iter()
+            .map(|node_ids| (self.induced_subgraph(&node_ids), node_ids))
+            .collect()
+    }
+
+    //Subgraphs corresponding to weakly connected components
+    //(in the order of graph_algos::components::component_ids)
+    pub fn split_components(&self) -> Vec<(Graph, Vec<usize>)> {
+        self.split_by(&crate::graph_algos::components::component_ids(self))
+    }
+
+    //Removes the nodes at once, see retain_nodes
+    pub fn remove_nodes(&mut self, node_ids: &[usize]) -> Vec<Option<usize>> {
+        let removed: HashSet<usize> = node_ids.iter().copied().collect();
+        self.retain_nodes(|n| !removed.contains(&n))
+    }
+
+    //Rebuilds the whole graph (O(N + E)), so removing nodes one by one is quadratic,
+    //use remove_nodes or retain_nodes to remove several nodes
+    pub fn remove_node(&mut self, node_id: usize) -> Vec<Option<usize>> {
+        self.remove_nodes(&[node_id])
+    }
+
+    //next vertex of the unbranching run
+    fn unbranching_next(&self, v: Vertex) -> Option<Vertex> {
+        let l = only_or_none(self.outgoing_edge_iter(v))?;
+        if l.end.node_id != v.node_id && self.incoming_edge_cnt(l.end) == 1 {
+            Some(l.end)
+        } else {
+            None
+        }
+    }
+
+    //Merges maximal unbranching runs of nodes into new nodes.
+    //Returns compacted graph and paths in the original graph, corresponding to the new nodes
+    //(in their forward orientation).
+    //Unitigs consisting of a single node keep its name, longer ones are named <first>_<last>.
+    //Length of the unitig is the length of the path, coverage is the length-weighted mean.
+    //Links, their attributes and gaps between unitig ends are preserved, groups and paths are not.
+    pub fn compact_unitigs(&self) -> (Graph, Vec<Path>) {
+        let mut unitigs: Vec<Path> = Vec::new();
+        let mut used = vec![false; self.node_cnt()];
+        for node_id in 0..self.node_cnt() {
+            if used[node_id] {
+                continue;
+            }
+            used[node_id] = true;
+            let mut path = Path::new(Vertex::forward(node_id));
+            for step in 0..2 {
+                while let Some(w) = self.unbranching_next(path.end()) {
+                    if used[w.node_id] {
+                        //unbranching cycle
+                        break;
+                    }
+                    used[w.node_id] = true;
+                    path.append(self.connector(path.end(), w).unwrap());
+                }
+                if step == 0 {
+                    path = path.reverse_complement();
+                }
+            }
+            //back to forward orientation of the initial node
+            unitigs.push(path.reverse_complement());
+        }
+
+        //original vertex to (unitig vertex, position of vertex within oriented unitig)
+        let mut vertex_map: HashMap<Vertex, (Vertex, usize)> = HashMap::new();
+        let mut g = Graph::new();
+        for (unitig_id, path) in unitigs.iter().enumerate() {
+            let first = self.name(path.start().node_id);
+            let mut name = if path.len() == 1 {
+                String::from(first)
+            } else {
+                format!("{}_{}", first, self.name(path.end().node_id))
+            };
+            while g.name2ids.contains_key(&name)
+                || (path.len() > 1 && self.name2ids.contains_key(&name))
+            {
+                name += "_utg";
+            }
+            let length = path.total_length(self);
+            let node_len_sum: usize = path.vertices().iter().map(|&v| self.vertex_length(v)).sum();
+            let coverage = path
+                .vertices()
+                .iter()
+                .map(|v| self.nodes[v.node_id].coverage * self.vertex_length(*v) as f64)
+                .sum::<f64>()
+                / node_len_sum as f64;
+            let new_id = g.add_node(Node {
+                name,
+                length,
+                coverage,
+            });
+            assert!(new_id == unitig_id);
+            g.sequences[new_id] = path.sequence(self);
+            for (i, &v) in path.vertices().iter().enumerate() {
+                vertex_map.insert(v, (Vertex::forward(new_id), i));
+                vertex_map.insert(v.rc(), (Vertex::reverse(new_id), path.len() - 1 - i));
+            }
+        }
+
+        //maps vertex (expected to be at the specified end of its unitig) to the unitig vertex
+        let map_end = |v: Vertex, at_start: bool| {
+            let (u, pos) = vertex_map[&v];
+            let unitig_len = unitigs[u.node_id].len();
+            if (at_start && pos == 0) || (!at_start && pos == unitig_len - 1) {
+                Some(u)
+            } else {
+                None
+            }
+        };
+
+        for l in self.all_links() {
+            let (u_start, start_pos) = vertex_map[&l.start];
+            let (u_end, end_pos) = vertex_map[&l.end];
+            if u_start == u_end && end_pos == start_pos + 1 {
+                //internal link of the unitig
+                continue;
+            }
+            let new_l = Link {
+                start: map_end(l.start, false).unwrap(),
+                end: map_end(l.end, true).unwrap(),
+                overlap: l.overlap,
+            };
+            self.copy_link_attrs(&l, &mut g, &new_l);
+            g.add_link(new_l);
+        }
+        for gap in &self.gaps {
+            match (map_end(gap.start, false), map_end(gap.end, true)) {
+                (Some(start), Some(end)) => self.copy_gap(gap, &mut g, start, end),
+                _ => debug!(
+                    "Dropping gap between {} and {} not connecting unitig ends",
+                    self.v_str(gap.start),
+                    self.v_str(gap.end)
+                ),
+            }
+        }
+        g.check_links();
+        g.compact();
+        (g, unitigs)
+    }
+
+    //Expands path in the graph compacted by compact_unitigs into the path in this (original) graph.
+    //Fails if unitigs don't correspond to the path nodes or links between them are missing from this graph.
+    pub fn expand_unitig_path(&self, path: &Path, unitigs: &[Path]) -> Result<Path, GfaError> {
+        let oriented = |v: Vertex| {
+            let unitig = unitigs.get(v.node_id).ok_or_else(|| {
+                GfaError::new(0, "P", &v.node_id.to_string(), GfaErrorKind::UnknownSegment)
+            })?;
+            Ok(match v.direction {
+                Direction::FORWARD => unitig.clone(),
+                Direction::REVERSE => unitig.clone().reverse_complement(),
+            })
+        };
+        let mut expanded = oriented(path.start())?;
+        for (l, &v) in path.l_storage.iter().zip(path.v_storage.iter().skip(1)) {
+            let next = oriented(v)?;
+            match l {
+                GeneralizedLink::LINK(l) => {
+                    let orig_l = self
+                        .outgoing_edge_iter(expanded.end())
+                        .find(|x| x.end == next.start() && x.overlap == l.overlap)
+                        .ok_or_else(|| {
+                            GfaError::new(
+                                0,
+                                "L",
+                                &format!(
+                                    "{},{}",
+                                    self.v_str(expanded.end()),
+                                    self.v_str(next.start())
+                                ),
+                                GfaErrorKind::MissingLink,
+                            )
+                        })?;
+                    expanded.append(orig_l);
+                }
+                GeneralizedLink::GAP(gap) => {
+                    expanded.append_general(GeneralizedLink::GAP(GapInfo {
+                        start: expanded.end(),
+                        end: next.start(),
+                        ..gap.clone()
+                    }))
+                }
+            }
+            expanded.extend(next);
+        }
+        Ok(expanded)
+    }
+
+    //Splits nodes at the specified positions (0-based offsets within forward node sequence).
+    //Pieces of a split node are named <name>_1, <name>_2, ... and connected by 0-overlap links,
+    //external links, gaps, groups and paths are redirected to the pieces.
+    //Positions within link overlaps (closer to the node start/end than the longest incoming/outgoing
+    //overlap) can't be split, since the overlap would span several pieces; such positions are skipped.
+    //Returns new graph and (forward-ordered) ids of the pieces for every original node.
+    pub fn split_nodes(&self, splits: &HashMap<usize, Vec<usize>>) -> (Graph, Vec<Vec<usize>>) {
+        let mut g = Graph::new();
+        let mut pieces = Vec::with_capacity(self.node_cnt());
+        for (node_id, n) in self.all_nodes().enumerate() {
+            let max_overlap = |links: Vec<Link>| links.iter().map(|l| l.overlap).max().unwrap_or(0);
+            let in_overlap = max_overlap(self.incoming_edges(Vertex::forward(node_id)));
+            let out_overlap = max_overlap(self.outgoing_edges(Vertex::forward(node_id)));
+            let mut positions: Vec<usize> = splits
+                .get(&node_id)
+                .map(|p| {
+                    p.iter()
+                        .copied()
+                        .filter(|&p| p > 0 && p < n.length)
+                        .filter(|&p| {
+                            let ok = p >= in_overlap && p + out_overlap <= n.length;
+                            if !ok {
+                                warn!(
+                                    "Not splitting node {} at position {}, which is within a link overlap",
+                                    n.name, p
+                                );
+                            }
+                            ok
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            positions.sort();
+            positions.dedup();
+            if positions.is_empty() {
+                let new_id = g.add_node(n.clone());
+                g.sequences[new_id] = self.sequences[node_id].clone();
+                pieces.push(vec![new_id]);
+                continue;
+            }
+            let bounds: Vec<usize> = std::iter::once(0)
+                .chain(positions)
+                .chain(std::iter::once(n.length))
+                .collect();
+            let mut node_pieces = Vec::new();
+            for (i, w) in bounds.windows(2).enumerate() {
+                let mut name = format!("{}_{}", n.name, i + 1);
+                while g.name2ids.contains_key(&name) || self.name2ids.contains_key(&name) {
+                    name += "_split";
+                }
+                let new_id = g.add_node(Node {
+                    name,
+                    length: w[1] - w[0],
+                    coverage: n.coverage,
+                });
+                g.sequences[new_id] = self.sequences[node_id]
+                    .as_ref()
+                    .and_then(|seq| seq.get(w[0]..w[1]))
+                    .map(String::from);
+                node_pieces.push(new_id);
+            }
+            for w in node_pieces.windows(2) {
+                g.add_link(Link {
+                    start: Vertex::forward(w[0]),
+                    end: Vertex::forward(w[1]),
+                    overlap: 0,
+                });
+            }
+            pieces.push(node_pieces);
+        }
+
+        //vertex of the piece through which the path enters/exits the original vertex
+        let entry = |v: Vertex| match v.direction {
+            Direction::FORWARD => Vertex::forward(pieces[v.node_id][0]),
+            Direction::REVERSE => Vertex::reverse(*pieces[v.node_id].last().unwrap()),
+        };
+        let exit = |v: Vertex| entry(v.rc()).rc();
+        let oriented = |v: Vertex| {
+            let mut piece_vertices: Vec<Vertex> = pieces[v.node_id]
+                .iter()
+                .map(|&n| Vertex {
+                    node_id: n,
+                    direction: v.direction,
+                })
+                .collect();
+            if v.direction == Direction::REVERSE {
+                piece_vertices.reverse();
+            }
+            let mut path = Path::new(piece_vertices[0]);
+            for w in piece_vertices.windows(2) {
+                path.append(Link {
+                    start: w[0],
+                    end: w[1],
+                    overlap: 0,
+                });
+            }
+            path
+        };
+
+        for l in self.all_links() {
+            let new_l = Link {
+                start: exit(l.start),
+                end: entry(l.end),
+                overlap: l.overlap,
+            };
+            self.copy_link_attrs(&l, &mut g, &new_l);
+            g.add_link(new_l);
+        }
+        for gap in &self.gaps {
+            self.copy_gap(gap, &mut g, exit(gap.start), entry(gap.end));
+        }
+        for group in &self.node_groups {
+            g.node_groups.push(NodeGroup {
+                node_ids: group
+                    .node_ids
+                    .iter()
+                    .flat_map(|&n| pieces[n].iter().copied())
+                    .collect(),
+                ..group.clone()
+            });
+        }
+        for (name, path) in &self.paths {
+            let mut new_path = oriented(path.start());
+            for (l, &v) in path.l_storage.iter().zip(path.v_storage.iter().skip(1)) {
+                match l {
+                    GeneralizedLink::LINK(l) => new_path.append(Link {
+                        start: exit(l.start),
+                        end: entry(l.end),
+                        overlap: l.overlap,
+                    }),
+                    GeneralizedLink::GAP(gap) => {
+                        new_path.append_general(GeneralizedLink::GAP(GapInfo {
+                            start: exit(gap.start),
+                            end: entry(gap.end),
+                            ..gap.clone()
+                        }))
+                    }
+                }
+                new_path.extend(oriented(v));
+            }
+            g.paths.push((name.clone(), new_path));
+        }
+        g.check_links();
+        g.compact();
+        (g, pieces)
+    }
+}
+
+fn complement(c: char) -> char {
+    match c {
+        'A' => 'T',
+        'C' => 'G',
+        'G' => 'C',
+        'T' => 'A',
+        'a' => 't',
+        'c' => 'g',
+        'g' => 'c',
+        't' => 'a',
+        'n' => 'n',
+        _ => 'N',
+    }
+}
+
+pub fn reverse_complement(seq: &str) -> String {
+    seq.chars().rev().map(complement).collect()
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -653,6 +2337,33 @@ impl Path {
         &self.v_storage
     }
 
+    //Path with vertices substituted by the mapping (e.g. between a graph and its subgraph),
+    //None if some vertex can't be mapped
+    pub fn map_vertices(&self, map: impl Fn(Vertex) -> Option<Vertex>) -> Option<Path> {
+        let map_link = |l: &GeneralizedLink| -> Option<GeneralizedLink> {
+            Some(match l {
+                GeneralizedLink::LINK(l) => GeneralizedLink::LINK(Link {
+                    start: map(l.start)?,
+                    end: map(l.end)?,
+                    overlap: l.overlap,
+                }),
+                GeneralizedLink::GAP(g) => GeneralizedLink::GAP(GapInfo {
+                    start: map(g.start)?,
+                    end: map(g.end)?,
+                    ..g.clone()
+                }),
+            })
+        };
+        Some(Path {
+            v_storage: self
+                .v_storage
+                .iter()
+                .map(|&v| map(v))
+                .collect::<Option<_>>()?,
+            l_storage: self.l_storage.iter().map(map_link).collect::<Option<_>>()?,
+        })
+    }
+
     pub fn start(&self) -> Vertex {
         self.v_storage[0]
     }
@@ -786,14 +2497,73 @@ impl Path {
         ans
     }
 
+    //P record, where segments joined by gaps are separated by ';' (should be backed by J records)
+    pub fn gfa_line(&self, g: &Graph, name: &str) -> String {
+        let mut segments = g.v_str(self.start());
+        for (l, &v) in self.l_storage.iter().zip(self.v_storage.iter().skip(1)) {
+            segments.push(match l {
+                GeneralizedLink::LINK(_) => ',',
+                GeneralizedLink::GAP(_) => ';',
+            });
+            segments += &g.v_str(v);
+        }
+        format!("P\t{name}\t{segments}\t*")
+    }
+
+    //unknown link overlaps are treated as zero (see has_unknown_overlaps)
+    fn known_overlap(g: &Graph, l: &GeneralizedLink) -> i64 {
+        match l {
+            GeneralizedLink::LINK(link) if !g.overlap_known(link) => 0,
+            _ => l.overlap(),
+        }
+    }
+
+    //Copy of the path with gaps of unknown size (see Graph::gap_size_known) set to gap_size
+    pub fn fill_unknown_gap_sizes(&self, g: &Graph, gap_size: i64) -> Path {
+        let mut path = self.clone();
+        for l in path.l_storage.iter_mut() {
+            if let GeneralizedLink::GAP(gap) = l {
+                if !g.gap_size_known(gap) {
+                    gap.gap_size = gap_size;
+                }
+            }
+        }
+        path
+    }
+
+    pub fn has_unknown_overlaps(&self, g: &Graph) -> bool {
+        self.l_storage.iter().any(|l| match l {
+            GeneralizedLink::LINK(link) => !g.overlap_known(link),
+            GeneralizedLink::GAP(_) => false,
+        })
+    }
+
+    //upper bound of the length if the path has unknown overlaps
     pub fn total_length(&self, g: &Graph) -> usize {
         let mut tot_length = g.vertex_length(self.v_storage[0]) as i64;
         for l in &self.l_storage {
-            tot_length += g.vertex_length(l.end()) as i64 - l.overlap();
+            tot_length += g.vertex_length(l.end()) as i64 - Self::known_overlap(g, l);
         }
         tot_length as usize
     }
 
+    //overlaps are trimmed from the start of the next vertex, gaps are filled with Ns
+    //None if sequence of some node is not available
+    pub fn sequence(&self, g: &Graph) -> Option<String> {
+        let mut seq = g.vertex_sequence(self.v_storage[0])?;
+        for l in &self.l_storage {
+            let next = g.vertex_sequence(l.end())?;
+            let ovl = Self::known_overlap(g, l);
+            if ovl < 0 {
+                seq.extend(std::iter::repeat_n('N', (-ovl) as usize));
+                seq.push_str(&next);
+            } else {
+                seq.push_str(&next[std::cmp::min(ovl as usize, next.len())..]);
+            }
+        }
+        Some(seq)
+    }
+
     pub fn check_subpath(&self, other: &Path, start_pos: usize) -> bool {
         if self.len() < start_pos + other.len() {
             return false;
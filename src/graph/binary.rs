@@ -0,0 +1,444 @@
+//Versioned binary representation of the graph, allowing to avoid re-parsing of large GFA files.
+//Layout: magic, format version, flags, optional description of the source file,
+//followed by sections (4-byte tag, payload size, payload) terminated by the END section.
+//Unknown sections are skipped by the reader.
+use super::*;
+use std::io::Read;
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &[u8; 4] = b"RKG\0";
+pub const BINARY_VERSION: u32 = 2;
+
+const NODES_TAG: &[u8; 4] = b"NODE";
+const SEQUENCES_TAG: &[u8; 4] = b"SEQS";
+const LINKS_TAG: &[u8; 4] = b"LINK";
+const GAPS_TAG: &[u8; 4] = b"GAPS";
+const GROUPS_TAG: &[u8; 4] = b"GRPS";
+const PATHS_TAG: &[u8; 4] = b"PATH";
+const END_TAG: &[u8; 4] = b"END\0";
+
+const FLAG_SEQUENCES: u32 = 1;
+const FLAG_SOURCE: u32 = 2;
+
+//Size and modification time of the file the graph was parsed from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BinarySource {
+    pub size: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+}
+
+impl BinarySource {
+    pub fn from_file(path: &std::path::Path) -> io::Result<BinarySource> {
+        let metadata = std::fs::metadata(path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(BinarySource {
+            size: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryHeader {
+    pub version: u32,
+    pub has_sequences: bool,
+    pub source: Option<BinarySource>,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, x: u8) {
+        self.buf.push(x);
+    }
+
+    fn u64(&mut self, x: u64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn usize(&mut self, x: usize) {
+        self.u64(x as u64);
+    }
+
+    fn i64(&mut self, x: i64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn f64(&mut self, x: f64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn vertex(&mut self, v: Vertex) {
+        self.usize(v.node_id);
+        self.u8(match v.direction {
+            Direction::FORWARD => 0,
+            Direction::REVERSE => 1,
+        });
+    }
+
+    fn gap(&mut self, gap: &GapInfo) {
+        self.vertex(gap.start);
+        self.vertex(gap.end);
+        self.i64(gap.gap_size);
+        self.str(&gap.info);
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(invalid_data("unexpected end of section"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> io::Result<usize> {
+        usize::try_from(self.u64()?).map_err(|_| invalid_data("value out of range"))
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.usize()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid_data("invalid string"))
+    }
+
+    fn vertex(&mut self, node_cnt: usize) -> io::Result<Vertex> {
+        let node_id = self.usize()?;
+        if node_id >= node_cnt {
+            return Err(invalid_data("invalid node id"));
+        }
+        match self.u8()? {
+            0 => Ok(Vertex::forward(node_id)),
+            1 => Ok(Vertex::reverse(node_id)),
+            _ => Err(invalid_data("invalid direction")),
+        }
+    }
+
+    fn gap(&mut self, node_cnt: usize) -> io::Result<GapInfo> {
+        Ok(GapInfo {
+            start: self.vertex(node_cnt)?,
+            end: self.vertex(node_cnt)?,
+            gap_size: self.i64()?,
+            info: self.string()?,
+        })
+    }
+}
+
+fn write_section<W: Write>(output: &mut W, tag: &[u8; 4], payload: &[u8]) -> io::Result<()> {
+    output.write_all(tag)?;
+    output.write_all(&(payload.len() as u64).to_le_bytes())?;
+    output.write_all(payload)
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+impl Graph {
+    //Sequences are only stored if with_sequences is set
+    pub fn save_binary<W: Write>(
+        &self,
+        output: &mut W,
+        with_sequences: bool,
+        source: Option<&BinarySource>,
+    ) -> io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_all(&BINARY_VERSION.to_le_bytes())?;
+        let mut flags = 0;
+        if with_sequences {
+            flags |= FLAG_SEQUENCES;
+        }
+        if source.is_some() {
+            flags |= FLAG_SOURCE;
+        }
+        output.write_all(&flags.to_le_bytes())?;
+        if let Some(source) = source {
+            output.write_all(&source.size.to_le_bytes())?;
+            output.write_all(&source.mtime_secs.to_le_bytes())?;
+            output.write_all(&source.mtime_nanos.to_le_bytes())?;
+        }
+
+        let mut e = Encoder::default();
+        e.usize(self.node_cnt());
+        for n in &self.nodes {
+            e.str(&n.name);
+            e.usize(n.length);
+            e.f64(n.coverage);
+        }
+        write_section(output, NODES_TAG, &e.buf)?;
+
+        if with_sequences {
+            let mut e = Encoder::default();
+            for seq in &self.sequences {
+                match seq {
+                    None => e.u8(0),
+                    Some(seq) => {
+                        e.u8(1);
+                        e.str(seq);
+                    }
+                }
+            }
+            write_section(output, SEQUENCES_TAG, &e.buf)?;
+        }
+
+        let mut e = Encoder::default();
+        e.usize(self.link_cnt());
+        for l in self.all_links() {
+            e.vertex(l.start);
+            e.vertex(l.end);
+            e.usize(l.overlap);
+            e.u8(self.overlap_known(&l) as u8);
+            match self.link_support(&l) {
+                None => e.u8(0),
+                Some(support) => {
+                    e.u8(1);
+                    e.usize(support);
+                }
+            }
+        }
+        write_section(output, LINKS_TAG, &e.buf)?;
+
+        let mut e = Encoder::default();
+        e.usize(self.gaps.len());
+        for gap in &self.gaps {
+            e.gap(gap);
+            e.u8(self.gap_size_known(gap) as u8);
+        }
+        write_section(output, GAPS_TAG, &e.buf)?;
+
+        let mut e = Encoder::default();
+        e.usize(self.node_groups.len());
+        for group in &self.node_groups {
+            e.str(&group.name);
+            e.u8(group.ordered as u8);
+            e.usize(group.node_ids.len());
+            for &node_id in &group.node_ids {
+                e.usize(node_id);
+            }
+        }
+        write_section(output, GROUPS_TAG, &e.buf)?;
+
+        let mut e = Encoder::default();
+        e.usize(self.paths.len());
+        for (name, path) in &self.paths {
+            e.str(name);
+            e.vertex(path.start());
+            e.usize(path.l_storage.len());
+            for l in &path.l_storage {
+                match l {
+                    GeneralizedLink::LINK(l) => {
+                        e.u8(0);
+                        e.vertex(l.end);
+                        e.usize(l.overlap);
+                    }
+                    GeneralizedLink::GAP(gap) => {
+                        e.u8(1);
+                        e.gap(gap);
+                    }
+                }
+            }
+        }
+        write_section(output, PATHS_TAG, &e.buf)?;
+
+        write_section(output, END_TAG, &[])
+    }
+
+    pub fn binary_header<R: Read>(input: &mut R) -> io::Result<BinaryHeader> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a rukki binary graph"));
+        }
+        let version = read_u32(input)?;
+        if version != BINARY_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported binary graph version {version} (expected {BINARY_VERSION})"
+            )));
+        }
+        let flags = read_u32(input)?;
+        let source = if flags & FLAG_SOURCE != 0 {
+            Some(BinarySource {
+                size: read_u64(input)?,
+                mtime_secs: read_u64(input)?,
+                mtime_nanos: read_u32(input)?,
+            })
+        } else {
+            None
+        };
+        Ok(BinaryHeader {
+            version,
+            has_sequences: flags & FLAG_SEQUENCES != 0,
+            source,
+        })
+    }
+
+    //Sequences are skipped unless keep_sequences is set
+    pub fn load_binary<R: Read>(input: &mut R, keep_sequences: bool) -> io::Result<Graph> {
+        Self::binary_header(input)?;
+        let mut g = Graph::new();
+        loop {
+            let mut tag = [0; 4];
+            input.read_exact(&mut tag)?;
+            let len = read_u64(input)?;
+            if &tag == END_TAG {
+                break;
+            }
+            if &tag == SEQUENCES_TAG && !keep_sequences {
+                io::copy(&mut input.take(len), &mut io::sink())?;
+                continue;
+            }
+            let mut payload = Vec::new();
+            input.take(len).read_to_end(&mut payload)?;
+            if payload.len() as u64 != len {
+                return Err(invalid_data("truncated section"));
+            }
+            let mut d = Decoder { buf: &payload };
+            match &tag {
+                NODES_TAG => {
+                    for _ in 0..d.usize()? {
+                        let name = d.string()?;
+                        if g.name2ids.contains_key(&name) {
+                            return Err(invalid_data(&format!("duplicate node name {name}")));
+                        }
+                        g.add_node(Node {
+                            name,
+                            length: d.usize()?,
+                            coverage: d.f64()?,
+                        });
+                    }
+                }
+                SEQUENCES_TAG => {
+                    for node_id in 0..g.node_cnt() {
+                        if d.u8()? == 1 {
+                            g.sequences[node_id] = Some(d.string()?);
+                        }
+                    }
+                }
+                LINKS_TAG => {
+                    let node_cnt = g.node_cnt();
+                    for _ in 0..d.usize()? {
+                        let l = Link {
+                            start: d.vertex(node_cnt)?,
+                            end: d.vertex(node_cnt)?,
+                            overlap: d.usize()?,
+                        };
+                        if d.u8()? == 0 {
+                            g.unknown_overlaps.insert(Self::link_key(l.start, l.end));
+                        }
+                        if d.u8()? == 1 {
+                            g.set_link_support(&l, d.usize()?);
+                        }
+                        g.add_link(l);
+                    }
+                }
+                GAPS_TAG => {
+                    let node_cnt = g.node_cnt();
+                    for _ in 0..d.usize()? {
+                        let gap = d.gap(node_cnt)?;
+                        if d.u8()? == 1 {
+                            g.add_gap(gap);
+                        } else {
+                            g.add_gap_unknown_size(gap);
+                        }
+                    }
+                }
+                GROUPS_TAG => {
+                    for _ in 0..d.usize()? {
+                        let name = d.string()?;
+                        let ordered = d.u8()? == 1;
+                        let mut node_ids = Vec::new();
+                        for _ in 0..d.usize()? {
+                            let node_id = d.usize()?;
+                            if node_id >= g.node_cnt() {
+                                return Err(invalid_data("invalid node id"));
+                            }
+                            node_ids.push(node_id);
+                        }
+                        g.node_groups.push(NodeGroup {
+                            name,
+                            ordered,
+                            node_ids,
+                        });
+                    }
+                }
+                PATHS_TAG => {
+                    let node_cnt = g.node_cnt();
+                    for _ in 0..d.usize()? {
+                        let name = d.string()?;
+                        let mut path = Path::new(d.vertex(node_cnt)?);
+                        for _ in 0..d.usize()? {
+                            let l = match d.u8()? {
+                                0 => GeneralizedLink::LINK(Link {
+                                    start: path.end(),
+                                    end: d.vertex(node_cnt)?,
+                                    overlap: d.usize()?,
+                                }),
+                                1 => GeneralizedLink::GAP(d.gap(node_cnt)?),
+                                _ => return Err(invalid_data("invalid path link type")),
+                            };
+                            if l.start() != path.end() {
+                                return Err(invalid_data("inconsistent path"));
+                            }
+                            path.v_storage.push(l.end());
+                            path.l_storage.push(l);
+                        }
+                        g.paths.push((name, path));
+                    }
+                }
+                _ => debug!(
+                    "Skipping unknown section {} of binary graph",
+                    String::from_utf8_lossy(&tag)
+                ),
+            }
+        }
+        g.check_links();
+        g.compact();
+        Ok(g)
+    }
+}
@@ -1,4 +1,6 @@
+pub mod components;
 pub mod dfs;
+pub mod neighborhood;
 pub mod scc;
 pub mod superbubble;
 
@@ -0,0 +1,96 @@
+use crate::graph::*;
+use itertools::Itertools;
+use std::cmp::{min, Reverse};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+//Weakly connected components (link directions are ignored) as lists of node ids.
+//Components are reported in the order of their minimal node ids, nodes within a component are sorted.
+pub fn weakly_connected(g: &Graph) -> Vec<Vec<usize>> {
+    let mut used = vec![false; g.node_cnt()];
+    let mut components = Vec::new();
+    for init_id in 0..g.node_cnt() {
+        if used[init_id] {
+            continue;
+        }
+        used[init_id] = true;
+        let mut component = vec![init_id];
+        let mut stack = vec![init_id];
+        while let Some(node_id) = stack.pop() {
+            for v in [Vertex::forward(node_id), Vertex::reverse(node_id)] {
+                for n in g.outgoing_neighbors(v) {
+                    let w = n.end.node_id;
+                    if !used[w] {
+                        used[w] = true;
+                        component.push(w);
+                        stack.push(w);
+                    }
+                }
+            }
+        }
+        component.sort();
+        components.push(component);
+    }
+    components
+}
+
+//Component id for every node (components are numbered as in weakly_connected)
+pub fn component_ids(g: &Graph) -> Vec<usize> {
+    let mut ids = vec![0; g.node_cnt()];
+    for (component_id, component) in weakly_connected(g).into_iter().enumerate() {
+        for node_id in component {
+            ids[node_id] = component_id;
+        }
+    }
+    ids
+}
+
+//Applies f to subgraphs formed by groups of components using the specified number of threads.
+//To reduce overhead small components are grouped together (into ~4 parts per thread, balancing total lengths).
+//For every part returns the result and ids of the subgraph nodes in the original graph.
+pub fn process_components<T: Send>(
+    g: &Graph,
+    threads: usize,
+    f: impl Fn(&Graph) -> T + Sync,
+) -> Vec<(T, Vec<usize>)> {
+    let components = weakly_connected(g);
+    let part_cnt = min(components.len(), 4 * threads).max(1);
+    let mut part_lengths = vec![0; part_cnt];
+    let mut part_ids = vec![0; g.node_cnt()];
+    //longest components first, each to the currently shortest part
+    for component in components
+        .iter()
+        .sorted_by_key(|c| Reverse(c.iter().map(|&n| g.node_length(n)).sum::<usize>()))
+    {
+        let part_id = (0..part_cnt).min_by_key(|&p| part_lengths[p]).unwrap();
+        part_lengths[part_id] += component.iter().map(|&n| g.node_length(n)).sum::<usize>();
+        for &node_id in component {
+            part_ids[node_id] = part_id;
+        }
+    }
+
+    let parts = g.split_by(&part_ids);
+    let next_part = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..parts.len()).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..min(threads, parts.len()) {
+            scope.spawn(|| loop {
+                let part_id = next_part.fetch_add(1, Ordering::Relaxed);
+                if part_id >= parts.len() {
+                    break;
+                }
+                let result = f(&parts[part_id].0);
+                results.lock().unwrap()[part_id] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .zip(parts)
+        .map(|(result, (_, node_ids))| (result.unwrap(), node_ids))
+        .collect()
+}
@@ -10,6 +10,24 @@ pub enum TraversalDirection {
     //UNDIRECTED,
 }
 
+//Returned by visitor callbacks to control the traversal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DfsControl {
+    Continue,
+    Stop,
+}
+
+//Reported to DFS visitor callbacks
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DfsEvent {
+    //vertex is visited (marked as blocked)
+    Enter(Vertex),
+    //vertex is reached, but not visited
+    Boundary(Vertex),
+    //all neighbors of the vertex were processed (vertex is added to exit order)
+    Exit(Vertex),
+}
+
 //TODO replace sets with arrays
 
 //TODO pass functions giving neighbour iterators to allow more flexibility (directions, subgraphs, length boundaries, etc)
@@ -64,32 +82,66 @@ impl<'a> DFS<'a> {
         self.blocked.extend(iter);
     }
 
-    //TODO use iterators
-    fn neighbors(&self, v: Vertex) -> Vec<Vertex> {
+    fn nth_neighbor(&self, v: Vertex, i: usize) -> Option<Vertex> {
+        //incoming neighbors are reverse-complements of outgoing neighbors of v.rc()
         match self.direction {
-            TraversalDirection::FORWARD => self.g.outgoing_edges(v).iter().map(|l| l.end).collect(),
+            TraversalDirection::FORWARD => self.g.outgoing_neighbors(v).get(i).map(|n| n.end),
             TraversalDirection::REVERSE => {
-                self.g.incoming_edges(v).iter().map(|l| l.start).collect()
+                self.g.outgoing_neighbors(v.rc()).get(i).map(|n| n.end.rc())
             }
         }
     }
 
+    fn can_visit(&self, w: Vertex) -> bool {
+        !self.blocked.contains(&w)
+            && self.visit_f.is_none_or(|f| f(w))
+            && self.g.vertex_length(w) < self.node_len_thr
+    }
+
     pub fn run_from(&mut self, v: Vertex) {
+        self.run_from_with(v, |_| DfsControl::Continue);
+    }
+
+    //Uses explicit stack, so that huge components can be traversed.
+    //If the visitor stops the traversal, exit order, boundary, etc reflect the partial state.
+    pub fn run_from_with(
+        &mut self,
+        v: Vertex,
+        mut visitor: impl FnMut(DfsEvent) -> DfsControl,
+    ) -> DfsControl {
         assert!(!self.blocked.contains(&v));
         self.blocked.insert(v);
+        if visitor(DfsEvent::Enter(v)) == DfsControl::Stop {
+            return DfsControl::Stop;
+        }
 
-        for w in self.neighbors(v) {
-            if !self.blocked.contains(&w)
-                && (self.visit_f.is_none() || self.visit_f.unwrap()(w))
-                && self.g.vertex_length(w) < self.node_len_thr
-            {
-                self.run_from(w);
-            } else {
-                self.boundary.insert(w);
+        //vertices with positions of the next neighbor to consider
+        let mut stack = vec![(v, 0)];
+        while let Some((u, pos)) = stack.last_mut() {
+            let u = *u;
+            let event = match self.nth_neighbor(u, *pos) {
+                Some(w) => {
+                    *pos += 1;
+                    if self.can_visit(w) {
+                        self.blocked.insert(w);
+                        stack.push((w, 0));
+                        DfsEvent::Enter(w)
+                    } else {
+                        self.boundary.insert(w);
+                        DfsEvent::Boundary(w)
+                    }
+                }
+                None => {
+                    stack.pop();
+                    self.tout.push(u);
+                    DfsEvent::Exit(u)
+                }
+            };
+            if visitor(event) == DfsControl::Stop {
+                return DfsControl::Stop;
             }
         }
-
-        self.tout.push(v);
+        DfsControl::Continue
     }
 
     //TODO maybe rename into topsort?
@@ -112,9 +164,10 @@ impl<'a> DFS<'a> {
         self.tout.iter().copied().collect()
     }
 
-    //pub fn blocked(&self) -> &HashSet<Vertex> {
-    //    &self.blocked
-    //}
+    //includes visited and initially blocked
+    pub fn blocked(&self) -> &HashSet<Vertex> {
+        &self.blocked
+    }
 
     pub fn exit_order(&self) -> &Vec<Vertex> {
         &self.tout
@@ -142,7 +195,7 @@ impl<'a> DFS<'a> {
     pub fn dead_ends(&self) -> Vec<Vertex> {
         self.tout
             .iter()
-            .filter(|&v| self.neighbors(*v).is_empty())
+            .filter(|&v| self.nth_neighbor(*v, 0).is_none())
             .copied()
             .collect()
     }
@@ -155,61 +208,111 @@ pub struct ShortNodeComponent {
     pub inner: HashSet<Vertex>,
 }
 
-impl ShortNodeComponent {
-    fn consider(&mut self, g: &Graph, v: Vertex, l: Link, length_threshold: usize) {
-        let mut is_source = false;
-        let mut is_sink = false;
-
-        if g.vertex_length(v) < length_threshold {
-            if !self.inner.insert(v) {
-                //inner already considered
-                return;
+//Reported to ShortNodeComponent visitor callbacks when a vertex is added to the component
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ComponentEvent {
+    Inner(Vertex),
+    Source(Vertex),
+    Sink(Vertex),
+}
+
+//Visitor stopping the search as soon as the boundary stops being simple
+//(see ShortNodeComponent::simple_boundary)
+pub fn simple_boundary_guard() -> impl FnMut(ComponentEvent) -> DfsControl {
+    let mut boundary_nodes = HashSet::new();
+    move |e| match e {
+        ComponentEvent::Source(v) | ComponentEvent::Sink(v) => {
+            if boundary_nodes.insert(v.node_id) {
+                DfsControl::Continue
+            } else {
+                DfsControl::Stop
             }
-        } else {
-            //v is long
-            if v == l.start {
-                //if v is long and we came from the 'right'
-                is_source = true;
-                if !self.sources.insert(v) {
-                    //source already considered
-                    return;
+        }
+        ComponentEvent::Inner(_) => DfsControl::Continue,
+    }
+}
+
+impl ShortNodeComponent {
+    fn new(sources: &[Vertex], sinks: &[Vertex], inner: &[Vertex]) -> ShortNodeComponent {
+        ShortNodeComponent {
+            sources: sources.iter().copied().collect(),
+            sinks: sinks.iter().copied().collect(),
+            has_deadends: false,
+            inner: inner.iter().copied().collect(),
+        }
+    }
+
+    //Processes (vertex, link via which it was reached) pairs with explicit stack.
+    //Returns false if stopped by the visitor.
+    fn grow(
+        &mut self,
+        g: &Graph,
+        mut to_consider: Vec<(Vertex, Link)>,
+        length_threshold: usize,
+        visitor: &mut dyn FnMut(ComponentEvent) -> DfsControl,
+    ) -> bool {
+        while let Some((v, l)) = to_consider.pop() {
+            let mut is_source = false;
+            let mut is_sink = false;
+
+            let event = if g.vertex_length(v) < length_threshold {
+                if !self.inner.insert(v) {
+                    //inner already considered
+                    continue;
                 }
+                ComponentEvent::Inner(v)
             } else {
-                assert!(v == l.end);
-                //if v is long and we came from the 'left'
-                is_sink = true;
-                if !self.sinks.insert(v) {
-                    //sink already considered
-                    return;
+                //v is long
+                if v == l.start {
+                    //if v is long and we came from the 'right'
+                    is_source = true;
+                    if !self.sources.insert(v) {
+                        //source already considered
+                        continue;
+                    }
+                    ComponentEvent::Source(v)
+                } else {
+                    assert!(v == l.end);
+                    //if v is long and we came from the 'left'
+                    is_sink = true;
+                    if !self.sinks.insert(v) {
+                        //sink already considered
+                        continue;
+                    }
+                    ComponentEvent::Sink(v)
                 }
+            };
+            if visitor(event) == DfsControl::Stop {
+                return false;
             }
-        }
 
-        //if not a source consider it's incoming edges
-        if !is_source {
-            if g.incoming_edge_cnt(v) == 0 {
-                assert!(g.vertex_length(v) < length_threshold);
-                self.has_deadends = true;
-            }
-            for i_l in g.incoming_edges(v) {
-                if i_l != l {
-                    self.consider(g, i_l.start, i_l, length_threshold);
+            //if not a source consider it's incoming edges
+            if !is_source {
+                if g.incoming_edge_cnt(v) == 0 {
+                    assert!(g.vertex_length(v) < length_threshold);
+                    self.has_deadends = true;
                 }
+                to_consider.extend(
+                    g.incoming_edge_iter(v)
+                        .filter(|&i_l| i_l != l)
+                        .map(|i_l| (i_l.start, i_l)),
+                );
             }
-        }
 
-        //if not a sink consider outgoing edges
-        if !is_sink {
-            if g.outgoing_edge_cnt(v) == 0 {
-                assert!(g.vertex_length(v) < length_threshold);
-                self.has_deadends = true;
-            }
-            for o_l in g.outgoing_edges(v) {
-                if o_l != l {
-                    self.consider(g, o_l.end, o_l, length_threshold);
+            //if not a sink consider outgoing edges
+            if !is_sink {
+                if g.outgoing_edge_cnt(v) == 0 {
+                    assert!(g.vertex_length(v) < length_threshold);
+                    self.has_deadends = true;
                 }
+                to_consider.extend(
+                    g.outgoing_edge_iter(v)
+                        .filter(|&o_l| o_l != l)
+                        .map(|o_l| (o_l.end, o_l)),
+                );
             }
         }
+        true
     }
 
     //returns true if all nodes are distinct within sources/sinks union
@@ -225,33 +328,52 @@ impl ShortNodeComponent {
     }
 
     pub fn ahead_from_long(g: &Graph, v: Vertex, length_threshold: usize) -> ShortNodeComponent {
-        assert!(g.vertex_length(v) >= length_threshold);
-        let mut component = ShortNodeComponent {
-            sources: std::iter::once(v).collect(),
-            sinks: HashSet::new(),
-            has_deadends: false,
-            inner: HashSet::new(),
-        };
+        Self::ahead_from_long_with(g, v, length_threshold, |_| DfsControl::Continue).unwrap()
+    }
 
-        for o_l in g.outgoing_edges(v) {
-            component.consider(g, o_l.end, o_l, length_threshold);
+    //None if the search was stopped by the visitor
+    pub fn ahead_from_long_with(
+        g: &Graph,
+        v: Vertex,
+        length_threshold: usize,
+        mut visitor: impl FnMut(ComponentEvent) -> DfsControl,
+    ) -> Option<ShortNodeComponent> {
+        assert!(g.vertex_length(v) >= length_threshold);
+        let mut component = Self::new(&[v], &[], &[]);
+        if visitor(ComponentEvent::Source(v)) == DfsControl::Stop {
+            return None;
         }
-        component
+        let to_consider = g.outgoing_edge_iter(v).map(|o_l| (o_l.end, o_l)).collect();
+        if !component.grow(g, to_consider, length_threshold, &mut visitor) {
+            return None;
+        }
+        Some(component)
     }
 
     pub fn back_from_long(g: &Graph, v: Vertex, length_threshold: usize) -> ShortNodeComponent {
-        assert!(g.vertex_length(v) >= length_threshold);
-        let mut component = ShortNodeComponent {
-            sources: HashSet::new(),
-            sinks: std::iter::once(v).collect(),
-            has_deadends: false,
-            inner: HashSet::new(),
-        };
+        Self::back_from_long_with(g, v, length_threshold, |_| DfsControl::Continue).unwrap()
+    }
 
-        for i_l in g.incoming_edges(v) {
-            component.consider(g, i_l.start, i_l, length_threshold);
+    //None if the search was stopped by the visitor
+    pub fn back_from_long_with(
+        g: &Graph,
+        v: Vertex,
+        length_threshold: usize,
+        mut visitor: impl FnMut(ComponentEvent) -> DfsControl,
+    ) -> Option<ShortNodeComponent> {
+        assert!(g.vertex_length(v) >= length_threshold);
+        let mut component = Self::new(&[], &[v], &[]);
+        if visitor(ComponentEvent::Sink(v)) == DfsControl::Stop {
+            return None;
+        }
+        let to_consider = g
+            .incoming_edge_iter(v)
+            .map(|i_l| (i_l.start, i_l))
+            .collect();
+        if !component.grow(g, to_consider, length_threshold, &mut visitor) {
+            return None;
         }
-        component
+        Some(component)
     }
 
     //todo refactor and simplify logic!
@@ -260,18 +382,16 @@ impl ShortNodeComponent {
         if g.vertex_length(v) >= length_threshold {
             Self::ahead_from_long(g, v, length_threshold)
         } else {
-            let mut component = ShortNodeComponent {
-                sources: HashSet::new(),
-                sinks: HashSet::new(),
-                has_deadends: (g.outgoing_edge_cnt(v) == 0 || g.incoming_edge_cnt(v) == 0),
-                inner: std::iter::once(v).collect(),
-            };
-            for i_l in g.incoming_edges(v) {
-                component.consider(g, i_l.start, i_l, length_threshold);
-            }
-            for o_l in g.outgoing_edges(v) {
-                component.consider(g, o_l.end, o_l, length_threshold);
-            }
+            let mut component = Self::new(&[], &[], &[v]);
+            component.has_deadends = g.outgoing_edge_cnt(v) == 0 || g.incoming_edge_cnt(v) == 0;
+            let to_consider = g
+                .incoming_edge_iter(v)
+                .map(|i_l| (i_l.start, i_l))
+                .chain(g.outgoing_edge_iter(v).map(|o_l| (o_l.end, o_l)))
+                .collect();
+            component.grow(g, to_consider, length_threshold, &mut |_| {
+                DfsControl::Continue
+            });
             component
         }
     }
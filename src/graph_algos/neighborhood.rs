@@ -0,0 +1,122 @@
+use super::dfs::{DfsControl, DfsEvent, DFS};
+use crate::graph::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+//Limit on the distance from the seed nodes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Radius {
+    //number of links
+    Hops(usize),
+    //total length of the nodes between the seed and the node
+    Bases(usize),
+    Unbounded,
+}
+
+impl Radius {
+    fn step(&self, g: &Graph, node_id: usize, is_seed: bool) -> usize {
+        match self {
+            Radius::Hops(_) => 1,
+            Radius::Bases(_) if is_seed => 0,
+            Radius::Bases(_) => g.node_length(node_id),
+            Radius::Unbounded => 0,
+        }
+    }
+
+    fn limit(&self) -> usize {
+        match self {
+            Radius::Hops(l) | Radius::Bases(l) => *l,
+            Radius::Unbounded => usize::MAX,
+        }
+    }
+}
+
+//Bounded DFS from the seeds, ignoring link directions
+//(forward DFS is also launched from reverse-complements of the visited vertices).
+//Returns ids of the visited nodes and of the remaining boundary nodes (length >= max_node_len).
+fn bounded_dfs(
+    g: &Graph,
+    seeds: &[usize],
+    max_node_len: usize,
+) -> (HashSet<usize>, HashSet<usize>) {
+    let mut dfs = DFS::new_forward(g);
+    dfs.set_max_node_len(max_node_len);
+    let mut to_run: Vec<Vertex> = seeds
+        .iter()
+        .flat_map(|&node_id| [Vertex::forward(node_id), Vertex::reverse(node_id)])
+        .collect();
+    while let Some(v) = to_run.pop() {
+        if dfs.blocked().contains(&v) {
+            continue;
+        }
+        dfs.run_from_with(v, |e| {
+            if let DfsEvent::Enter(w) = e {
+                to_run.push(w.rc());
+            }
+            DfsControl::Continue
+        });
+    }
+    let visited: HashSet<usize> = dfs.blocked().iter().map(|v| v.node_id).collect();
+    let boundary = dfs
+        .boundary()
+        .iter()
+        .map(|v| v.node_id)
+        .filter(|node_id| !visited.contains(node_id))
+        .collect();
+    (visited, boundary)
+}
+
+//Nodes within the radius from the seeds (link directions are ignored), sorted by id.
+//Nodes of length >= max_node_len are included (as DFS boundary),
+//but the search doesn't proceed through them (unless they are seeds).
+pub fn neighborhood(g: &Graph, seeds: &[usize], radius: Radius, max_node_len: usize) -> Vec<usize> {
+    let (visited, boundary) = bounded_dfs(g, seeds, max_node_len);
+    let mut nodes: Vec<usize> = match radius {
+        Radius::Unbounded => visited.union(&boundary).copied().collect(),
+        _ => within_radius(g, seeds, radius, &visited),
+    };
+    nodes.sort();
+    nodes
+}
+
+//DFS order doesn't give shortest distances, so the radius is checked by a distance-ordered search,
+//which only proceeds through the nodes visited by the bounded DFS
+fn within_radius(
+    g: &Graph,
+    seeds: &[usize],
+    radius: Radius,
+    passable: &HashSet<usize>,
+) -> Vec<usize> {
+    let mut dist = vec![usize::MAX; g.node_cnt()];
+    let mut is_seed = vec![false; g.node_cnt()];
+    let mut heap = BinaryHeap::new();
+    for &node_id in seeds {
+        is_seed[node_id] = true;
+        dist[node_id] = 0;
+        heap.push(Reverse((0, node_id)));
+    }
+
+    while let Some(Reverse((d, node_id))) = heap.pop() {
+        if d > dist[node_id] || !passable.contains(&node_id) {
+            //outdated entry or boundary node
+            continue;
+        }
+        let next_d = d.saturating_add(radius.step(g, node_id, is_seed[node_id]));
+        if next_d > radius.limit() {
+            continue;
+        }
+        for v in [Vertex::forward(node_id), Vertex::reverse(node_id)] {
+            for n in g.outgoing_neighbors(v) {
+                let w = n.end.node_id;
+                if next_d < dist[w] {
+                    dist[w] = next_d;
+                    heap.push(Reverse((next_d, w)));
+                }
+            }
+        }
+    }
+
+    (0..g.node_cnt())
+        .filter(|&node_id| dist[node_id] != usize::MAX)
+        .collect()
+}
@@ -10,7 +10,7 @@ use std::collections::{HashMap, HashSet};
 //NB. Loop of single vertex is considered 'NON-trivial'
 pub fn strongly_connected(graph: &Graph) -> Vec<Vec<Vertex>> {
     let mut non_trivial_sccs: Vec<Vec<Vertex>> = Vec::new();
-    let is_loop = |v: Vertex| graph.outgoing_edges(v).iter().any(|l| l.end == v);
+    let is_loop = |v: Vertex| graph.outgoing_edge_iter(v).any(|l| l.end == v);
 
     // run DFS on direct edges
     let mut dfs = dfs::DFS::new_forward(graph);
@@ -162,7 +162,7 @@ pub fn condensation(
             debug!("Loop ignored for vertex {}", condensation.v_str(v));
             continue;
         }
-        if !condensation.outgoing_edges(v).iter().any(|l| l.end == w) {
+        if !condensation.outgoing_edge_iter(v).any(|l| l.end == w) {
             condensation.add_link(Link {
                 start: v,
                 end: w,
@@ -184,12 +184,10 @@ pub struct LocalizedTangle {
 //subtracts minimal incoming overlap from every vertex and takes sum
 pub fn estimate_size_no_mult(tangle: &LocalizedTangle, g: &Graph) -> usize {
     let shortest_incoming_overlap = |v: Vertex| {
-        return g
-            .incoming_edges(v)
-            .iter()
+        g.incoming_edge_iter(v)
             .map(|l| l.overlap)
             .min()
-            .unwrap_or(0);
+            .unwrap_or(0)
     };
 
     tangle
@@ -206,22 +204,22 @@ fn find_localized(g: &Graph, non_trivial_scc: &[Vertex]) -> Option<LocalizedTang
     let entrance = only_or_none(
         component_vertices
             .iter()
-            .flat_map(|&v| g.incoming_edges(v))
+            .flat_map(|&v| g.incoming_edge_iter(v))
             .filter(|l| !component_vertices.contains(&l.start)),
     )?;
 
     let exit = only_or_none(
         component_vertices
             .iter()
-            .flat_map(|&v| g.outgoing_edges(v))
+            .flat_map(|&v| g.outgoing_edge_iter(v))
             .filter(|l| !component_vertices.contains(&l.end)),
     )?;
 
     //TODO think where this check should be performed
     //Also checking that entrance and exit
     //are the only ways to go from corresponding vertices
-    let entrance = only_or_none(g.outgoing_edges(entrance.start).into_iter())?;
-    let exit = only_or_none(g.incoming_edges(exit.end).into_iter())?;
+    let entrance = only_or_none(g.outgoing_edge_iter(entrance.start))?;
+    let exit = only_or_none(g.incoming_edge_iter(exit.end))?;
 
     //guard against potential tricky strand-switching case
     if entrance.start.node_id == exit.end.node_id {
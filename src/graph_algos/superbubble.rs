@@ -35,7 +35,7 @@ impl Superbubble {
         let mut rc_p = Path::new(v.rc());
         'outer: while v != self.start_vertex {
             //let l = self.heaviest_backtrace.get(v).unwrap();
-            for l in g.incoming_edges(v) {
+            for l in g.incoming_edge_iter(v) {
                 if let Some((_, l_d)) = self.link_dist_range(l, g) {
                     if l_d == longest_dist {
                         assert!(l.end == v);
@@ -57,7 +57,7 @@ impl Superbubble {
         let mut rc_p = Path::new(v.rc());
         'outer: while v != self.start_vertex {
             //let l = self.heaviest_backtrace.get(v).unwrap();
-            for l in g.incoming_edges(v) {
+            for l in g.incoming_edge_iter(v) {
                 if let Some((l_d, _)) = self.link_dist_range(l, g) {
                     if l_d == shortest_dist {
                         assert!(l.end == v);
@@ -151,41 +151,22 @@ pub fn find_superbubble_subgraph(
 
     let outgoing_edge_cnt = |v| match consider_vertex_f {
         None => g.outgoing_edge_cnt(v),
-        Some(avail) => g.outgoing_edges(v).iter().filter(|l| avail(l.end)).count(),
+        Some(avail) => g.outgoing_edge_iter(v).filter(|l| avail(l.end)).count(),
     };
 
     let incoming_edge_cnt = |v| match consider_vertex_f {
         None => g.incoming_edge_cnt(v),
-        Some(avail) => g
-            .incoming_edges(v)
-            .iter()
-            .filter(|l| avail(l.start))
-            .count(),
+        Some(avail) => g.incoming_edge_iter(v).filter(|l| avail(l.start)).count(),
     };
 
-    let outgoing_edges = |v| match consider_vertex_f {
-        None => g.outgoing_edges(v),
-        Some(avail) => g
-            .outgoing_edges(v)
-            .iter()
-            .copied()
-            .filter(|l| avail(l.end))
-            .collect(),
-    };
-
-    let _incoming_edges = |v| match consider_vertex_f {
-        None => g.incoming_edges(v),
-        Some(avail) => g
-            .incoming_edges(v)
-            .iter()
-            .copied()
-            .filter(|l| avail(l.start))
-            .collect(),
+    let outgoing_edges = |v| {
+        g.outgoing_edge_iter(v)
+            .filter(move |l| consider_vertex_f.is_none_or(|avail| avail(l.end)))
     };
 
     if outgoing_edge_cnt(bubble.start_vertex) < 2
         //same check, but excluding loops
-        || outgoing_edges(bubble.start_vertex).iter().filter(|l| l.start != l.end).count() < 2
+        || outgoing_edges(bubble.start_vertex).filter(|l| l.start != l.end).count() < 2
     {
         return None;
     }
@@ -0,0 +1,231 @@
+//Parent-specific k-mer sets and counting of their occurrences in the node sequences.
+//K-mers (k <= 32) are 2-bit encoded and stored in canonical form (minimum of the k-mer and its reverse complement).
+use crate::error::{Error, Result};
+use crate::graph::*;
+use crate::trio::{TrioGroup, TrioInfo};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+pub const MAX_K: usize = 32;
+
+//Binary k-mer list: magic, k (u32), k-mer count (u64), packed k-mers (u64), all little-endian
+const BINARY_MAGIC: &[u8; 4] = b"RKK\0";
+
+fn encode(c: u8) -> Option<u64> {
+    match c {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+fn mask(k: usize) -> u64 {
+    if k == MAX_K {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    }
+}
+
+fn rc_code(code: u64, k: usize) -> u64 {
+    let mut code = !code;
+    let mut rc = 0;
+    for _ in 0..k {
+        rc = (rc << 2) | (code & 3);
+        code >>= 2;
+    }
+    rc
+}
+
+pub struct KmerSet {
+    k: usize,
+    kmers: HashSet<u64>,
+}
+
+impl KmerSet {
+    pub fn new(k: usize) -> Result<KmerSet> {
+        if k == 0 || k > MAX_K {
+            return Err(Error::Settings(format!(
+                "k-mer size should be between 1 and {MAX_K}, got {k}"
+            )));
+        }
+        Ok(KmerSet {
+            k,
+            kmers: HashSet::new(),
+        })
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn len(&self) -> usize {
+        self.kmers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kmers.is_empty()
+    }
+
+    //canonical code of the k-mer, None if it has wrong length or includes non-ACGT characters
+    fn canonical(&self, kmer: &str) -> Option<u64> {
+        if kmer.len() != self.k {
+            return None;
+        }
+        let mut code = 0;
+        for c in kmer.bytes() {
+            code = (code << 2) | encode(c)?;
+        }
+        Some(code.min(rc_code(code, self.k)))
+    }
+
+    //false if the k-mer has wrong length or includes non-ACGT characters
+    pub fn insert(&mut self, kmer: &str) -> bool {
+        match self.canonical(kmer) {
+            Some(code) => {
+                self.kmers.insert(code);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, kmer: &str) -> bool {
+        self.canonical(kmer)
+            .is_some_and(|code| self.kmers.contains(&code))
+    }
+
+    //Start positions (within the forward sequence) of the k-mers (in either orientation) present in the set
+    pub fn hits(&self, seq: &str) -> Vec<usize> {
+        let mut hits = Vec::new();
+        let mask = mask(self.k);
+        let mut fwd = 0;
+        let mut rev = 0;
+        //number of consecutive valid characters
+        let mut valid = 0;
+        for (i, c) in seq.bytes().enumerate() {
+            match encode(c) {
+                Some(x) => {
+                    fwd = ((fwd << 2) | x) & mask;
+                    rev = (rev >> 2) | ((3 - x) << (2 * (self.k - 1)));
+                    valid += 1;
+                }
+                None => valid = 0,
+            }
+            if valid >= self.k && self.kmers.contains(&fwd.min(rev)) {
+                hits.push(i + 1 - self.k);
+            }
+        }
+        hits
+    }
+
+    //Plain-text (k-mer in the first column of every line, e.g. output of 'meryl print') or binary k-mer list
+    pub fn read(path: &PathBuf, k: usize) -> Result<KmerSet> {
+        let mut reader = BufReader::new(File::open(path)?);
+        if reader.fill_buf()?.starts_with(BINARY_MAGIC) {
+            return Self::read_binary(&mut reader, k);
+        }
+        let mut set = KmerSet::new(k)?;
+        for (i, line) in reader.lines().enumerate() {
+            let l = line?;
+            let kmer = l.split_whitespace().next().unwrap_or("");
+            if kmer.is_empty() || kmer.starts_with('#') {
+                continue;
+            }
+            if !set.insert(kmer) {
+                return Err(Error::Table(format!(
+                    "invalid {k}-mer '{kmer}' in line {} of {}",
+                    i + 1,
+                    path.to_str().unwrap()
+                )));
+            }
+        }
+        Ok(set)
+    }
+
+    fn read_binary<R: Read>(reader: &mut R, k: usize) -> Result<KmerSet> {
+        let mut header = [0u8; 16];
+        reader.read_exact(&mut header)?;
+        let file_k = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        if file_k != k {
+            return Err(Error::Settings(format!(
+                "binary k-mer list was built for k={file_k}, while k={k} was requested"
+            )));
+        }
+        let cnt = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let mut set = KmerSet::new(k)?;
+        let mut buf = [0u8; 8];
+        for _ in 0..cnt {
+            reader.read_exact(&mut buf)?;
+            let code = u64::from_le_bytes(buf) & mask(k);
+            set.kmers.insert(code.min(rc_code(code, k)));
+        }
+        Ok(set)
+    }
+
+    pub fn write_binary<W: Write>(&self, output: &mut W) -> std::io::Result<()> {
+        output.write_all(BINARY_MAGIC)?;
+        output.write_all(&(self.k as u32).to_le_bytes())?;
+        output.write_all(&(self.kmers.len() as u64).to_le_bytes())?;
+        let mut codes: Vec<u64> = self.kmers.iter().copied().collect();
+        codes.sort();
+        for code in codes {
+            output.write_all(&code.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+//Marker hits of a single node
+pub struct NodeHits {
+    pub node_id: usize,
+    pub mat_positions: Vec<usize>,
+    pub pat_positions: Vec<usize>,
+}
+
+//Counts hits of parental k-mers within every node, segment sequences are required
+pub fn count_hits(g: &Graph, mat: &KmerSet, pat: &KmerSet) -> Result<Vec<NodeHits>> {
+    if mat.k() != pat.k() {
+        return Err(Error::Settings(String::from(
+            "parental k-mer sets have different k",
+        )));
+    }
+    (0..g.node_cnt())
+        .map(|node_id| {
+            let seq = g
+                .sequence(node_id)
+                .ok_or_else(|| g.missing_sequence_error(node_id))?;
+            Ok(NodeHits {
+                node_id,
+                mat_positions: mat.hits(seq),
+                pat_positions: pat.hits(seq),
+            })
+        })
+        .collect()
+}
+
+impl NodeHits {
+    pub fn trio_info(&self, g: &Graph) -> TrioInfo {
+        TrioInfo::new(
+            g.name(self.node_id),
+            self.mat_positions.len(),
+            self.pat_positions.len(),
+        )
+    }
+
+    //Hits of both parents ordered by position
+    pub fn positions(&self) -> Vec<(usize, TrioGroup)> {
+        let mut positions: Vec<(usize, TrioGroup)> = self
+            .mat_positions
+            .iter()
+            .map(|&p| (p, TrioGroup::MATERNAL))
+            .chain(self.pat_positions.iter().map(|&p| (p, TrioGroup::PATERNAL)))
+            .collect();
+        positions.sort();
+        positions
+    }
+}
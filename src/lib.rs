@@ -1,22 +1,32 @@
+use graph_algos::components;
+use graph_algos::neighborhood::{neighborhood, Radius};
 use log::{debug, info, warn};
 use std::collections::HashMap;
-use std::error::Error;
-use std::fs;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::{collections::HashSet, path::PathBuf};
 use trio_walk::HaploSearchSettings;
 
 //tests don't compile without the pub
 //FIXME what to do?
+pub mod error;
 pub mod graph;
 pub mod graph_algos;
+pub mod kmers;
+pub mod marker_model;
 pub mod pseudo_hap;
+pub mod reads;
+pub mod stats;
+pub mod switches;
 pub mod trio;
 pub mod trio_walk;
 
+pub use error::{Error, Result};
 pub use graph::*;
 
+use crate::kmers::KmerSet;
+use crate::marker_model::{MarkerModel, MarkerThresholds};
+use crate::stats::{weighted_mean_solid_cov, GraphStats, StatsParams};
 use crate::trio::{
     assign_short_node_tangles, GroupAssignmentSettings, TangleAssignmentSettings, TrioGroup,
 };
@@ -29,9 +39,55 @@ pub struct TrioSettings {
     #[clap(short, long)]
     graph: PathBuf,
 
-    /// Parental markers file
-    #[clap(short, long)]
-    markers: PathBuf,
+    /// Parental markers file (node name and maternal/paternal count columns, named columns are detected from the header)
+    #[clap(short, long, required_unless_present = "gaf")]
+    markers: Option<PathBuf>,
+
+    /// GAF alignments of haplotype-labeled reads, used to derive node marker counts instead of the markers file
+    #[clap(long, conflicts_with = "markers", requires = "read_haplotypes")]
+    gaf: Option<PathBuf>,
+
+    /// Haplotypes of the reads aligned in GAF (read<TAB>haplotype, other labels are ignored)
+    #[clap(long, requires = "gaf")]
+    read_haplotypes: Option<PathBuf>,
+
+    /// Read alignments with lower mapping quality are ignored
+    #[clap(long, default_value_t = 0)]
+    read_min_mapq: usize,
+
+    /// Number of aligned bases of haplotype-labeled reads counted as a single marker
+    #[clap(long, default_value_t = 1000)]
+    bases_per_marker: usize,
+
+    /// How to treat several marker table rows referring to the same node
+    #[clap(long, value_enum, default_value_t = trio::DuplicateMarkers::Sum)]
+    duplicate_markers: trio::DuplicateMarkers,
+
+    /// Positional parental markers file (node<TAB>position<TAB>haplotype), used to detect haplotype switches within nodes
+    #[clap(long)]
+    marker_positions: Option<PathBuf>,
+
+    /// Haplotype switches within nodes (BED-like intervals between markers flanking the switch)
+    #[clap(long, requires = "marker_positions")]
+    switches: Option<PathBuf>,
+
+    /// Split nodes at detected haplotype switches (pieces are named <node>_1, <node>_2, ...
+    /// and assigned based on positional markers)
+    #[clap(long, requires = "marker_positions")]
+    split_at_switches: bool,
+
+    /// Log-likelihood penalty of a haplotype switch in marker segmentation
+    #[clap(long, default_value_t = 15.)]
+    switch_penalty: f64,
+
+    /// Node coverage table (node<TAB>coverage or node<TAB>hap1_coverage<TAB>hap2_coverage), overrides coverage from the GFA
+    #[clap(long)]
+    coverage: Option<PathBuf>,
+
+    /// Save binary graph cache (<graph>.rkg) next to the GFA unless a fresh one is available.
+    /// Fresh cache is always used instead of the GFA
+    #[clap(long)]
+    graph_cache: bool,
 
     /// Marker-based annotation output file
     #[clap(long)]
@@ -57,17 +113,70 @@ pub struct TrioSettings {
     #[clap(long)]
     gaf_format: bool,
 
-    /// Minimal number of parent-specific markers required for assigning parental group to a node
-    #[clap(long, default_value_t = 10)]
-    marker_cnt: usize,
+    /// Haplo-path sequences in FASTA format (requires segment sequences in the GFA)
+    #[clap(long)]
+    fasta: Option<PathBuf>,
 
-    /// Require at least (node_length / <value>) markers within the node for parental group assignment
-    #[clap(long, default_value_t = 10_000)]
-    marker_sparsity: usize,
+    /// GFA output annotated with node assignments (CL:z: and HP:Z: tags) and haplo-paths (P lines)
+    #[clap(long)]
+    annotated_gfa: Option<PathBuf>,
 
-    /// Sets minimal marker excess for assigning a parental group to <value>:1
-    #[clap(long, default_value_t = 5.0)]
-    marker_ratio: f64,
+    /// Use paths (P/W lines) from the GFA as fixed seeds of haplo-paths
+    #[clap(long)]
+    gfa_paths: bool,
+
+    /// Connected component of every output path (with component node count and total length)
+    #[clap(long)]
+    component_report: Option<PathBuf>,
+
+    /// Process connected components independently using <value> threads
+    #[clap(long, default_value_t = 1)]
+    threads: usize,
+
+    /// Assign parental groups based on posteriors of the likelihood-based marker model instead of marker count/ratio thresholds
+    #[clap(long)]
+    marker_model: bool,
+
+    /// Minimal posterior for assigning parental group (or ISSUE label) with the marker model
+    #[clap(long, default_value_t = 0.95)]
+    min_posterior: f64,
+
+    /// Expected number of haplotype-specific markers per 10kb of the marker model (by default estimated from long nodes)
+    #[clap(long)]
+    marker_density: Option<f64>,
+
+    /// Expected ratio of markers of the other haplotype of the marker model (by default estimated from long nodes)
+    #[clap(long)]
+    marker_error_rate: Option<f64>,
+
+    /// Marker model posteriors of parental groups for all nodes with markers
+    #[clap(long)]
+    posteriors: Option<PathBuf>,
+
+    /// Do not launch haplo-path search from nodes assigned by the marker model with lower posterior
+    #[clap(long, default_value_t = 0.)]
+    min_seed_posterior: f64,
+
+    /// Derive --marker-cnt, --marker-sparsity and --marker-ratio (unless specified) from marker density and error rate
+    /// fitted on clearly phased solid nodes
+    #[clap(long)]
+    calibrate: bool,
+
+    /// Parameters used in the run (including calibrated thresholds and fitted marker density/error rate)
+    #[clap(long)]
+    run_report: Option<PathBuf>,
+
+    /// Minimal number of parent-specific markers required for assigning parental group to a node (default: 10)
+    #[clap(long)]
+    marker_cnt: Option<usize>,
+
+    /// Require at least (node_length / <value>) markers within the node for parental group assignment (default: 10000)
+    #[clap(long)]
+    marker_sparsity: Option<usize>,
+
+    /// Sets minimal marker excess for assigning a parental group to <value>:1 (default: 5.0)
+    #[clap(long)]
+    marker_ratio: Option<f64>,
 
     /// Longer nodes are unlikely to be spurious and likely to be reliably assigned based on markers (used in HOMOZYGOUS node labeling)
     #[clap(long, default_value_t = 200_000)]
@@ -151,6 +260,15 @@ pub struct TrioSettings {
     #[clap(long, default_value_t = 5000)]
     default_gap_size: usize,
 
+    /// Links with read support (RC:i: or ex:i: tags) below <value> will not be used for extension. 0 disables check
+    #[clap(long, default_value_t = 0)]
+    min_link_support: usize,
+
+    /// Prefer extension via link (or bubble side) if its read support exceeds support of all alternatives <value> times.
+    /// 0. disables
+    #[clap(long, default_value_t = 0.)]
+    link_support_ratio: f64,
+
     /// Assign tangles flanked by solid nodes from the same class
     #[clap(long)]
     assign_tangles: bool,
@@ -169,36 +287,377 @@ pub struct TrioSettings {
 }
 
 impl TrioSettings {
-    pub fn validate(&self) {
+    pub fn validate(&self) -> Result<()> {
+        let check = |ok: bool, msg: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(Error::Settings(String::from(msg)))
+            }
+        };
+
+        //calibrated marker ratio is checked after calibration
+        if !self.calibrate {
+            self.check_ratios(
+                self.marker_thresholds(MarkerThresholds::default())
+                    .marker_ratio,
+            )?;
+        }
+
+        check(
+            self.good_side_cov_gap >= 0.,
+            "--good-side-cov-gap can't be negative",
+        )?;
+        check(
+            self.solid_homozygous_cov_coeff >= 0.,
+            "--solid-homozygous-cov-coeff can't be negative",
+        )?;
+        check(self.threads > 0, "--threads should be positive")?;
+        check(
+            self.bases_per_marker > 0,
+            "--bases-per-marker should be positive",
+        )?;
+        check(
+            self.min_posterior > 0. && self.min_posterior <= 1.,
+            "--min-posterior should be in (0, 1]",
+        )?;
+        check(
+            self.posteriors.is_none() || self.marker_model,
+            "--posteriors requires --marker-model",
+        )?;
+        check(
+            self.min_seed_posterior == 0. || self.marker_model,
+            "--min-seed-posterior requires --marker-model",
+        )?;
+        check(
+            parse_hap_names(&self.hap_names).is_some(),
+            "--hap-names should be two comma-separated names",
+        )?;
+        Ok(())
+    }
+
+    fn check_ratios(&self, marker_ratio: f64) -> Result<()> {
         if let Some(issue_ratio) = self.issue_ratio {
-            assert!(
-                issue_ratio <= self.marker_ratio,
-                "--issue-ratio can't be set to a value higher than --marker-ratio"
-            );
+            if issue_ratio > marker_ratio {
+                return Err(Error::Settings(format!(
+                    "--issue-ratio can't be set to a value higher than marker ratio ({marker_ratio})"
+                )));
+            }
         }
 
         if let Some(solid_ratio) = self.solid_ratio {
-            assert!(
-                solid_ratio <= self.marker_ratio,
-                "--solid-ratio can't be set to a value higher than --marker-ratio"
-            );
+            if solid_ratio > marker_ratio {
+                return Err(Error::Settings(format!(
+                    "--solid-ratio can't be set to a value higher than marker ratio ({marker_ratio})"
+                )));
+            }
 
-            if solid_ratio < self.issue_ratio.unwrap_or(self.marker_ratio) {
+            if solid_ratio < self.issue_ratio.unwrap_or(marker_ratio) {
                 warn!(
                     "Specified --solid-ratio value is smaller than --issue-ratio. \
                     Please double-check the logic and consider specifying smaller --issue-ratio."
                 );
             }
         }
+        Ok(())
+    }
+
+    //user-specified thresholds override the provided ones
+    fn marker_thresholds(&self, base: MarkerThresholds) -> MarkerThresholds {
+        MarkerThresholds {
+            marker_cnt: self.marker_cnt.unwrap_or(base.marker_cnt),
+            marker_sparsity: self.marker_sparsity.unwrap_or(base.marker_sparsity),
+            marker_ratio: self.marker_ratio.unwrap_or(base.marker_ratio),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PrimaryAltSettings {
+    /// GFA file
+    #[clap(short, long)]
+    graph: PathBuf,
+
+    /// Node annotation output file (PRIMARY, PRIMARY_BOUNDARY or ALT with colors)
+    #[clap(long)]
+    assign: Option<PathBuf>,
+
+    /// Primary and alt paths output file
+    #[clap(long, short)]
+    paths: Option<PathBuf>,
+
+    /// Use GAF ([<>]<name1>)+ format for paths
+    #[clap(long)]
+    gaf_format: bool,
+
+    /// Connected component of every primary path (with component node count and total length)
+    #[clap(long)]
+    component_report: Option<PathBuf>,
+
+    /// Process connected components independently using <value> threads
+    #[clap(long, default_value_t = 1)]
+    threads: usize,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SubgraphSettings {
+    /// GFA file
+    #[clap(short, long)]
+    graph: PathBuf,
+
+    /// Comma separated names of the nodes around which the subgraph is extracted
+    #[clap(short, long)]
+    nodes: String,
+
+    /// Maximal number of links between the extracted and the specified nodes
+    #[clap(long, conflicts_with = "bases")]
+    hops: Option<usize>,
+
+    /// Maximal total length of the nodes between the extracted and the specified nodes
+    #[clap(long)]
+    bases: Option<usize>,
+
+    /// Do not extend the subgraph beyond nodes of length >= <value> (the nodes themselves are included)
+    #[clap(long)]
+    stop_at_long: Option<usize>,
+
+    /// Output GFA file
+    #[clap(short, long)]
+    output: PathBuf,
+
+    /// Parental markers file to be restricted to the subgraph nodes
+    #[clap(short, long, requires = "markers_out")]
+    markers: Option<PathBuf>,
+
+    /// Output file for the restricted markers
+    #[clap(long)]
+    markers_out: Option<PathBuf>,
+
+    /// Node annotation file to be restricted to the subgraph nodes
+    #[clap(long, requires = "assign_out")]
+    assign: Option<PathBuf>,
+
+    /// Output file for the restricted node annotation
+    #[clap(long)]
+    assign_out: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatsSettings {
+    /// GFA file
+    #[clap(short, long)]
+    graph: PathBuf,
+
+    /// Output file
+    #[clap(short, long)]
+    output: PathBuf,
+
+    /// Output in JSON format (TSV by default)
+    #[clap(long)]
+    json: bool,
+
+    /// Nodes of at least this length are used for the coverage estimate
+    #[clap(long, default_value_t = 500_000)]
+    solid_len: usize,
+
+    /// Localized tangles of bigger estimated size are not reported
+    #[clap(long, default_value_t = 1_000_000)]
+    tangle_size: usize,
+}
 
-        assert!(self.good_side_cov_gap >= 0.);
-        assert!(self.solid_homozygous_cov_coeff >= 0.);
+#[derive(clap::Args, Debug)]
+pub struct PolySettings {
+    /// GFA file
+    #[clap(short, long)]
+    graph: PathBuf,
+
+    /// Haplotype markers file (header with haplotype names followed by node name and per-haplotype marker counts)
+    #[clap(short, long)]
+    markers: PathBuf,
+
+    /// Comma separated names of marker count columns to use (default: all columns after the first)
+    #[clap(long)]
+    hap_names: Option<String>,
+
+    /// How to treat several marker table rows referring to the same node
+    #[clap(long, value_enum, default_value_t = trio::DuplicateMarkers::Sum)]
+    duplicate_markers: trio::DuplicateMarkers,
+
+    /// Node annotation output file
+    #[clap(long)]
+    assign: Option<PathBuf>,
+
+    /// Haplo-paths of every haplotype
+    #[clap(long, short)]
+    paths: Option<PathBuf>,
+
+    /// Use GAF ([<>]<name1>)+ format for paths
+    #[clap(long)]
+    gaf_format: bool,
+
+    /// Minimal total number of markers required for assigning haplotypes to a node
+    #[clap(long, default_value_t = 10)]
+    marker_cnt: usize,
+
+    /// Require at least (node_length / <value>) markers within the node for haplotype assignment
+    #[clap(long, default_value_t = 10_000)]
+    marker_sparsity: usize,
+
+    /// Haplotypes with marker counts <value> times lower than the maximal one are excluded from node assignment
+    #[clap(long, default_value_t = 5.0)]
+    marker_ratio: f64,
+
+    /// Longer nodes are unlikely to represent repeats, polymorphic variants, etc (used to seed and guide the path search)
+    #[clap(long, default_value_t = 500_000)]
+    solid_len: usize,
+
+    /// Longer nodes are unlikely to be spurious and likely to be reliably assigned based on markers
+    #[clap(long, default_value_t = 200_000)]
+    trusted_len: usize,
+
+    /// Try to fill in small ambiguous bubbles
+    #[clap(long)]
+    try_fill_bubbles: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ValidateSettings {
+    /// GFA file
+    #[clap(short, long)]
+    graph: PathBuf,
+
+    /// Parental markers file
+    #[clap(short, long)]
+    markers: Option<PathBuf>,
+
+    /// Report output file (TSV: file, line, record, field, problem)
+    #[clap(short, long)]
+    output: PathBuf,
+
+    /// Comma separated haplotype names, used to detect marker count columns (default: "mat,pat")
+    #[clap(long, default_value_t = String::from("mat,pat"))]
+    hap_names: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct MarkersSettings {
+    /// GFA file (segment sequences are required)
+    #[clap(short, long)]
+    graph: PathBuf,
+
+    /// Maternal-specific k-mers (plain text with k-mer in the first column of every line, or binary list)
+    #[clap(long)]
+    mat: PathBuf,
+
+    /// Paternal-specific k-mers (plain text with k-mer in the first column of every line, or binary list)
+    #[clap(long)]
+    pat: PathBuf,
+
+    /// K-mer size (at most 32)
+    #[clap(short, long, default_value_t = 21)]
+    k: usize,
+
+    /// Marker table output file (node, maternal and paternal marker counts)
+    #[clap(short, long)]
+    output: PathBuf,
+
+    /// Positional markers output file (node, 0-based position, haplotype)
+    #[clap(long)]
+    positions: Option<PathBuf>,
+
+    /// Save k-mer sets in binary format as <value>.mat.rkk and <value>.pat.rkk (faster to read in later runs)
+    #[clap(long)]
+    save_kmers: Option<PathBuf>,
+
+    /// Comma separated haplotype names to be used in outputs (default: "mat,pat")
+    #[clap(long, default_value_t = String::from("mat,pat"))]
+    hap_names: String,
+}
+
+//Binary graph cache is stored next to the GFA (<graph.gfa>.rkg)
+pub fn graph_cache_path(graph_fn: &std::path::Path) -> PathBuf {
+    let mut cache_fn = graph_fn.as_os_str().to_owned();
+    cache_fn.push(".rkg");
+    PathBuf::from(cache_fn)
+}
+
+//Cache is used if it was made from the GFA file of the same size and modification time
+//(and includes sequences, if those are required)
+fn load_fresh_cache(graph_fn: &std::path::Path, keep_sequences: bool) -> Option<Graph> {
+    let cache_fn = graph_cache_path(graph_fn);
+    let mut input = BufReader::new(File::open(&cache_fn).ok()?);
+    let header = match Graph::binary_header(&mut input) {
+        Ok(header) => header,
+        Err(e) => {
+            warn!("Ignoring graph cache {}: {}", cache_fn.to_str().unwrap(), e);
+            return None;
+        }
+    };
+    if header.source.is_none() || header.source != BinarySource::from_file(graph_fn).ok() {
+        info!("Graph cache {} is outdated", cache_fn.to_str().unwrap());
+        return None;
+    }
+    if keep_sequences && !header.has_sequences {
+        info!(
+            "Graph cache {} doesn't include sequences",
+            cache_fn.to_str().unwrap()
+        );
+        return None;
+    }
+    info!("Reading graph from cache {}", cache_fn.to_str().unwrap());
+    let mut input = BufReader::new(File::open(&cache_fn).ok()?);
+    match Graph::load_binary(&mut input, keep_sequences) {
+        Ok(g) => Some(g),
+        Err(e) => {
+            warn!(
+                "Failed to read graph cache {}: {}",
+                cache_fn.to_str().unwrap(),
+                e
+            );
+            None
+        }
     }
 }
 
-fn read_graph(graph_fn: &PathBuf) -> Result<Graph, Box<dyn Error>> {
-    info!("Reading graph from {}", graph_fn.to_str().unwrap());
-    let g = Graph::read_sanitize(&fs::read_to_string(graph_fn)?);
+fn write_graph_cache(g: &Graph, graph_fn: &std::path::Path, with_sequences: bool) -> Result<()> {
+    let cache_fn = graph_cache_path(graph_fn);
+    info!("Writing graph cache to {}", cache_fn.to_str().unwrap());
+    let source = BinarySource::from_file(graph_fn)?;
+    let mut output = BufWriter::new(File::create(&cache_fn)?);
+    g.save_binary(&mut output, with_sequences, Some(&source))?;
+    output.flush()?;
+    Ok(())
+}
+
+//Uses the binary cache if it is fresh, otherwise parses the GFA (and updates the cache if write_cache is set)
+fn read_graph(graph_fn: &PathBuf, keep_sequences: bool, write_cache: bool) -> Result<Graph> {
+    let g = match load_fresh_cache(graph_fn, keep_sequences) {
+        Some(g) => g,
+        None => {
+            info!("Reading graph from {}", graph_fn.to_str().unwrap());
+            let g = Graph::read_from(
+                BufReader::new(File::open(graph_fn)?),
+                &GfaReadSettings {
+                    keep_sequences,
+                    ..GfaReadSettings::sanitize()
+                },
+            )?;
+            //cache is only an optimization, failing to write it shouldn't stop the run
+            if write_cache {
+                if let Err(e) = write_graph_cache(&g, graph_fn, keep_sequences) {
+                    let cache_fn = graph_cache_path(graph_fn);
+                    warn!(
+                        "Failed to write graph cache {}: {}",
+                        cache_fn.to_str().unwrap(),
+                        e
+                    );
+                    //don't leave a partially written cache behind
+                    let _ = std::fs::remove_file(cache_fn);
+                }
+            }
+            g
+        }
+    };
 
     info!("Graph read successfully");
     info!("Node count: {}", g.node_cnt());
@@ -206,23 +665,106 @@ fn read_graph(graph_fn: &PathBuf) -> Result<Graph, Box<dyn Error>> {
     Ok(g)
 }
 
+pub struct NodeCoverage {
+    pub node_name: String,
+    pub coverage: f64,
+}
+
+//Reads node<TAB>coverage or node<TAB>hap1_coverage<TAB>hap2_coverage table
+//(optional header line, same as in the marker table, and '#' comment lines),
+//per-haplotype coverage values are summed
+pub fn read_coverage(path: &PathBuf) -> Result<Vec<NodeCoverage>> {
+    let mut coverages = Vec::new();
+    for (line_num, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let split: Vec<&str> = line.split('\t').collect();
+        if trio::is_header_row(&split, None) {
+            continue;
+        }
+        let parse_cov = |s: &str| -> Result<f64> {
+            s.parse::<f64>()
+                .ok()
+                .filter(|c| c.is_finite() && *c >= 0.)
+                .ok_or_else(|| {
+                    Error::Table(format!(
+                        "invalid coverage value '{s}' in line {} of the coverage table",
+                        line_num + 1
+                    ))
+                })
+        };
+        let coverage = match split.len() {
+            2 => parse_cov(split[1])?,
+            3 => parse_cov(split[1])? + parse_cov(split[2])?,
+            cnt => {
+                return Err(Error::Table(format!(
+                    "expected 2 or 3 columns in line {} of the coverage table, found {cnt}",
+                    line_num + 1
+                )))
+            }
+        };
+        coverages.push(NodeCoverage {
+            node_name: String::from(split[0]),
+            coverage,
+        });
+    }
+    Ok(coverages)
+}
+
+//Overrides coverage of the nodes present in the table, other nodes keep coverage from the GFA
+pub fn apply_coverage(g: &mut Graph, coverages: &[NodeCoverage]) {
+    let mut covered = vec![false; g.node_cnt()];
+    let mut unknown_cnt = 0;
+    for cov in coverages {
+        match g.try_name2id(&cov.node_name) {
+            Some(node_id) => {
+                g.set_coverage(node_id, cov.coverage);
+                covered[node_id] = true;
+            }
+            None => {
+                debug!(
+                    "Node {} from coverage table is not in the graph",
+                    cov.node_name
+                );
+                unknown_cnt += 1;
+            }
+        }
+    }
+    if unknown_cnt > 0 {
+        warn!("{unknown_cnt} nodes from the coverage table are not in the graph");
+    }
+    let missing: Vec<usize> = (0..g.node_cnt()).filter(|&n| !covered[n]).collect();
+    if !missing.is_empty() {
+        warn!(
+            "{} graph nodes are missing from the coverage table (keeping coverage from the GFA), e.g. {}",
+            missing.len(),
+            missing.iter().take(5).map(|&n| g.name(n)).collect::<Vec<_>>().join(",")
+        );
+    }
+}
+
+fn group_color(group: TrioGroup) -> &'static str {
+    match group {
+        TrioGroup::PATERNAL => "#8888FF",
+        TrioGroup::MATERNAL => "#FF8888",
+        TrioGroup::ISSUE => "#FFDE24",
+        TrioGroup::HOMOZYGOUS => "#7900D6",
+    }
+}
+
 fn output_coloring(
     g: &Graph,
     assignments: &trio::AssignmentStorage,
     file_name: &PathBuf,
     hap_names: &(&str, &str),
-) -> Result<(), std::io::Error> {
+) -> std::io::Result<()> {
     let mut output = BufWriter::new(File::create(file_name)?);
     writeln!(output, "node\tassignment\tlength\tinfo\tcolor")?;
     for (node_id, n) in g.all_nodes().enumerate() {
         assert!(g.name2id(&n.name) == node_id);
         if let Some(assign) = assignments.get(node_id) {
-            let color = match assign.group {
-                trio::TrioGroup::PATERNAL => "#8888FF",
-                trio::TrioGroup::MATERNAL => "#FF8888",
-                trio::TrioGroup::ISSUE => "#FFDE24",
-                trio::TrioGroup::HOMOZYGOUS => "#7900D6",
-            };
             writeln!(
                 output,
                 "{}\t{}\t{}\t{}\t{}",
@@ -230,7 +772,7 @@ fn output_coloring(
                 group_str(Some(assign.group), hap_names).to_uppercase(),
                 n.length,
                 assign.info,
-                color
+                group_color(assign.group)
             )?;
         }
     }
@@ -241,9 +783,9 @@ pub fn augment_by_path_search(
     g: &Graph,
     assignments: trio::AssignmentStorage,
     settings: HaploSearchSettings,
-) -> trio::AssignmentStorage {
+) -> Result<trio::AssignmentStorage> {
     info!("Augmenting node annotation by path search. Round 1.");
-    let assignments = augment_by_path_search_round(g, assignments, settings);
+    let assignments = augment_by_path_search_round(g, assignments, settings)?;
     info!("Augmenting node annotation by path search. Round 2.");
     augment_by_path_search_round(g, assignments, settings)
 }
@@ -252,7 +794,7 @@ fn augment_by_path_search_round(
     g: &Graph,
     assignments: trio::AssignmentStorage,
     settings: HaploSearchSettings,
-) -> trio::AssignmentStorage {
+) -> Result<trio::AssignmentStorage> {
     let mut path_searcher =
         HaploSearcher::new(g, &assignments, settings.assigning_stage_adjusted(), None);
 
@@ -266,7 +808,7 @@ fn augment_assignments(
     mut assignments: trio::AssignmentStorage,
     extra_assignments: &trio::AssignmentStorage,
     exclude_homozygous: bool,
-) -> trio::AssignmentStorage {
+) -> Result<trio::AssignmentStorage> {
     for node_id in extra_assignments.assigned() {
         let tentative_group = extra_assignments.group(node_id).unwrap();
         assert!(tentative_group != TrioGroup::ISSUE);
@@ -284,23 +826,18 @@ fn augment_assignments(
                 assignments.assign(node_id, tentative_group, "PathSearch");
             }
             Some(init_group) => {
-                assert!(init_group == tentative_group || init_group == trio::TrioGroup::HOMOZYGOUS)
+                if init_group != tentative_group && init_group != TrioGroup::HOMOZYGOUS {
+                    return Err(Error::Assignments(format!(
+                        "node {} was assigned {:?}, but was used in {:?} haplo-path",
+                        g.name(node_id),
+                        init_group,
+                        tentative_group
+                    )));
+                }
             }
         }
     }
-    assignments
-}
-
-fn weighted_mean_solid_cov(g: &Graph, solid_len_thr: usize) -> f64 {
-    let mut total_len = 0;
-    let mut total_cov = 0.;
-    for n in g.all_nodes() {
-        if n.length >= solid_len_thr {
-            total_len += n.length;
-            total_cov += n.coverage * (n.length as f64);
-        }
-    }
-    total_cov / total_len as f64
+    Ok(assignments)
 }
 
 fn parse_hap_names(hap_names_s: &str) -> Option<(&str, &str)> {
@@ -318,40 +855,38 @@ fn group_str<'a>(o_g: Option<TrioGroup>, hap_names: &'a (&'a str, &'a str)) -> &
     }
 }
 
-pub fn write_paths(
+//(name, path, assignment)
+pub type PathRecord = (String, Path, Option<TrioGroup>);
+
+//Haplo-paths complemented by trivial paths, so that every node is covered
+pub fn haplo_path_records(
     g: &Graph,
     haplo_paths: Vec<trio_walk::HaploPath>,
     assignments: &trio::AssignmentStorage,
     node_usage: &trio::AssignmentStorage,
-    output: &PathBuf,
-    gaf_format: bool,
     hap_names: &(&str, &str),
-) -> Result<(), std::io::Error> {
-    //FIXME buffer
-    let mut output = File::create(output)?;
-    writeln!(output, "name\tpath\tassignment")?;
+) -> Vec<PathRecord> {
+    let mut records = Vec::new();
     for (path, node_id, group) in haplo_paths {
         assert!(path.vertices().contains(&Vertex::forward(node_id)));
         //info!("Identified {:?} path: {}", group, path.print(&g));
-        writeln!(
-            output,
-            "{}_from_{}\t{}\t{}",
-            group_str(Some(group), hap_names),
-            g.node(node_id).name,
-            path.print_format(g, gaf_format),
-            group_str(Some(group), hap_names).to_uppercase()
-        )?;
+        records.push((
+            format!(
+                "{}_from_{}",
+                group_str(Some(group), hap_names),
+                g.node(node_id).name
+            ),
+            path,
+            Some(group),
+        ));
     }
 
-    let mut write_node = |n: &Node, group: Option<TrioGroup>| {
-        writeln!(
-            output,
-            "{}_unused_{}\t{}\t{}",
-            group_str(group, hap_names),
-            n.name,
-            Direction::format_node(&n.name, Direction::FORWARD, gaf_format),
-            group_str(group, hap_names).to_uppercase()
-        )
+    let mut add_node = |node_id: usize, group: Option<TrioGroup>| {
+        records.push((
+            format!("{}_unused_{}", group_str(group, hap_names), g.name(node_id)),
+            Path::new(Vertex::forward(node_id)),
+            group,
+        ))
     };
 
     for (node_id, n) in g.all_nodes().enumerate() {
@@ -363,114 +898,186 @@ pub fn write_paths(
                     "Node: {} length: {} not assigned to any haplotype (adding trivial NA path)",
                     n.name, n.length
                 );
-                write_node(g.node(node_id), None)?;
+                add_node(node_id, None);
             }
             Some(assign) => {
                 if TrioGroup::compatible(assign, TrioGroup::MATERNAL)
                     //not present in haplopaths paths or incompatible
-                    && haplopath_assign.map_or(true,
+                    && haplopath_assign.is_none_or(
                         |x| TrioGroup::incompatible(x, TrioGroup::MATERNAL))
                 {
                     debug!("Node: {} length: {} not present in MATERNAL haplo-paths (adding trivial MATERNAL path)",
                         n.name, n.length);
-                    write_node(g.node(node_id), Some(TrioGroup::MATERNAL))?;
+                    add_node(node_id, Some(TrioGroup::MATERNAL));
                 }
                 if TrioGroup::compatible(assign, TrioGroup::PATERNAL)
                     //not present in haplopaths paths or incompatible
-                    && haplopath_assign.map_or(true,
+                    && haplopath_assign.is_none_or(
                         |x| TrioGroup::incompatible(x, TrioGroup::PATERNAL))
                 {
                     debug!("Node: {} length: {} not present in PATERNAL haplo-paths (adding trivial PATERNAL path)",
                         n.name, n.length);
-                    write_node(g.node(node_id), Some(TrioGroup::PATERNAL))?;
+                    add_node(node_id, Some(TrioGroup::PATERNAL));
                 }
             }
         }
     }
-    Ok(())
+    records
 }
 
-pub fn run_trio_analysis(settings: &TrioSettings) -> Result<(), Box<dyn Error>> {
-    let g = read_graph(&settings.graph)?;
-
-    //for n in g.all_nodes() {
-    //    println!("Node: {} length: {} cov: {}", n.name, n.length, n.coverage);
-    //}
-    //for l in g.all_links() {
-    //    println!("Link: {}", g.l_str(l));
-    //}
-    //write!(output, "{}", g.as_gfa())?;
-
-    let hap_names =
-        parse_hap_names(&settings.hap_names).expect("Problem while parsing haplotype names");
-
-    info!(
-        "Reading trio marker information from {}",
-        &settings.markers.to_str().unwrap()
-    );
-    let trio_infos = trio::read_trio(&settings.markers)?;
+pub fn write_paths(
+    g: &Graph,
+    records: &[PathRecord],
+    output: &PathBuf,
+    gaf_format: bool,
+    hap_names: &(&str, &str),
+) -> std::io::Result<()> {
+    let mut output = BufWriter::new(File::create(output)?);
+    writeln!(output, "name\tpath\tassignment")?;
+    for (name, path, group) in records {
+        writeln!(
+            output,
+            "{}\t{}\t{}",
+            name,
+            path.print_format(g, gaf_format),
+            group_str(*group, hap_names).to_uppercase()
+        )?;
+    }
+    Ok(())
+}
 
-    let solid_cov_est = weighted_mean_solid_cov(&g, settings.solid_len);
-    if settings.suspect_homozygous_cov_coeff > 0. || settings.solid_homozygous_cov_coeff > 0. {
-        info!("Coverage estimate based on long nodes was {solid_cov_est}");
-        if solid_cov_est == 0. {
-            warn!("Looks like the graph didn't have coverage information, which we were hoping to use. \
-                    Consider providing it or changing --suspect-homozygous-cov-coeff and --solid-homozygous-cov-coeff");
+//Graph annotated with assignment colors (CL:z:) and haplotypes (HP:Z:),
+//paths are written as P records (gaps within them as J records)
+pub fn write_gfa(
+    g: &Graph,
+    records: &[PathRecord],
+    assignments: &trio::AssignmentStorage,
+    output: &PathBuf,
+    hap_names: &(&str, &str),
+) -> std::io::Result<()> {
+    let mut output = BufWriter::new(File::create(output)?);
+    g.write_gfa(&mut output, |node_id| match assignments.get(node_id) {
+        None => Vec::new(),
+        Some(assign) => vec![
+            format!("CL:z:{}", group_color(assign.group)),
+            format!(
+                "HP:Z:{}",
+                group_str(Some(assign.group), hap_names).to_uppercase()
+            ),
+        ],
+    })?;
+
+    let mut written_gaps: HashSet<(Vertex, Vertex)> =
+        g.gaps().iter().map(|gap| (gap.start, gap.end)).collect();
+    for (_, path, _) in records {
+        for l in path.links() {
+            if let GeneralizedLink::GAP(gap) = l {
+                if !written_gaps.contains(&(gap.start, gap.end))
+                    && !written_gaps.contains(&(gap.end.rc(), gap.start.rc()))
+                {
+                    writeln!(output, "{}", g.gap_gfa_line(gap))?;
+                    written_gaps.insert((gap.start, gap.end));
+                }
+            }
         }
     }
+    for (name, path, _) in records {
+        writeln!(output, "{}", path.gfa_line(g, name))?;
+    }
+    Ok(())
+}
 
-    let suspect_homozygous_cov = if settings.suspect_homozygous_cov_coeff < 0. {
-        None
-    } else {
-        Some(settings.suspect_homozygous_cov_coeff * solid_cov_est)
-    };
+pub fn write_fasta(g: &Graph, records: &[PathRecord], output: &PathBuf) -> Result<()> {
+    let mut output = BufWriter::new(File::create(output)?);
+    for (name, path, _) in records {
+        if let Some(v) = path
+            .vertices()
+            .iter()
+            .find(|v| g.sequence(v.node_id).is_none())
+        {
+            return Err(Error::Gfa(g.missing_sequence_error(v.node_id)));
+        }
+        //all node sequences are available
+        let seq = path.sequence(g).unwrap();
+        if path.has_unknown_overlaps(g) {
+            warn!("Path {name} includes links with unknown overlaps, which were not trimmed");
+        }
+        writeln!(output, ">{name}\n{seq}")?;
+    }
+    Ok(())
+}
 
-    let solid_homozygous_cov = settings.solid_homozygous_cov_coeff * solid_cov_est;
+//Results of the trio analysis of the graph (or its part)
+struct TrioResults {
+    init_assignments: trio::AssignmentStorage,
+    refined_assignments: trio::AssignmentStorage,
+    final_assignments: trio::AssignmentStorage,
+    haplo_paths: Vec<trio_walk::HaploPath>,
+    node_usage: trio::AssignmentStorage,
+}
 
-    info!("Assigning initial parental groups to the nodes");
-    let assignments = trio::assign_parental_groups(
-        &g,
-        &trio_infos,
-        &GroupAssignmentSettings {
-            assign_cnt: settings.marker_cnt,
-            assign_sparsity: settings.marker_sparsity,
-            assign_ratio: settings.marker_ratio,
-            solid_ratio: settings.solid_ratio.unwrap_or(settings.marker_ratio),
-            issue_len: settings.issue_len,
-            issue_cnt: settings.issue_cnt.unwrap_or(settings.marker_cnt),
-            issue_sparsity: settings.issue_sparsity.unwrap_or(settings.marker_sparsity),
-            issue_ratio: settings.issue_ratio.unwrap_or(settings.marker_ratio),
-        },
-        settings.solid_len,
-        solid_homozygous_cov,
-    );
+fn lift_assignments(
+    target: &mut trio::AssignmentStorage,
+    assignments: &trio::AssignmentStorage,
+    node_ids: &[usize],
+) {
+    for node_id in assignments.assigned() {
+        let assign = assignments.get(node_id).unwrap();
+        match assign.confidence {
+            Some(p) => target.assign_with_confidence(
+                node_ids[node_id],
+                assign.group,
+                assign.info.clone(),
+                p,
+            ),
+            None => target.assign(node_ids[node_id], assign.group, assign.info.clone()),
+        };
+    }
+}
 
-    let raw_cnts = trio_infos
-        .into_iter()
-        .map(|ti| (g.name2id(&ti.node_name), ti))
-        .collect::<HashMap<usize, trio::TrioInfo>>();
+impl TrioResults {
+    fn new() -> TrioResults {
+        TrioResults {
+            init_assignments: trio::AssignmentStorage::new(),
+            refined_assignments: trio::AssignmentStorage::new(),
+            final_assignments: trio::AssignmentStorage::new(),
+            haplo_paths: Vec::new(),
+            node_usage: trio::AssignmentStorage::new(),
+        }
+    }
 
-    if let Some(output) = &settings.init_assign {
-        info!(
-            "Writing initial node annotation to {}",
-            output.to_str().unwrap()
+    //adds results obtained for the subgraph, node_ids[i] is the id of subgraph node i
+    fn merge_lifted(&mut self, other: &TrioResults, node_ids: &[usize]) {
+        lift_assignments(
+            &mut self.init_assignments,
+            &other.init_assignments,
+            node_ids,
+        );
+        lift_assignments(
+            &mut self.refined_assignments,
+            &other.refined_assignments,
+            node_ids,
+        );
+        lift_assignments(
+            &mut self.final_assignments,
+            &other.final_assignments,
+            node_ids,
         );
-        output_coloring(&g, &assignments, output, &hap_names)?;
+        lift_assignments(&mut self.node_usage, &other.node_usage, node_ids);
+        let map = |v: Vertex| {
+            Some(Vertex {
+                node_id: node_ids[v.node_id],
+                direction: v.direction,
+            })
+        };
+        for (path, node_id, group) in &other.haplo_paths {
+            self.haplo_paths
+                .push((path.map_vertices(map).unwrap(), node_ids[*node_id], *group));
+        }
     }
+}
 
-    info!("Marking homozygous nodes");
-    let assigner = trio::HomozygousAssigner::new(
-        &g,
-        assignments,
-        settings.trusted_len,
-        suspect_homozygous_cov,
-        settings.solid_len,
-        solid_homozygous_cov,
-        settings.max_homozygous_len,
-    );
-
-    let assignments = assigner.run();
-
+fn haplo_search_settings(settings: &TrioSettings, solid_cov_est: f64) -> HaploSearchSettings {
     let mut search_settings = HaploSearchSettings {
         solid_len: settings.solid_len,
         trusted_len: settings.trusted_len,
@@ -486,6 +1093,9 @@ pub fn run_trio_analysis(settings: &TrioSettings) -> Result<(), Box<dyn Error>>
         good_side_cov_gap: settings.good_side_cov_gap,
         min_gap_size: settings.min_gap_size as i64,
         default_gap_size: settings.default_gap_size as i64,
+        min_link_support: settings.min_link_support,
+        link_support_ratio: settings.link_support_ratio,
+        min_seed_confidence: settings.min_seed_posterior,
         ..HaploSearchSettings::default()
     };
 
@@ -509,12 +1119,116 @@ pub fn run_trio_analysis(settings: &TrioSettings) -> Result<(), Box<dyn Error>>
             info!("Will only fill bubbles between solid or homozygous nodes");
         }
     }
+    search_settings
+}
 
-    let assignments = augment_by_path_search(&g, assignments, search_settings);
+//Split nodes get marker counts from positional markers, counts of other nodes are kept
+fn split_at_switches(
+    g: &Graph,
+    trio_infos: Vec<trio::TrioInfo>,
+    markers: &HashMap<String, Vec<switches::PositionalMarker>>,
+    node_switches: &[switches::Switch],
+) -> (Graph, Vec<trio::TrioInfo>) {
+    let mut splits: HashMap<usize, Vec<usize>> = HashMap::new();
+    for s in node_switches {
+        splits.entry(s.node_id).or_default().push(s.split_pos());
+    }
+    let (split_g, pieces) = g.split_nodes(&splits);
+    let mut infos: Vec<trio::TrioInfo> = trio_infos
+        .into_iter()
+        .filter(|ti| pieces[g.name2id(&ti.node_name)].len() == 1)
+        .collect();
+    for (node_id, node_pieces) in pieces.iter().enumerate() {
+        if node_pieces.len() > 1 {
+            let positions: Vec<usize> = node_pieces[..node_pieces.len() - 1]
+                .iter()
+                .scan(0, |pos, &piece| {
+                    *pos += split_g.node_length(piece);
+                    Some(*pos)
+                })
+                .collect();
+            let names: Vec<&str> = node_pieces.iter().map(|&p| split_g.name(p)).collect();
+            infos.extend(switches::piece_counts(
+                &markers[g.name(node_id)],
+                &positions,
+                &names,
+            ));
+        }
+    }
+    (split_g, infos)
+}
+
+//Node assignment and haplo-path search.
+//Coverage estimate is provided, since the graph can be a part of the analyzed one.
+fn trio_pipeline(
+    g: &Graph,
+    trio_infos: &[trio::TrioInfo],
+    settings: &TrioSettings,
+    search_settings: HaploSearchSettings,
+    solid_cov_est: f64,
+    model: Option<&MarkerModel>,
+    thresholds: &MarkerThresholds,
+) -> Result<TrioResults> {
+    let suspect_homozygous_cov = if settings.suspect_homozygous_cov_coeff < 0. {
+        None
+    } else {
+        Some(settings.suspect_homozygous_cov_coeff * solid_cov_est)
+    };
+
+    let solid_homozygous_cov = settings.solid_homozygous_cov_coeff * solid_cov_est;
+
+    info!("Assigning initial parental groups to the nodes");
+    let init_assignments = match model {
+        Some(model) => marker_model::assign_by_model(
+            g,
+            trio_infos,
+            model,
+            settings.min_posterior,
+            settings.issue_len,
+        )?,
+        None => trio::assign_parental_groups(
+            g,
+            trio_infos,
+            &GroupAssignmentSettings {
+                assign_cnt: thresholds.marker_cnt,
+                assign_sparsity: thresholds.marker_sparsity,
+                assign_ratio: thresholds.marker_ratio,
+                solid_ratio: settings.solid_ratio.unwrap_or(thresholds.marker_ratio),
+                issue_len: settings.issue_len,
+                issue_cnt: settings.issue_cnt.unwrap_or(thresholds.marker_cnt),
+                issue_sparsity: settings
+                    .issue_sparsity
+                    .unwrap_or(thresholds.marker_sparsity),
+                issue_ratio: settings.issue_ratio.unwrap_or(thresholds.marker_ratio),
+            },
+            settings.solid_len,
+            solid_homozygous_cov,
+        )?,
+    };
+
+    let raw_cnts = trio_infos
+        .iter()
+        .map(|ti| (g.name2id(&ti.node_name), ti.clone()))
+        .collect::<HashMap<usize, trio::TrioInfo>>();
 
-    let assignments = if settings.assign_tangles {
+    info!("Marking homozygous nodes");
+    let assigner = trio::HomozygousAssigner::new(
+        g,
+        init_assignments.clone(),
+        settings.trusted_len,
+        suspect_homozygous_cov,
+        settings.solid_len,
+        solid_homozygous_cov,
+        settings.max_homozygous_len,
+    );
+
+    let assignments = assigner.run();
+
+    let assignments = augment_by_path_search(g, assignments, search_settings)?;
+
+    let refined_assignments = if settings.assign_tangles {
         assign_short_node_tangles(
-            &g,
+            g,
             assignments,
             settings.solid_len,
             TangleAssignmentSettings {
@@ -527,38 +1241,305 @@ pub fn run_trio_analysis(settings: &TrioSettings) -> Result<(), Box<dyn Error>>
         assignments
     };
 
-    if let Some(output) = &settings.refined_assign {
+    let mut path_searcher =
+        HaploSearcher::new(g, &refined_assignments, search_settings, Some(&raw_cnts));
+    if settings.gfa_paths {
+        info!("Using {} paths from the GFA as seeds", g.paths().len());
+        path_searcher.set_seeds(g.paths().iter().map(|(_, p)| p.clone()).collect());
+    }
+
+    let haplo_paths = path_searcher.find_all();
+    let node_usage = path_searcher.take_used();
+
+    let final_assignments =
+        augment_assignments(g, refined_assignments.clone(), &node_usage, false)?;
+
+    Ok(TrioResults {
+        init_assignments,
+        refined_assignments,
+        final_assignments,
+        haplo_paths,
+        node_usage,
+    })
+}
+
+pub fn run_trio_analysis(settings: &TrioSettings) -> Result<()> {
+    settings.validate()?;
+
+    let mut g = read_graph(
+        &settings.graph,
+        settings.fasta.is_some(),
+        settings.graph_cache,
+    )?;
+
+    if let Some(coverage_fn) = &settings.coverage {
         info!(
-            "Writing refined node annotation to {}",
+            "Reading node coverage from {}",
+            coverage_fn.to_str().unwrap()
+        );
+        apply_coverage(&mut g, &read_coverage(coverage_fn)?);
+    }
+
+    //for n in g.all_nodes() {
+    //    println!("Node: {} length: {} cov: {}", n.name, n.length, n.coverage);
+    //}
+    //for l in g.all_links() {
+    //    println!("Link: {}", g.l_str(l));
+    //}
+    //write!(output, "{}", g.as_gfa())?;
+
+    //checked by validate
+    let hap_names = parse_hap_names(&settings.hap_names).unwrap();
+
+    let mut trio_infos = match (&settings.markers, &settings.gaf, &settings.read_haplotypes) {
+        (Some(markers), _, _) => {
+            info!(
+                "Reading trio marker information from {}",
+                markers.to_str().unwrap()
+            );
+            let trio_infos = trio::read_trio_with(
+                markers,
+                &trio::MarkerReadSettings {
+                    hap_names: Some(hap_names),
+                    duplicates: settings.duplicate_markers,
+                },
+            )?;
+            trio::check_marker_nodes(&g, &trio_infos)?;
+            trio_infos
+        }
+        (None, Some(gaf), Some(read_haplotypes)) => {
+            info!(
+                "Reading read haplotypes from {}",
+                read_haplotypes.to_str().unwrap()
+            );
+            let read_haplotypes = reads::read_read_haplotypes(read_haplotypes, hap_names)?;
+            info!(
+                "Deriving node marker counts from read alignments in {}",
+                gaf.to_str().unwrap()
+            );
+            reads::aggregate_read_haplotypes(
+                &g,
+                gaf,
+                &read_haplotypes,
+                &reads::ReadAggregationSettings {
+                    min_mapq: settings.read_min_mapq,
+                    bases_per_marker: settings.bases_per_marker,
+                },
+            )?
+        }
+        //guaranteed by the argument parser
+        _ => unreachable!(),
+    };
+
+    if let Some(positions_fn) = &settings.marker_positions {
+        info!(
+            "Reading positional marker information from {}",
+            positions_fn.to_str().unwrap()
+        );
+        let markers = switches::read_positional_markers(positions_fn, hap_names)?;
+        let segmentations = switches::segment_nodes(
+            &g,
+            &markers,
+            &switches::SegmentationSettings {
+                switch_penalty: settings.switch_penalty,
+                ..switches::SegmentationSettings::default()
+            },
+        )?;
+        let node_switches: Vec<switches::Switch> =
+            segmentations.iter().flat_map(|s| s.switches()).collect();
+        info!(
+            "Detected {} haplotype switches within {} nodes",
+            node_switches.len(),
+            segmentations
+                .iter()
+                .filter(|s| s.segments.len() > 1)
+                .count()
+        );
+
+        if let Some(output) = &settings.switches {
+            info!("Writing haplotype switches to {}", output.to_str().unwrap());
+            let mut output = BufWriter::new(File::create(output)?);
+            switches::write_switches(&g, &node_switches, hap_names, &mut output)?;
+        }
+
+        if settings.split_at_switches {
+            (g, trio_infos) = split_at_switches(&g, trio_infos, &markers, &node_switches);
+            info!("Node count after splitting: {}", g.node_cnt());
+        }
+    }
+
+    let solid_cov_est = weighted_mean_solid_cov(&g, settings.solid_len);
+    if settings.suspect_homozygous_cov_coeff > 0. || settings.solid_homozygous_cov_coeff > 0. {
+        info!("Coverage estimate based on long nodes was {solid_cov_est}");
+        if solid_cov_est == 0. {
+            warn!("Looks like the graph didn't have coverage information, which we were hoping to use. \
+                    Consider providing it or changing --suspect-homozygous-cov-coeff and --solid-homozygous-cov-coeff");
+        }
+    }
+
+    let search_settings = haplo_search_settings(settings, solid_cov_est);
+
+    //estimated on the whole graph, even if the components are processed independently
+    let model = if settings.marker_model {
+        Some(MarkerModel::estimate(
+            &g,
+            &trio_infos,
+            settings.solid_len,
+            settings.marker_density.map(|d| d / 10_000.),
+            settings.marker_error_rate,
+        )?)
+    } else {
+        None
+    };
+    let calibration_model = if settings.calibrate {
+        Some(MarkerModel::fit_phased(
+            &g,
+            &trio_infos,
+            settings.solid_len,
+            settings.marker_density.map(|d| d / 10_000.),
+            settings.marker_error_rate,
+        )?)
+    } else {
+        None
+    };
+
+    let thresholds = match &calibration_model {
+        Some(model) => {
+            let thresholds = settings.marker_thresholds(MarkerThresholds::calibrated(model));
+            settings.check_ratios(thresholds.marker_ratio)?;
+            thresholds
+        }
+        None => settings.marker_thresholds(MarkerThresholds::default()),
+    };
+    info!(
+        "Marker thresholds: count {}, sparsity 1 in {}, ratio {:.2}:1",
+        thresholds.marker_cnt, thresholds.marker_sparsity, thresholds.marker_ratio
+    );
+
+    if let Some(output) = &settings.run_report {
+        info!("Writing run report to {}", output.to_str().unwrap());
+        write_run_report(
+            settings,
+            &thresholds,
+            //fit behind the thresholds if calibrated
+            calibration_model.as_ref().or(model.as_ref()),
+            solid_cov_est,
+            output,
+        )?;
+    }
+
+    if let (Some(model), Some(output)) = (&model, &settings.posteriors) {
+        info!(
+            "Writing marker model posteriors to {}",
             output.to_str().unwrap()
         );
-        output_coloring(&g, &assignments, output, &hap_names)?;
+        write_posteriors(&g, &trio_infos, model, output)?;
     }
-    let mut path_searcher = HaploSearcher::new(&g, &assignments, search_settings, Some(&raw_cnts));
 
-    let haplo_paths = path_searcher.find_all();
-    let node_usage = path_searcher.take_used();
+    let results = if settings.threads > 1 {
+        info!(
+            "Processing connected components independently using {} threads",
+            settings.threads
+        );
+        let mut results = TrioResults::new();
+        for (part_results, node_ids) in
+            components::process_components(&g, settings.threads, |part| {
+                let part_infos: Vec<trio::TrioInfo> = trio_infos
+                    .iter()
+                    .filter(|ti| part.try_name2id(&ti.node_name).is_some())
+                    .cloned()
+                    .collect();
+                trio_pipeline(
+                    part,
+                    &part_infos,
+                    settings,
+                    search_settings,
+                    solid_cov_est,
+                    model.as_ref(),
+                    &thresholds,
+                )
+            })
+        {
+            results.merge_lifted(&part_results?, &node_ids);
+        }
+        results
+    } else {
+        trio_pipeline(
+            &g,
+            &trio_infos,
+            settings,
+            search_settings,
+            solid_cov_est,
+            model.as_ref(),
+            &thresholds,
+        )?
+    };
 
-    let assignments = augment_assignments(&g, assignments, &node_usage, false);
+    if let Some(output) = &settings.init_assign {
+        info!(
+            "Writing initial node annotation to {}",
+            output.to_str().unwrap()
+        );
+        output_coloring(&g, &results.init_assignments, output, &hap_names)?;
+    }
+
+    if let Some(output) = &settings.refined_assign {
+        info!(
+            "Writing refined node annotation to {}",
+            output.to_str().unwrap()
+        );
+        output_coloring(&g, &results.refined_assignments, output, &hap_names)?;
+    }
 
     if let Some(output) = &settings.final_assign {
         info!(
             "Writing final node annotation to {}",
             output.to_str().unwrap()
         );
-        output_coloring(&g, &assignments, output, &hap_names)?;
+        output_coloring(&g, &results.final_assignments, output, &hap_names)?;
     }
 
+    let assignments = results.final_assignments;
+    let path_records = haplo_path_records(
+        &g,
+        results.haplo_paths,
+        &assignments,
+        &results.node_usage,
+        &hap_names,
+    );
+
     if let Some(output) = &settings.paths {
         info!("Outputting haplo-paths to {}", output.to_str().unwrap());
-        write_paths(
+        write_paths(&g, &path_records, output, settings.gaf_format, &hap_names)?;
+    }
+
+    if let Some(output) = &settings.fasta {
+        info!(
+            "Outputting haplo-path sequences to {}",
+            output.to_str().unwrap()
+        );
+        write_fasta(&g, &path_records, output)?;
+    }
+
+    if let Some(output) = &settings.annotated_gfa {
+        info!(
+            "Outputting annotated graph with haplo-paths to {}",
+            output.to_str().unwrap()
+        );
+        write_gfa(&g, &path_records, &assignments, output, &hap_names)?;
+    }
+
+    if let Some(output) = &settings.component_report {
+        info!(
+            "Outputting connected components of haplo-paths to {}",
+            output.to_str().unwrap()
+        );
+        write_component_report(
             &g,
-            haplo_paths,
-            &assignments,
-            &node_usage,
+            path_records
+                .iter()
+                .map(|(name, path, _)| (name.as_str(), path)),
             output,
-            settings.gaf_format,
-            &hap_names,
         )?;
     }
 
@@ -566,18 +1547,189 @@ pub fn run_trio_analysis(settings: &TrioSettings) -> Result<(), Box<dyn Error>>
     Ok(())
 }
 
+//parameter<TAB>value<TAB>source (user, calibrated, default or fit)
+fn write_run_report(
+    settings: &TrioSettings,
+    thresholds: &MarkerThresholds,
+    model: Option<&MarkerModel>,
+    solid_cov_est: f64,
+    output: &PathBuf,
+) -> std::io::Result<()> {
+    let source = |user: bool| {
+        if user {
+            "user"
+        } else if settings.calibrate {
+            "calibrated"
+        } else {
+            "default"
+        }
+    };
+    let mut output = BufWriter::new(File::create(output)?);
+    writeln!(output, "parameter\tvalue\tsource")?;
+    writeln!(
+        output,
+        "marker_cnt\t{}\t{}",
+        thresholds.marker_cnt,
+        source(settings.marker_cnt.is_some())
+    )?;
+    writeln!(
+        output,
+        "marker_sparsity\t{}\t{}",
+        thresholds.marker_sparsity,
+        source(settings.marker_sparsity.is_some())
+    )?;
+    writeln!(
+        output,
+        "marker_ratio\t{:.3}\t{}",
+        thresholds.marker_ratio,
+        source(settings.marker_ratio.is_some())
+    )?;
+    if let Some(model) = model {
+        writeln!(
+            output,
+            "marker_density_per_10kb\t{:.4}\t{}",
+            model.density * 10_000.,
+            if settings.marker_density.is_some() {
+                "user"
+            } else {
+                "fit"
+            }
+        )?;
+        writeln!(
+            output,
+            "marker_error_rate\t{:.5}\t{}",
+            model.error_rate,
+            if settings.marker_error_rate.is_some() {
+                "user"
+            } else {
+                "fit"
+            }
+        )?;
+    }
+    writeln!(output, "solid_cov\t{solid_cov_est:.3}\tfit")?;
+    Ok(())
+}
+
+pub fn write_posteriors(
+    g: &Graph,
+    trio_infos: &[trio::TrioInfo],
+    model: &MarkerModel,
+    output: &PathBuf,
+) -> std::io::Result<()> {
+    let mut output = BufWriter::new(File::create(output)?);
+    writeln!(
+        output,
+        "node\tlength\tmat\tpat\tmaternal\tpaternal\thomozygous\tissue"
+    )?;
+    for ti in trio_infos {
+        let length = g.node_length(g.name2id(&ti.node_name));
+        let p = model.posterior(ti.mat(), ti.pat(), length);
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}",
+            ti.node_name,
+            length,
+            ti.mat(),
+            ti.pat(),
+            p.maternal,
+            p.paternal,
+            p.homozygous,
+            p.issue
+        )?;
+    }
+    Ok(())
+}
+
+//For every path reports the connected component it belongs to, its node count and total node length
+pub fn write_component_report<'a>(
+    g: &Graph,
+    paths: impl Iterator<Item = (&'a str, &'a Path)>,
+    output: &PathBuf,
+) -> std::io::Result<()> {
+    let component_ids = components::component_ids(g);
+    let mut component_sizes = HashMap::new();
+    for (node_id, n) in g.all_nodes().enumerate() {
+        let size = component_sizes
+            .entry(component_ids[node_id])
+            .or_insert((0, 0));
+        size.0 += 1;
+        size.1 += n.length;
+    }
+    let mut output = BufWriter::new(File::create(output)?);
+    writeln!(output, "name\tcomponent\tcomponent_nodes\tcomponent_length")?;
+    for (name, path) in paths {
+        let component_id = component_ids[path.start().node_id];
+        let (node_cnt, length) = component_sizes[&component_id];
+        writeln!(output, "{name}\t{component_id}\t{node_cnt}\t{length}")?;
+    }
+    Ok(())
+}
+
+//kept for existing callers, see run_primary_alt for component report and threads
+#[allow(clippy::ptr_arg)]
 pub fn run_primary_alt_analysis(
     graph_fn: &PathBuf,
     colors_fn: &Option<String>,
     paths_fn: &Option<String>,
     gaf_paths: bool,
-) -> Result<(), Box<dyn Error>> {
-    let g = read_graph(graph_fn)?;
+) -> Result<()> {
+    run_primary_alt(&PrimaryAltSettings {
+        graph: graph_fn.clone(),
+        assign: colors_fn.as_ref().map(PathBuf::from),
+        paths: paths_fn.as_ref().map(PathBuf::from),
+        gaf_format: gaf_paths,
+        component_report: None,
+        threads: 1,
+    })
+}
+
+pub fn run_primary_alt(settings: &PrimaryAltSettings) -> Result<()> {
+    if settings.threads == 0 {
+        return Err(Error::Settings(String::from(
+            "--threads should be positive",
+        )));
+    }
+    let g = read_graph(&settings.graph, false, false)?;
+    let gaf_paths = settings.gaf_format;
+    let threads = settings.threads;
     let unique_block_len = 500_000;
-    let linear_blocks = pseudo_hap::pseudo_hap_decompose(&g, unique_block_len);
+    let linear_blocks = if threads > 1 {
+        info!("Processing connected components independently using {threads} threads");
+        components::process_components(&g, threads, |part| {
+            pseudo_hap::pseudo_hap_decompose(part, unique_block_len)
+        })
+        .into_iter()
+        .flat_map(|(blocks, node_ids)| {
+            blocks
+                .into_iter()
+                .map(|b| b.lift(&node_ids))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+    } else {
+        pseudo_hap::pseudo_hap_decompose(&g, unique_block_len)
+    };
 
-    if let Some(output) = colors_fn {
-        info!("Writing node colors to {}", output);
+    if let Some(output) = &settings.component_report {
+        info!(
+            "Outputting connected components of primary paths to {}",
+            output.to_str().unwrap()
+        );
+        let names = (0..linear_blocks.len())
+            .map(|block_id| format!("primary_{block_id}"))
+            .collect::<Vec<_>>();
+        write_component_report(
+            &g,
+            names
+                .iter()
+                .map(|n| n.as_str())
+                .zip(linear_blocks.iter().map(|b| b.instance_path())),
+            output,
+        )?;
+    }
+
+    if let Some(output) = &settings.assign {
+        info!("Writing node colors to {}", output.to_str().unwrap());
         let mut output = File::create(output)?;
 
         let mut primary_nodes = HashSet::new();
@@ -614,8 +1766,8 @@ pub fn run_primary_alt_analysis(
 
     let used: HashSet<usize> = linear_blocks.iter().flat_map(|b| b.all_nodes()).collect();
 
-    if let Some(output) = paths_fn {
-        info!("Outputting paths in {}", output);
+    if let Some(output) = &settings.paths {
+        info!("Outputting paths in {}", output.to_str().unwrap());
         let mut output = File::create(output)?;
 
         writeln!(output, "name\tlen\tpath\tassignment")?;
@@ -656,3 +1808,318 @@ pub fn run_primary_alt_analysis(
     info!("All done");
     Ok(())
 }
+
+//Copies header and lines referring to the specified nodes (in the first column)
+fn restrict_table(
+    input: &PathBuf,
+    output: &PathBuf,
+    node_names: &HashSet<&str>,
+) -> std::io::Result<()> {
+    let mut output = BufWriter::new(File::create(output)?);
+    for line in BufReader::new(File::open(input)?).lines() {
+        let line = line?;
+        let split: Vec<&str> = line.trim().split('\t').collect();
+        if trio::is_header_row(&split, None) || node_names.contains(split[0]) {
+            writeln!(output, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run_subgraph_extraction(settings: &SubgraphSettings) -> Result<()> {
+    let mut g = read_graph(&settings.graph, true, false)?;
+
+    let mut seeds = Vec::new();
+    for name in settings.nodes.split(',') {
+        match g.try_name2id(name) {
+            Some(node_id) => seeds.push(node_id),
+            None => {
+                return Err(Error::Settings(format!(
+                    "node {name} is missing from the graph"
+                )))
+            }
+        }
+    }
+
+    let radius = match (settings.hops, settings.bases) {
+        (Some(hops), _) => Radius::Hops(hops),
+        (None, Some(bases)) => Radius::Bases(bases),
+        (None, None) => Radius::Unbounded,
+    };
+    let kept: HashSet<usize> = neighborhood(
+        &g,
+        &seeds,
+        radius,
+        settings.stop_at_long.unwrap_or(usize::MAX),
+    )
+    .into_iter()
+    .collect();
+    info!(
+        "Extracting subgraph of {} nodes around {} specified nodes",
+        kept.len(),
+        seeds.len()
+    );
+    g.retain_nodes(|node_id| kept.contains(&node_id));
+
+    info!("Writing subgraph to {}", settings.output.to_str().unwrap());
+    let mut output = BufWriter::new(File::create(&settings.output)?);
+    write!(output, "{}", g.as_gfa())?;
+
+    let node_names: HashSet<&str> = g.all_nodes().map(|n| n.name.as_str()).collect();
+    if let (Some(markers), Some(output)) = (&settings.markers, &settings.markers_out) {
+        info!("Writing subgraph markers to {}", output.to_str().unwrap());
+        restrict_table(markers, output, &node_names)?;
+    }
+    if let (Some(assign), Some(output)) = (&settings.assign, &settings.assign_out) {
+        info!(
+            "Writing subgraph node annotation to {}",
+            output.to_str().unwrap()
+        );
+        restrict_table(assign, output, &node_names)?;
+    }
+
+    info!("All done");
+    Ok(())
+}
+
+pub fn run_stats(settings: &StatsSettings) -> Result<()> {
+    let g = read_graph(&settings.graph, false, false)?;
+    info!("Computing graph statistics");
+    let stats = GraphStats::compute(
+        &g,
+        &StatsParams {
+            solid_len: settings.solid_len,
+            tangle_size: settings.tangle_size,
+        },
+    );
+    info!(
+        "Writing statistics to {}",
+        settings.output.to_str().unwrap()
+    );
+    let mut output = BufWriter::new(File::create(&settings.output)?);
+    if settings.json {
+        stats.write_json(&mut output)?;
+    } else {
+        stats.write_tsv(&mut output)?;
+    }
+    info!("All done");
+    Ok(())
+}
+
+fn read_kmer_set(path: &PathBuf, k: usize) -> Result<KmerSet> {
+    info!("Reading k-mers from {}", path.to_str().unwrap());
+    let set = KmerSet::read(path, k)?;
+    info!("{} distinct canonical {}-mers loaded", set.len(), k);
+    Ok(set)
+}
+
+pub fn run_marker_counting(settings: &MarkersSettings) -> Result<()> {
+    let hap_names = parse_hap_names(&settings.hap_names).ok_or_else(|| {
+        Error::Settings(format!(
+            "expected two comma separated haplotype names, got '{}'",
+            settings.hap_names
+        ))
+    })?;
+    //fail early on invalid k
+    KmerSet::new(settings.k)?;
+    let g = read_graph(&settings.graph, true, false)?;
+    let mat = read_kmer_set(&settings.mat, settings.k)?;
+    let pat = read_kmer_set(&settings.pat, settings.k)?;
+
+    if let Some(prefix) = &settings.save_kmers {
+        for (set, name) in [(&mat, "mat"), (&pat, "pat")] {
+            let mut path = prefix.as_os_str().to_owned();
+            path.push(format!(".{name}.rkk"));
+            info!("Writing binary k-mer set to {}", path.to_str().unwrap());
+            let mut output = BufWriter::new(File::create(&path)?);
+            set.write_binary(&mut output)?;
+            output.flush()?;
+        }
+    }
+
+    info!("Counting marker hits within {} nodes", g.node_cnt());
+    let hits = kmers::count_hits(&g, &mat, &pat)?;
+
+    info!(
+        "Writing marker counts to {}",
+        settings.output.to_str().unwrap()
+    );
+    let mut output = BufWriter::new(File::create(&settings.output)?);
+    writeln!(output, "node\t{}\t{}", hap_names.0, hap_names.1)?;
+    for h in &hits {
+        let info = h.trio_info(&g);
+        writeln!(output, "{}\t{}\t{}", info.node_name, info.mat(), info.pat())?;
+    }
+
+    if let Some(positions_fn) = &settings.positions {
+        info!(
+            "Writing marker positions to {}",
+            positions_fn.to_str().unwrap()
+        );
+        let mut output = BufWriter::new(File::create(positions_fn)?);
+        writeln!(output, "node\tpos\thaplotype")?;
+        for h in &hits {
+            for (pos, group) in h.positions() {
+                writeln!(
+                    output,
+                    "{}\t{}\t{}",
+                    g.name(h.node_id),
+                    pos,
+                    group_str(Some(group), &hap_names)
+                )?;
+            }
+        }
+    }
+
+    info!("All done");
+    Ok(())
+}
+
+//Returns the number of detected problems
+pub fn run_validation(settings: &ValidateSettings) -> Result<usize> {
+    let hap_names = parse_hap_names(&settings.hap_names).ok_or_else(|| {
+        Error::Settings(format!(
+            "expected two comma separated haplotype names, got '{}'",
+            settings.hap_names
+        ))
+    })?;
+    let graph_fn = settings.graph.to_str().unwrap();
+    info!("Checking graph {graph_fn}");
+    let (gfa_issues, segment_names) = Graph::lint_gfa(BufReader::new(File::open(&settings.graph)?));
+    info!("{} problems found in the graph", gfa_issues.len());
+
+    let mut output = BufWriter::new(File::create(&settings.output)?);
+    writeln!(output, "file\tline\trecord\tfield\tproblem")?;
+    for e in &gfa_issues {
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}\t{}",
+            graph_fn,
+            e.line,
+            e.record,
+            e.field,
+            e.description()
+        )?;
+    }
+
+    let mut issue_cnt = gfa_issues.len();
+    if let Some(markers) = &settings.markers {
+        let markers_fn = markers.to_str().unwrap();
+        info!("Checking markers {markers_fn}");
+        let marker_issues = trio::lint_trio(markers, Some(hap_names), &|name| {
+            segment_names.contains(name)
+        })?;
+        info!("{} problems found in the markers", marker_issues.len());
+        for issue in &marker_issues {
+            writeln!(
+                output,
+                "{}\t{}\t-\t{}\t{}",
+                markers_fn,
+                issue.line,
+                issue.node_name,
+                issue.description()
+            )?;
+        }
+        issue_cnt += marker_issues.len();
+    }
+    Ok(issue_cnt)
+}
+
+pub fn run_poly_analysis(settings: &PolySettings) -> Result<()> {
+    if settings.marker_ratio < 1. {
+        return Err(Error::Settings(String::from(
+            "--marker-ratio can't be smaller than 1",
+        )));
+    }
+    let g = read_graph(&settings.graph, false, false)?;
+
+    let hap_names: Option<Vec<String>> = settings
+        .hap_names
+        .as_ref()
+        .map(|s| s.split(',').map(String::from).collect());
+    info!(
+        "Reading haplotype marker information from {}",
+        settings.markers.to_str().unwrap()
+    );
+    let (hap_names, infos) = trio::read_haplotype_markers(
+        &settings.markers,
+        hap_names.as_deref(),
+        settings.duplicate_markers,
+    )?;
+    info!("Haplotypes: {}", hap_names.join(","));
+
+    let assignments = trio::assign_haplo_sets(
+        &g,
+        &infos,
+        hap_names.len(),
+        &trio::HaploAssignmentSettings {
+            assign_cnt: settings.marker_cnt,
+            assign_sparsity: settings.marker_sparsity,
+            assign_ratio: settings.marker_ratio,
+        },
+    )?;
+
+    if let Some(output) = &settings.assign {
+        info!("Writing node annotation to {}", output.to_str().unwrap());
+        let mut output = BufWriter::new(File::create(output)?);
+        writeln!(output, "node\tassignment\tlength\tinfo")?;
+        for (node_id, n) in g.all_nodes().enumerate() {
+            if let Some(assign) = assignments.get(node_id) {
+                writeln!(
+                    output,
+                    "{}\t{}\t{}\t{}",
+                    n.name,
+                    assign.set.format(&hap_names),
+                    n.length,
+                    assign.info
+                )?;
+            }
+        }
+    }
+
+    if let Some(output) = &settings.paths {
+        let search_settings = HaploSearchSettings {
+            solid_len: settings.solid_len,
+            trusted_len: settings.trusted_len,
+            fill_bubbles: settings.try_fill_bubbles,
+            ..HaploSearchSettings::default()
+        };
+        let hap_paths = trio_walk::haplotype_paths(&g, &assignments, search_settings);
+
+        info!("Outputting haplo-paths to {}", output.to_str().unwrap());
+        let mut output = BufWriter::new(File::create(output)?);
+        writeln!(output, "name\tpath\tassignment")?;
+        for (hap, paths) in hap_paths.iter().enumerate() {
+            let mut used = HashSet::new();
+            for (path, node_id) in paths {
+                used.extend(path.vertices().iter().map(|v| v.node_id));
+                writeln!(
+                    output,
+                    "{}_from_{}\t{}\t{}",
+                    hap_names[hap],
+                    g.name(*node_id),
+                    path.print_format(&g, settings.gaf_format),
+                    hap_names[hap]
+                )?;
+            }
+            //nodes assigned to the haplotype, but not used by its paths
+            for (node_id, n) in g.all_nodes().enumerate() {
+                if !used.contains(&node_id)
+                    && assignments.set(node_id).is_some_and(|s| s.contains(hap))
+                {
+                    writeln!(
+                        output,
+                        "{}_unused_{}\t{}\t{}",
+                        hap_names[hap],
+                        n.name,
+                        Path::new(Vertex::forward(node_id)).print_format(&g, settings.gaf_format),
+                        hap_names[hap]
+                    )?;
+                }
+            }
+        }
+    }
+
+    info!("All done");
+    Ok(())
+}
@@ -14,7 +14,19 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Trio-marker based analysis
-    Trio(rukki::TrioSettings),
+    Trio(Box<rukki::TrioSettings>),
+    /// Primary/alt decomposition of the graph (no markers required)
+    Primary(Box<rukki::PrimaryAltSettings>),
+    /// Extraction of the subgraph around specified nodes
+    Subgraph(Box<rukki::SubgraphSettings>),
+    /// Assembly graph summary statistics
+    Stats(Box<rukki::StatsSettings>),
+    /// Haplotype-marker based analysis of polyploid genomes
+    Poly(Box<rukki::PolySettings>),
+    /// Check GFA and marker inputs, reporting all detected problems
+    Validate(Box<rukki::ValidateSettings>),
+    /// Counting of parent-specific k-mers within the graph nodes
+    Markers(Box<rukki::MarkersSettings>),
 }
 
 fn main() {
@@ -28,15 +40,52 @@ fn main() {
 
     let args = Args::parse();
 
-    match &args.subcmd {
+    let result = match &args.subcmd {
         Commands::Trio(settings) => {
             info!("Running trio marker analysis");
-            settings.validate();
-
-            match rukki::run_trio_analysis(settings) {
-                Ok(()) => info!("Success"),
-                Err(e) => info!("Some error happened {:?}", e),
+            rukki::run_trio_analysis(settings)
+        }
+        Commands::Poly(settings) => {
+            info!("Running polyploid marker analysis");
+            rukki::run_poly_analysis(settings)
+        }
+        Commands::Primary(settings) => {
+            info!("Running primary/alt decomposition");
+            rukki::run_primary_alt(settings)
+        }
+        Commands::Subgraph(settings) => {
+            info!("Extracting subgraph");
+            rukki::run_subgraph_extraction(settings)
+        }
+        Commands::Stats(settings) => {
+            info!("Computing graph statistics");
+            rukki::run_stats(settings)
+        }
+        Commands::Markers(settings) => {
+            info!("Counting parental markers");
+            rukki::run_marker_counting(settings)
+        }
+        Commands::Validate(settings) => {
+            info!("Validating inputs");
+            match rukki::run_validation(settings) {
+                Ok(0) => {
+                    info!("No problems found");
+                    Ok(())
+                }
+                Ok(cnt) => {
+                    error!("{cnt} problems found");
+                    std::process::exit(1);
+                }
+                Err(e) => Err(e),
             }
         }
+    };
+
+    match result {
+        Ok(()) => info!("Success"),
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(e.exit_code());
+        }
     }
 }
@@ -0,0 +1,261 @@
+use crate::error::{Error, Result};
+use crate::graph::*;
+use crate::trio::{AssignmentStorage, TrioGroup, TrioInfo};
+use log::{debug, info};
+
+//Likelihood-based alternative to the hard thresholds of trio::assign_parental_groups.
+//Marker counts of a node of length L are modeled as Poisson variables:
+// MATERNAL -- mat ~ Pois(d * L), pat ~ Pois(e * d * L) (PATERNAL is symmetric)
+// HOMOZYGOUS -- no parent-specific sequence, mat, pat ~ Pois(e * d * L)
+// ISSUE -- mixture of both haplotypes, mat, pat ~ Pois(d * L / 2)
+//where d is the expected density of haplotype-specific markers and e is the rate of 'wrong' markers.
+#[derive(Clone, Copy, Debug)]
+pub struct MarkerModel {
+    //expected number of haplotype-specific markers per base
+    pub density: f64,
+    //expected ratio of markers of the other haplotype
+    pub error_rate: f64,
+}
+
+//Posterior probabilities of the groups (uniform prior)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroupPosterior {
+    pub maternal: f64,
+    pub paternal: f64,
+    pub homozygous: f64,
+    pub issue: f64,
+}
+
+impl GroupPosterior {
+    pub fn get(&self, group: TrioGroup) -> f64 {
+        match group {
+            TrioGroup::MATERNAL => self.maternal,
+            TrioGroup::PATERNAL => self.paternal,
+            TrioGroup::HOMOZYGOUS => self.homozygous,
+            TrioGroup::ISSUE => self.issue,
+        }
+    }
+
+    //most probable group and its posterior
+    pub fn best(&self) -> (TrioGroup, f64) {
+        [
+            TrioGroup::MATERNAL,
+            TrioGroup::PATERNAL,
+            TrioGroup::HOMOZYGOUS,
+            TrioGroup::ISSUE,
+        ]
+        .into_iter()
+        .map(|group| (group, self.get(group)))
+        .fold((TrioGroup::MATERNAL, f64::MIN), |best, x| {
+            if x.1 > best.1 {
+                x
+            } else {
+                best
+            }
+        })
+    }
+}
+
+const MIN_RATE: f64 = 1e-9;
+const MIN_ERROR_RATE: f64 = 1e-3;
+const MAX_ERROR_RATE: f64 = 0.5;
+//nodes with bigger marker excess are considered clearly phased
+const PHASED_RATIO: usize = 3;
+
+//Thresholds of trio::assign_parental_groups
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MarkerThresholds {
+    pub marker_cnt: usize,
+    pub marker_sparsity: usize,
+    pub marker_ratio: f64,
+}
+
+impl Default for MarkerThresholds {
+    fn default() -> Self {
+        Self {
+            marker_cnt: 10,
+            marker_sparsity: 10_000,
+            marker_ratio: 5.0,
+        }
+    }
+}
+
+impl MarkerThresholds {
+    //Heuristic thresholds for the fitted marker density and error rate:
+    // * ratio -- quarter of the expected marker excess of phased nodes (within [2, 20])
+    // * sparsity -- node should have at least quarter of the expected markers
+    // * count -- enough markers for the ratio to be meaningful (twice the ratio, within [5, 20])
+    pub fn calibrated(model: &MarkerModel) -> MarkerThresholds {
+        let marker_ratio = (0.25 / model.error_rate.max(MIN_ERROR_RATE)).clamp(2., 20.);
+        MarkerThresholds {
+            marker_cnt: ((2. * marker_ratio).ceil() as usize).clamp(5, 20),
+            marker_sparsity: ((4. / model.density).round() as usize).clamp(100, 1_000_000),
+            marker_ratio,
+        }
+    }
+}
+
+//Poisson log-likelihood without the log(k!) term (same for all hypotheses)
+fn poisson_ll(k: usize, rate: f64) -> f64 {
+    let rate = rate.max(MIN_RATE);
+    k as f64 * rate.ln() - rate
+}
+
+impl MarkerModel {
+    pub fn new(density: f64, error_rate: f64) -> Result<MarkerModel> {
+        if !(density.is_finite() && density > 0.) {
+            return Err(Error::Settings(format!(
+                "marker density should be positive, got {density}"
+            )));
+        }
+        if !(0. ..1.).contains(&error_rate) {
+            return Err(Error::Settings(format!(
+                "marker error rate should be in [0, 1), got {error_rate}"
+            )));
+        }
+        Ok(MarkerModel {
+            density,
+            error_rate,
+        })
+    }
+
+    //Estimates density and error rate from the nodes with markers of at least min_len
+    //(falling back to all nodes with markers). Values provided explicitly are kept.
+    pub fn estimate(
+        g: &Graph,
+        trio_infos: &[TrioInfo],
+        min_len: usize,
+        density: Option<f64>,
+        error_rate: Option<f64>,
+    ) -> Result<MarkerModel> {
+        Self::fit(g, trio_infos, min_len, density, error_rate, false)
+    }
+
+    //Same as estimate, but only clearly phased nodes are considered (used for threshold calibration).
+    //Minor counts of the excluded nodes are not attributed to errors, so the error rate is lower.
+    pub fn fit_phased(
+        g: &Graph,
+        trio_infos: &[TrioInfo],
+        min_len: usize,
+        density: Option<f64>,
+        error_rate: Option<f64>,
+    ) -> Result<MarkerModel> {
+        Self::fit(g, trio_infos, min_len, density, error_rate, true)
+    }
+
+    fn fit(
+        g: &Graph,
+        trio_infos: &[TrioInfo],
+        min_len: usize,
+        density: Option<f64>,
+        error_rate: Option<f64>,
+        phased_only: bool,
+    ) -> Result<MarkerModel> {
+        let nodes_desc = if phased_only {
+            "clearly phased nodes"
+        } else {
+            "nodes with markers"
+        };
+        let totals = |min_len: usize| {
+            let mut total_len = 0;
+            let mut major_total = 0;
+            let mut minor_total = 0;
+            for ti in trio_infos {
+                if let Some(node_id) = g.try_name2id(&ti.node_name) {
+                    let len = g.node_length(node_id);
+                    let (major, minor) = (ti.mat().max(ti.pat()), ti.mat().min(ti.pat()));
+                    if len >= min_len
+                        && major > 0
+                        && (!phased_only || major >= PHASED_RATIO * minor)
+                    {
+                        total_len += len;
+                        major_total += major;
+                        minor_total += minor;
+                    }
+                }
+            }
+            (total_len, major_total, minor_total)
+        };
+        let (mut total_len, mut major, mut minor) = totals(min_len);
+        if major == 0 {
+            debug!("No long {nodes_desc}, estimating marker model from all {nodes_desc}");
+            (total_len, major, minor) = totals(0);
+        }
+        if major == 0 && (density.is_none() || error_rate.is_none()) {
+            return Err(Error::Markers(format!(
+                "can't estimate marker model parameters, no {nodes_desc}"
+            )));
+        }
+        let model = MarkerModel::new(
+            density.unwrap_or(major as f64 / total_len as f64),
+            error_rate.unwrap_or(
+                (minor as f64 / major.max(1) as f64).clamp(MIN_ERROR_RATE, MAX_ERROR_RATE),
+            ),
+        )?;
+        info!(
+            "Marker model: {:.3} haplotype-specific markers per 10kb, error rate {:.4}",
+            model.density * 10_000.,
+            model.error_rate
+        );
+        Ok(model)
+    }
+
+    pub fn posterior(&self, mat: usize, pat: usize, length: usize) -> GroupPosterior {
+        let rate = self.density * length as f64;
+        let err_rate = self.error_rate.max(MIN_ERROR_RATE) * rate;
+        let ll = [
+            poisson_ll(mat, rate) + poisson_ll(pat, err_rate),
+            poisson_ll(mat, err_rate) + poisson_ll(pat, rate),
+            poisson_ll(mat, err_rate) + poisson_ll(pat, err_rate),
+            poisson_ll(mat, rate / 2.) + poisson_ll(pat, rate / 2.),
+        ];
+        let max_ll = ll.iter().copied().fold(f64::MIN, f64::max);
+        let weights = ll.map(|x| (x - max_ll).exp());
+        let total: f64 = weights.iter().sum();
+        GroupPosterior {
+            maternal: weights[0] / total,
+            paternal: weights[1] / total,
+            homozygous: weights[2] / total,
+            issue: weights[3] / total,
+        }
+    }
+}
+
+//Assigns MATERNAL/PATERNAL/ISSUE groups with posterior of at least min_posterior
+//(HOMOZYGOUS nodes are left to the later stages).
+//Short nodes are not labeled as ISSUE, since markers tend to cluster more than the model assumes.
+pub fn assign_by_model(
+    g: &Graph,
+    trio_infos: &[TrioInfo],
+    model: &MarkerModel,
+    min_posterior: f64,
+    issue_len: usize,
+) -> Result<AssignmentStorage> {
+    let mut assignments = AssignmentStorage::new();
+    info!("Running parental group assignment based on the marker model.");
+    for trio_info in trio_infos {
+        let node_id = g.try_name2id(&trio_info.node_name).ok_or_else(|| {
+            Error::Markers(format!("node {} is not in the graph", trio_info.node_name))
+        })?;
+        let posterior = model.posterior(trio_info.mat(), trio_info.pat(), g.node_length(node_id));
+        let (group, p) = posterior.best();
+        debug!(
+            "Node {} (len={}), mat:pat={}, most probable group {:?} (posterior {:.4})",
+            trio_info.node_name,
+            g.node_length(node_id),
+            trio_info.counts_str(),
+            group,
+            p
+        );
+        if p >= min_posterior
+            && match group {
+                TrioGroup::HOMOZYGOUS => false,
+                TrioGroup::ISSUE => g.node_length(node_id) >= issue_len,
+                _ => true,
+            }
+        {
+            assignments.assign_with_confidence(node_id, group, trio_info.counts_str(), p);
+        }
+    }
+    Ok(assignments)
+}
@@ -21,6 +21,22 @@ impl LinearBlock {
         &self.known_alt_nodes
     }
 
+    //Block with node ids substituted by node_ids[id] (e.g. from a subgraph to the original graph)
+    pub fn lift(&self, node_ids: &[usize]) -> LinearBlock {
+        LinearBlock {
+            instance_path: self
+                .instance_path
+                .map_vertices(|v| {
+                    Some(Vertex {
+                        node_id: node_ids[v.node_id],
+                        direction: v.direction,
+                    })
+                })
+                .unwrap(),
+            known_alt_nodes: self.known_alt_nodes.iter().map(|&n| node_ids[n]).collect(),
+        }
+    }
+
     pub fn all_nodes(&self) -> impl Iterator<Item = usize> + '_ {
         self.instance_path
             .vertices()
@@ -39,8 +55,7 @@ impl LinearBlock {
     fn merge_in(&mut self, other: LinearBlock) {
         debug_assert!(self.can_merge_in(&other));
         self.instance_path.merge_in(other.instance_path);
-        self.known_alt_nodes
-            .extend(other.known_alt_nodes.into_iter());
+        self.known_alt_nodes.extend(other.known_alt_nodes);
     }
 
     fn try_merge_in(mut self, other: LinearBlock) -> Option<LinearBlock> {
@@ -345,7 +360,7 @@ fn end_vertex(b: &LinearBlock) -> Vertex {
 }
 
 impl<'a> PrimaryDecomposer<'a> {
-    fn new(g: &Graph, unique_block_len: usize) -> PrimaryDecomposer {
+    fn new(g: &Graph, unique_block_len: usize) -> PrimaryDecomposer<'_> {
         PrimaryDecomposer {
             g,
             unique_block_len,
@@ -0,0 +1,207 @@
+//Derivation of node marker counts from haplotype labels of reads and their GAF alignments to the graph
+use crate::error::{Error, Result};
+use crate::graph::*;
+use crate::trio::{TrioGroup, TrioInfo};
+use log::{debug, info};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+//Reads read<TAB>haplotype table, where haplotype is mat/maternal/pat/paternal or one of hap_names.
+//Reads with other labels (e.g. 'unknown' or a header line) are ignored,
+//reads listed several times with different haplotypes are reported as an error.
+pub fn read_read_haplotypes(
+    path: &PathBuf,
+    hap_names: (&str, &str),
+) -> Result<HashMap<String, TrioGroup>> {
+    let mut haplotypes: HashMap<String, (TrioGroup, usize)> = HashMap::new();
+    let mut ignored = 0;
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let l = line?;
+        let l = l.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let split: Vec<&str> = l.split('\t').collect();
+        if split.len() < 2 {
+            return Err(Error::Markers(format!(
+                "expected read name and haplotype in line {}",
+                i + 1
+            )));
+        }
+        let hap = split[1];
+        let is = |names: &[&str]| names.iter().any(|n| hap.eq_ignore_ascii_case(n));
+        let group = if is(&["mat", "maternal", hap_names.0]) {
+            TrioGroup::MATERNAL
+        } else if is(&["pat", "paternal", hap_names.1]) {
+            TrioGroup::PATERNAL
+        } else {
+            ignored += 1;
+            continue;
+        };
+        match haplotypes.get(split[0]) {
+            Some(&(prev, prev_line)) if prev != group => {
+                return Err(Error::Markers(format!(
+                    "read {} is assigned to different haplotypes in line {} and line {}",
+                    split[0],
+                    prev_line,
+                    i + 1
+                )));
+            }
+            Some(_) => {}
+            None => {
+                haplotypes.insert(String::from(split[0]), (group, i + 1));
+            }
+        }
+    }
+    info!(
+        "Haplotypes of {} reads loaded, {} lines with other labels ignored",
+        haplotypes.len(),
+        ignored
+    );
+    Ok(haplotypes
+        .into_iter()
+        .map(|(read, (group, _))| (read, group))
+        .collect())
+}
+
+pub struct ReadAggregationSettings {
+    //alignments with lower mapping quality are ignored
+    pub min_mapq: usize,
+    //number of aligned bases corresponding to a single marker
+    pub bases_per_marker: usize,
+}
+
+impl Default for ReadAggregationSettings {
+    fn default() -> Self {
+        Self {
+            min_mapq: 0,
+            bases_per_marker: 1000,
+        }
+    }
+}
+
+//Parses GAF path (either ([<>]<node>)+ or a single node name with the strand given separately)
+fn parse_gaf_path(g: &Graph, path: &str, strand: &str, line: usize) -> Result<Vec<Vertex>> {
+    let unknown =
+        |name: &str| Error::Markers(format!("node {name} (GAF line {line}) is not in the graph"));
+    if !path.starts_with(['>', '<']) {
+        let node_id = g.try_name2id(path).ok_or_else(|| unknown(path))?;
+        let direction = if strand == "-" {
+            Direction::REVERSE
+        } else {
+            Direction::FORWARD
+        };
+        return Ok(vec![Vertex { node_id, direction }]);
+    }
+    let mut vertices = Vec::new();
+    let mut rest = path;
+    while let Some(c) = rest.chars().next() {
+        let direction = match c {
+            '>' => Direction::FORWARD,
+            '<' => Direction::REVERSE,
+            _ => {
+                return Err(Error::Markers(format!(
+                    "invalid GAF path '{path}' in line {line}"
+                )))
+            }
+        };
+        let end = rest[1..].find(['>', '<']).map_or(rest.len(), |p| p + 1);
+        let name = &rest[1..end];
+        let node_id = g.try_name2id(name).ok_or_else(|| unknown(name))?;
+        vertices.push(Vertex { node_id, direction });
+        rest = &rest[end..];
+    }
+    Ok(vertices)
+}
+
+//Aggregates aligned lengths of the haplotype-labeled reads onto the nodes.
+//Node intervals within the path are computed by trimming known link overlaps,
+//so bases within the overlaps count for both nodes.
+//Resulting 'marker counts' are aligned bases divided by bases_per_marker (rounded).
+pub fn aggregate_read_haplotypes(
+    g: &Graph,
+    gaf: &PathBuf,
+    read_haplotypes: &HashMap<String, TrioGroup>,
+    settings: &ReadAggregationSettings,
+) -> Result<Vec<TrioInfo>> {
+    //aligned bases (mat, pat) and first contributing line of every node
+    let mut aligned: HashMap<usize, (usize, usize, usize)> = HashMap::new();
+    let mut used = 0;
+    for (i, line) in BufReader::new(File::open(gaf)?).lines().enumerate() {
+        let l = line?;
+        if l.trim().is_empty() {
+            continue;
+        }
+        let split: Vec<&str> = l.split('\t').collect();
+        if split.len() < 12 {
+            return Err(Error::Markers(format!(
+                "expected at least 12 columns in GAF line {}",
+                i + 1
+            )));
+        }
+        let group = match read_haplotypes.get(split[0]) {
+            Some(&group) => group,
+            None => continue,
+        };
+        let field = |idx: usize| {
+            split[idx].parse::<usize>().map_err(|_| {
+                Error::Markers(format!(
+                    "invalid value '{}' in column {} of GAF line {}",
+                    split[idx],
+                    idx + 1,
+                    i + 1
+                ))
+            })
+        };
+        let mapq = field(11)?;
+        //255 stands for missing mapping quality
+        if mapq != 255 && mapq < settings.min_mapq {
+            continue;
+        }
+        let (path_start, path_end) = (field(7)?, field(8)?);
+        let vertices = parse_gaf_path(g, split[5], split[4], i + 1)?;
+        used += 1;
+
+        let mut node_start = 0;
+        for (j, &v) in vertices.iter().enumerate() {
+            if j > 0 {
+                let overlap = g.connector(vertices[j - 1], v).map_or(0, |l| l.overlap);
+                node_start -= overlap.min(node_start);
+            }
+            let node_end = node_start + g.vertex_length(v);
+            let bases = path_end
+                .min(node_end)
+                .saturating_sub(path_start.max(node_start));
+            if bases > 0 {
+                let entry = aligned.entry(v.node_id).or_insert((0, 0, i + 1));
+                match group {
+                    TrioGroup::MATERNAL => entry.0 += bases,
+                    _ => entry.1 += bases,
+                }
+            }
+            node_start = node_end;
+        }
+    }
+    info!("{used} alignments of haplotype-labeled reads used");
+
+    let to_cnt = |bases: usize| (bases + settings.bases_per_marker / 2) / settings.bases_per_marker;
+    let mut infos: Vec<(usize, TrioInfo)> = aligned
+        .into_iter()
+        .map(|(node_id, (mat, pat, line))| {
+            debug!(
+                "Node {}: {} maternal and {} paternal aligned bases",
+                g.name(node_id),
+                mat,
+                pat
+            );
+            (
+                node_id,
+                TrioInfo::new(g.name(node_id), to_cnt(mat), to_cnt(pat)).with_line(line),
+            )
+        })
+        .collect();
+    infos.sort_by_key(|(node_id, _)| *node_id);
+    Ok(infos.into_iter().map(|(_, info)| info).collect())
+}
@@ -0,0 +1,262 @@
+use crate::graph::*;
+use crate::graph_algos::components::weakly_connected;
+use crate::graph_algos::dfs::DFS;
+use crate::graph_algos::{scc, superbubble};
+use itertools::Itertools;
+use std::io::Write;
+
+pub struct StatsParams {
+    //nodes of at least this length are used for coverage estimation
+    pub solid_len: usize,
+    //localized tangles of bigger (estimated) size are not reported
+    pub tangle_size: usize,
+}
+
+impl Default for StatsParams {
+    fn default() -> Self {
+        Self {
+            solid_len: 500_000,
+            tangle_size: 1_000_000,
+        }
+    }
+}
+
+pub struct ComponentStats {
+    pub node_cnt: usize,
+    pub length: usize,
+}
+
+pub struct TangleStats {
+    pub entrance: String,
+    pub exit: String,
+    pub vertex_cnt: usize,
+    pub size_estimate: usize,
+}
+
+pub struct ChainStats {
+    pub start: String,
+    pub end: String,
+    pub bubble_cnt: usize,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub linear_frac: f32,
+}
+
+pub struct GraphStats {
+    pub node_cnt: usize,
+    pub link_cnt: usize,
+    pub total_length: usize,
+    pub n50: usize,
+    //vertices (oriented nodes) without outgoing links
+    pub dead_end_cnt: usize,
+    //sorted by decreasing length
+    pub components: Vec<ComponentStats>,
+    //sizes (in vertices) of non-trivial SCCs, reverse-complement SCCs are reported separately
+    pub scc_sizes: Vec<usize>,
+    pub tangles: Vec<TangleStats>,
+    pub bubble_chains: Vec<ChainStats>,
+    pub solid_cov: f64,
+}
+
+pub fn n50(lengths: impl Iterator<Item = usize>) -> usize {
+    let lengths = lengths.sorted_by(|a, b| b.cmp(a)).collect_vec();
+    let total: usize = lengths.iter().sum();
+    let mut cumulative = 0;
+    for l in lengths {
+        cumulative += l;
+        if 2 * cumulative >= total {
+            return l;
+        }
+    }
+    0
+}
+
+pub fn weighted_mean_solid_cov(g: &Graph, solid_len_thr: usize) -> f64 {
+    let mut total_len = 0;
+    let mut total_cov = 0.;
+    for n in g.all_nodes() {
+        if n.length >= solid_len_thr {
+            total_len += n.length;
+            total_cov += n.coverage * (n.length as f64);
+        }
+    }
+    total_cov / total_len as f64
+}
+
+impl GraphStats {
+    pub fn compute(g: &Graph, params: &StatsParams) -> GraphStats {
+        let mut dfs = DFS::new_forward(g);
+        dfs.run();
+        let dead_end_cnt = dfs.dead_ends().len();
+
+        let components = weakly_connected(g)
+            .into_iter()
+            .map(|c| ComponentStats {
+                node_cnt: c.len(),
+                length: c.iter().map(|&n| g.node_length(n)).sum(),
+            })
+            .sorted_by(|a, b| b.length.cmp(&a.length).then(b.node_cnt.cmp(&a.node_cnt)))
+            .collect();
+
+        let sccs = scc::strongly_connected(g);
+        let tangles = scc::find_small_localized(g, &sccs, params.tangle_size)
+            .into_iter()
+            .map(|t| TangleStats {
+                entrance: g.v_str(t.entrance.start),
+                exit: g.v_str(t.exit.end),
+                vertex_cnt: t.vertices.len(),
+                size_estimate: scc::estimate_size_no_mult(&t, g),
+            })
+            .collect();
+
+        let bubble_chains =
+            superbubble::find_maximal_chains(g, &superbubble::SbSearchParams::unrestricted())
+                .into_iter()
+                .map(|c| {
+                    let (min_length, max_length) = superbubble::length_range(&c, g);
+                    ChainStats {
+                        start: g.v_str(c.first().unwrap().start_vertex()),
+                        end: g.v_str(c.last().unwrap().end_vertex()),
+                        bubble_cnt: c.len(),
+                        min_length,
+                        max_length,
+                        linear_frac: superbubble::linear_frac(&c, g),
+                    }
+                })
+                .collect();
+
+        GraphStats {
+            node_cnt: g.node_cnt(),
+            link_cnt: g.link_cnt(),
+            total_length: g.all_nodes().map(|n| n.length).sum(),
+            n50: n50(g.all_nodes().map(|n| n.length)),
+            dead_end_cnt,
+            components,
+            scc_sizes: sccs.iter().map(|s| s.len()).collect(),
+            tangles,
+            bubble_chains,
+            solid_cov: weighted_mean_solid_cov(g, params.solid_len),
+        }
+    }
+
+    //Two column (metric, value) table, list values are comma separated
+    pub fn write_tsv<W: Write>(&self, output: &mut W) -> std::io::Result<()> {
+        writeln!(output, "metric\tvalue")?;
+        writeln!(output, "nodes\t{}", self.node_cnt)?;
+        writeln!(output, "links\t{}", self.link_cnt)?;
+        writeln!(output, "total_length\t{}", self.total_length)?;
+        writeln!(output, "n50\t{}", self.n50)?;
+        writeln!(output, "dead_ends\t{}", self.dead_end_cnt)?;
+        writeln!(output, "components\t{}", self.components.len())?;
+        writeln!(
+            output,
+            "component_nodes\t{}",
+            self.components.iter().map(|c| c.node_cnt).join(",")
+        )?;
+        writeln!(
+            output,
+            "component_lengths\t{}",
+            self.components.iter().map(|c| c.length).join(",")
+        )?;
+        writeln!(output, "nontrivial_sccs\t{}", self.scc_sizes.len())?;
+        writeln!(output, "scc_sizes\t{}", self.scc_sizes.iter().join(","))?;
+        writeln!(output, "localized_tangles\t{}", self.tangles.len())?;
+        writeln!(
+            output,
+            "tangles\t{}",
+            self.tangles
+                .iter()
+                .map(|t| format!("{}->{}:{}", t.entrance, t.exit, t.size_estimate))
+                .join(",")
+        )?;
+        writeln!(output, "bubble_chains\t{}", self.bubble_chains.len())?;
+        writeln!(
+            output,
+            "bubble_chain_linear_fracs\t{}",
+            self.bubble_chains
+                .iter()
+                .map(|c| format!("{:.3}", c.linear_frac))
+                .join(",")
+        )?;
+        writeln!(output, "solid_cov\t{:.3}", self.solid_cov)?;
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, output: &mut W) -> std::io::Result<()> {
+        writeln!(output, "{{")?;
+        writeln!(output, "  \"nodes\": {},", self.node_cnt)?;
+        writeln!(output, "  \"links\": {},", self.link_cnt)?;
+        writeln!(output, "  \"total_length\": {},", self.total_length)?;
+        writeln!(output, "  \"n50\": {},", self.n50)?;
+        writeln!(output, "  \"dead_ends\": {},", self.dead_end_cnt)?;
+        writeln!(
+            output,
+            "  \"components\": [{}],",
+            self.components
+                .iter()
+                .map(|c| format!("{{\"nodes\": {}, \"length\": {}}}", c.node_cnt, c.length))
+                .join(", ")
+        )?;
+        writeln!(
+            output,
+            "  \"scc_sizes\": [{}],",
+            self.scc_sizes.iter().join(", ")
+        )?;
+        writeln!(
+            output,
+            "  \"tangles\": [{}],",
+            self.tangles
+                .iter()
+                .map(|t| format!(
+                    "{{\"entrance\": {}, \"exit\": {}, \"vertices\": {}, \"size_estimate\": {}}}",
+                    json_str(&t.entrance),
+                    json_str(&t.exit),
+                    t.vertex_cnt,
+                    t.size_estimate
+                ))
+                .join(", ")
+        )?;
+        writeln!(
+            output,
+            "  \"bubble_chains\": [{}],",
+            self.bubble_chains
+                .iter()
+                .map(|c| format!(
+                    "{{\"start\": {}, \"end\": {}, \"bubbles\": {}, \"min_length\": {}, \"max_length\": {}, \"linear_frac\": {}}}",
+                    json_str(&c.start),
+                    json_str(&c.end),
+                    c.bubble_cnt,
+                    c.min_length,
+                    c.max_length,
+                    json_num(c.linear_frac as f64)
+                ))
+                .join(", ")
+        )?;
+        writeln!(output, "  \"solid_cov\": {}", json_num(self.solid_cov))?;
+        writeln!(output, "}}")?;
+        Ok(())
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+//NaN (e.g. coverage estimate without solid nodes) is not valid JSON
+fn json_num(x: f64) -> String {
+    if x.is_finite() {
+        format!("{x}")
+    } else {
+        String::from("null")
+    }
+}
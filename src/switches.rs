@@ -0,0 +1,292 @@
+use crate::error::{Error, Result};
+use crate::graph::*;
+use crate::trio::{TrioGroup, TrioInfo};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionalMarker {
+    //0-based position within the forward node sequence
+    pub pos: usize,
+    //MATERNAL or PATERNAL
+    pub group: TrioGroup,
+    //line of the marker table
+    pub line: usize,
+}
+
+//Reads node<TAB>position<TAB>haplotype table (optional header), where haplotype is
+//mat/maternal/pat/paternal or one of hap_names. Markers of every node are sorted by position.
+pub fn read_positional_markers(
+    path: &PathBuf,
+    hap_names: (&str, &str),
+) -> Result<HashMap<String, Vec<PositionalMarker>>> {
+    let mut markers: HashMap<String, Vec<PositionalMarker>> = HashMap::new();
+    let mut first = true;
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let l = line?;
+        let l = l.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let split: Vec<&str> = l.split('\t').collect();
+        let is_header = first && split.get(1).is_some_and(|p| p.parse::<usize>().is_err());
+        first = false;
+        if is_header {
+            continue;
+        }
+        if split.len() < 3 {
+            return Err(Error::Markers(format!(
+                "expected node, position and haplotype in line {}",
+                i + 1
+            )));
+        }
+        let pos = split[1].parse::<usize>().map_err(|_| {
+            Error::Markers(format!(
+                "invalid marker position '{}' in line {}",
+                split[1],
+                i + 1
+            ))
+        })?;
+        let hap = split[2];
+        let is = |names: &[&str]| names.iter().any(|n| hap.eq_ignore_ascii_case(n));
+        let group = if is(&["mat", "maternal", hap_names.0]) {
+            TrioGroup::MATERNAL
+        } else if is(&["pat", "paternal", hap_names.1]) {
+            TrioGroup::PATERNAL
+        } else {
+            return Err(Error::Markers(format!(
+                "unknown haplotype '{hap}' in line {}",
+                i + 1
+            )));
+        };
+        markers
+            .entry(String::from(split[0]))
+            .or_default()
+            .push(PositionalMarker {
+                pos,
+                group,
+                line: i + 1,
+            });
+    }
+    for node_markers in markers.values_mut() {
+        node_markers.sort_by_key(|m| m.pos);
+    }
+    Ok(markers)
+}
+
+pub struct SegmentationSettings {
+    //probability of a marker disagreeing with the haplotype of the segment
+    pub error_rate: f64,
+    //log-likelihood penalty of a haplotype switch
+    pub switch_penalty: f64,
+}
+
+impl Default for SegmentationSettings {
+    fn default() -> Self {
+        Self {
+            error_rate: 0.05,
+            switch_penalty: 15.,
+        }
+    }
+}
+
+//Run of markers assigned to the same haplotype, [first_pos, last_pos] are positions of its first and last markers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub first_pos: usize,
+    pub last_pos: usize,
+    pub group: TrioGroup,
+    pub mat: usize,
+    pub pat: usize,
+}
+
+//Most likely (Viterbi) haplotype segmentation of the markers sorted by position.
+//Two state model, where every switch costs switch_penalty.
+pub fn segment_markers(
+    markers: &[PositionalMarker],
+    settings: &SegmentationSettings,
+) -> Vec<Segment> {
+    if markers.is_empty() {
+        return Vec::new();
+    }
+    let groups = [TrioGroup::MATERNAL, TrioGroup::PATERNAL];
+    let agree = (1. - settings.error_rate).ln();
+    let disagree = settings.error_rate.ln();
+    let emission = |state: usize, m: &PositionalMarker| {
+        if m.group == groups[state] {
+            agree
+        } else {
+            disagree
+        }
+    };
+
+    let mut scores = [emission(0, &markers[0]), emission(1, &markers[0])];
+    //previous state for every marker and state
+    let mut back: Vec<[usize; 2]> = vec![[0, 1]];
+    for m in &markers[1..] {
+        let mut next = [0.; 2];
+        let mut prev = [0; 2];
+        for state in 0..2 {
+            let stay = scores[state];
+            let switch = scores[1 - state] - settings.switch_penalty;
+            (next[state], prev[state]) = if stay >= switch {
+                (stay, state)
+            } else {
+                (switch, 1 - state)
+            };
+            next[state] += emission(state, m);
+        }
+        scores = next;
+        back.push(prev);
+    }
+
+    let mut state = if scores[0] >= scores[1] { 0 } else { 1 };
+    let mut states = vec![0; markers.len()];
+    for i in (0..markers.len()).rev() {
+        states[i] = state;
+        state = back[i][state];
+    }
+
+    let mut segments: Vec<Segment> = Vec::new();
+    for (m, &state) in markers.iter().zip(&states) {
+        match segments.last_mut() {
+            Some(s) if s.group == groups[state] => s.last_pos = m.pos,
+            _ => segments.push(Segment {
+                first_pos: m.pos,
+                last_pos: m.pos,
+                group: groups[state],
+                mat: 0,
+                pat: 0,
+            }),
+        }
+        let s = segments.last_mut().unwrap();
+        match m.group {
+            TrioGroup::MATERNAL => s.mat += 1,
+            _ => s.pat += 1,
+        }
+    }
+    segments
+}
+
+//Haplotype switch inside the node, happening between last_pos and next_pos markers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Switch {
+    pub node_id: usize,
+    pub last_pos: usize,
+    pub next_pos: usize,
+    pub from: TrioGroup,
+    pub to: TrioGroup,
+}
+
+impl Switch {
+    //position to split the node at
+    pub fn split_pos(&self) -> usize {
+        (self.last_pos + self.next_pos).div_ceil(2)
+    }
+}
+
+pub struct NodeSegmentation {
+    pub node_id: usize,
+    pub segments: Vec<Segment>,
+}
+
+impl NodeSegmentation {
+    pub fn switches(&self) -> Vec<Switch> {
+        self.segments
+            .windows(2)
+            .map(|w| Switch {
+                node_id: self.node_id,
+                last_pos: w[0].last_pos,
+                next_pos: w[1].first_pos,
+                from: w[0].group,
+                to: w[1].group,
+            })
+            .collect()
+    }
+}
+
+//Segments markers of every node, nodes absent from the graph are reported at once
+pub fn segment_nodes(
+    g: &Graph,
+    markers: &HashMap<String, Vec<PositionalMarker>>,
+    settings: &SegmentationSettings,
+) -> Result<Vec<NodeSegmentation>> {
+    let mut unknown: Vec<(usize, &str)> = markers
+        .iter()
+        .filter(|(name, _)| g.try_name2id(name).is_none())
+        .map(|(name, m)| (m.iter().map(|m| m.line).min().unwrap(), name.as_str()))
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort();
+        return Err(Error::Markers(format!(
+            "{} nodes are not in the graph: {}",
+            unknown.len(),
+            unknown
+                .iter()
+                .map(|(line, name)| format!("{name} (line {line})"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    let mut segmentations: Vec<NodeSegmentation> = markers
+        .iter()
+        .map(|(name, m)| NodeSegmentation {
+            node_id: g.name2id(name),
+            segments: segment_markers(m, settings),
+        })
+        .collect();
+    segmentations.sort_by_key(|s| s.node_id);
+    Ok(segmentations)
+}
+
+//BED-like intervals (node, start, end, from>to) spanning the markers flanking every switch
+pub fn write_switches<W: Write>(
+    g: &Graph,
+    switches: &[Switch],
+    hap_names: (&str, &str),
+    output: &mut W,
+) -> std::io::Result<()> {
+    let name = |group: TrioGroup| match group {
+        TrioGroup::MATERNAL => hap_names.0,
+        _ => hap_names.1,
+    };
+    for s in switches {
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}>{}",
+            g.name(s.node_id),
+            s.last_pos,
+            s.next_pos + 1,
+            name(s.from),
+            name(s.to)
+        )?;
+    }
+    Ok(())
+}
+
+//Marker counts of the pieces of the node split at split positions (sorted)
+pub fn piece_counts(
+    markers: &[PositionalMarker],
+    split_positions: &[usize],
+    piece_names: &[&str],
+) -> Vec<TrioInfo> {
+    assert!(piece_names.len() == split_positions.len() + 1);
+    let mut infos: Vec<TrioInfo> = piece_names
+        .iter()
+        .map(|&n| TrioInfo::new(n, 0, 0))
+        .collect();
+    for m in markers {
+        let piece = split_positions.partition_point(|&p| p <= m.pos);
+        let info = &mut infos[piece];
+        if info.line().is_none() {
+            *info = info.clone().with_line(m.line);
+        }
+        match m.group {
+            TrioGroup::MATERNAL => info.add(0, 1),
+            _ => info.add(1, 1),
+        }
+    }
+    infos
+}
@@ -1,3 +1,4 @@
+use crate::error::{Error, Result};
 use crate::graph::*;
 use crate::graph_algos::dfs;
 use crate::graph_algos::superbubble;
@@ -6,7 +7,6 @@ use log::info;
 use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Result as IOResult;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 
@@ -20,11 +20,22 @@ pub enum TrioGroup {
 }
 
 impl TrioGroup {
+    //diploid case of the haplotype sets, MATERNAL is haplotype 0 and PATERNAL is haplotype 1
+    //(ISSUE can't be represented)
+    pub fn haplo_set(&self) -> Option<HaploSet> {
+        match self {
+            TrioGroup::MATERNAL => Some(HaploSet(1)),
+            TrioGroup::PATERNAL => Some(HaploSet(2)),
+            TrioGroup::HOMOZYGOUS => Some(HaploSet(3)),
+            TrioGroup::ISSUE => None,
+        }
+    }
+
     pub fn incompatible(g1: TrioGroup, g2: TrioGroup) -> bool {
-        g1 == TrioGroup::ISSUE
-            || g2 == TrioGroup::ISSUE
-            || (g1 == TrioGroup::MATERNAL && g2 == TrioGroup::PATERNAL)
-            || (g1 == TrioGroup::PATERNAL && g2 == TrioGroup::MATERNAL)
+        match (g1.haplo_set(), g2.haplo_set()) {
+            (Some(s1), Some(s2)) => HaploSet::incompatible(s1, s2),
+            _ => true,
+        }
     }
 
     pub fn compatible(g1: TrioGroup, g2: TrioGroup) -> bool {
@@ -32,16 +43,14 @@ impl TrioGroup {
     }
 
     pub fn is_definite(&self) -> bool {
-        matches!(*self, TrioGroup::MATERNAL | TrioGroup::PATERNAL)
+        self.haplo_set().is_some_and(|s| s.definite().is_some())
     }
 
     pub fn blend(g1: TrioGroup, g2: TrioGroup) -> TrioGroup {
         assert!(g1 != TrioGroup::ISSUE && g2 != TrioGroup::ISSUE);
-        if g1 == g2 {
-            g1
-        } else {
-            TrioGroup::HOMOZYGOUS
-        }
+        HaploSet::blend(g1.haplo_set().unwrap(), g2.haplo_set().unwrap())
+            .trio_group()
+            .unwrap()
     }
 
     pub fn optional_blend(og1: Option<TrioGroup>, og2: Option<TrioGroup>) -> Option<TrioGroup> {
@@ -55,49 +64,487 @@ impl TrioGroup {
     }
 }
 
+//Set of haplotypes (indices < MAX_HAPLOTYPES)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HaploSet(u64);
+
+impl HaploSet {
+    pub const MAX_HAPLOTYPES: usize = 64;
+
+    pub fn empty() -> HaploSet {
+        HaploSet(0)
+    }
+
+    fn check_cnt(hap_cnt: usize) -> Result<()> {
+        if hap_cnt > Self::MAX_HAPLOTYPES {
+            return Err(Error::Settings(format!(
+                "at most {} haplotypes are supported, got {hap_cnt}",
+                Self::MAX_HAPLOTYPES
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn single(hap: usize) -> Result<HaploSet> {
+        Self::check_cnt(hap + 1)?;
+        Ok(HaploSet(1 << hap))
+    }
+
+    //set of all haplotypes (generalization of HOMOZYGOUS)
+    pub fn all(hap_cnt: usize) -> Result<HaploSet> {
+        Self::check_cnt(hap_cnt)?;
+        if hap_cnt == Self::MAX_HAPLOTYPES {
+            Ok(HaploSet(u64::MAX))
+        } else {
+            Ok(HaploSet((1 << hap_cnt) - 1))
+        }
+    }
+
+    pub fn contains(&self, hap: usize) -> bool {
+        hap < Self::MAX_HAPLOTYPES && self.0 & (1 << hap) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    //the only haplotype of the set (generalization of MATERNAL/PATERNAL)
+    pub fn definite(&self) -> Option<usize> {
+        if self.len() == 1 {
+            Some(self.0.trailing_zeros() as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn haplotypes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Self::MAX_HAPLOTYPES).filter(|&h| self.contains(h))
+    }
+
+    pub fn union(&self, other: HaploSet) -> HaploSet {
+        HaploSet(self.0 | other.0)
+    }
+
+    pub fn intersection(&self, other: HaploSet) -> HaploSet {
+        HaploSet(self.0 & other.0)
+    }
+
+    //node can be used by paths of both sets only if they share a haplotype
+    pub fn compatible(s1: HaploSet, s2: HaploSet) -> bool {
+        !s1.intersection(s2).is_empty()
+    }
+
+    pub fn incompatible(s1: HaploSet, s2: HaploSet) -> bool {
+        !Self::compatible(s1, s2)
+    }
+
+    pub fn blend(s1: HaploSet, s2: HaploSet) -> HaploSet {
+        s1.union(s2)
+    }
+
+    //inverse of TrioGroup::haplo_set, None for sets including other haplotypes
+    pub fn trio_group(&self) -> Option<TrioGroup> {
+        match self.0 {
+            1 => Some(TrioGroup::MATERNAL),
+            2 => Some(TrioGroup::PATERNAL),
+            3 => Some(TrioGroup::HOMOZYGOUS),
+            _ => None,
+        }
+    }
+
+    //Two-haplotype view used to reuse the trio machinery:
+    //MATERNAL -- only the haplotype, HOMOZYGOUS -- haplotype and some other ones, PATERNAL -- other ones
+    pub fn project(&self, hap: usize) -> TrioGroup {
+        if !self.contains(hap) {
+            TrioGroup::PATERNAL
+        } else if self.len() == 1 {
+            TrioGroup::MATERNAL
+        } else {
+            TrioGroup::HOMOZYGOUS
+        }
+    }
+
+    pub fn format(&self, hap_names: &[String]) -> String {
+        if self.len() == hap_names.len() {
+            String::from("all")
+        } else {
+            self.haplotypes()
+                .map(|h| hap_names[h].as_str())
+                .collect::<Vec<_>>()
+                .join("+")
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Assignment {
     pub group: TrioGroup,
     pub info: String,
+    //posterior probability of the group (if assigned based on the marker model)
+    pub confidence: Option<f64>,
 }
 
+//Marker counts of the node for every haplotype (maternal and paternal ones in the trio case)
 #[derive(Clone, Debug)]
 pub struct TrioInfo {
     pub node_name: String,
-    pub mat: usize,
-    pub pat: usize,
+    counts: Vec<usize>,
+    //line of the marker table where the node was (first) listed, 0 if unknown
+    line: usize,
 }
 
 impl TrioInfo {
-    fn _total(&self) -> usize {
-        self.mat + self.pat
+    pub fn new<S: Into<String>>(node_name: S, mat: usize, pat: usize) -> TrioInfo {
+        Self::with_counts(node_name, vec![mat, pat])
+    }
+
+    pub fn with_counts<S: Into<String>>(node_name: S, counts: Vec<usize>) -> TrioInfo {
+        TrioInfo {
+            node_name: node_name.into(),
+            counts,
+            line: 0,
+        }
+    }
+
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    pub fn hap_cnt(&self) -> usize {
+        self.counts.len()
+    }
+
+    //count of haplotype 0
+    pub fn mat(&self) -> usize {
+        self.counts.first().copied().unwrap_or(0)
+    }
+
+    //count of haplotype 1
+    pub fn pat(&self) -> usize {
+        self.counts.get(1).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn add(&mut self, hap: usize, cnt: usize) {
+        self.counts[hap] += cnt;
+    }
+
+    pub(crate) fn with_line(self, line: usize) -> TrioInfo {
+        TrioInfo { line, ..self }
     }
 
-    fn counts_str(&self) -> String {
-        format!("m{}:p{}", self.mat, self.pat)
+    //line of the marker table where the node was (first) listed (if known)
+    pub fn line(&self) -> Option<usize> {
+        if self.line == 0 {
+            None
+        } else {
+            Some(self.line)
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub(crate) fn counts_str(&self) -> String {
+        format!("m{}:p{}", self.mat(), self.pat())
+    }
+}
+
+//How to treat several rows of the marker table referring to the same node
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateMarkers {
+    Sum,
+    Reject,
+}
+
+pub struct MarkerReadSettings<'a> {
+    //names of maternal and paternal count columns (in addition to mat/pat/maternal/paternal)
+    pub hap_names: Option<(&'a str, &'a str)>,
+    pub duplicates: DuplicateMarkers,
+}
+
+impl Default for MarkerReadSettings<'_> {
+    fn default() -> Self {
+        Self {
+            hap_names: None,
+            duplicates: DuplicateMarkers::Sum,
+        }
     }
 }
 
-pub fn read_trio(path: &PathBuf) -> IOResult<Vec<TrioInfo>> {
-    let mut infos = Vec::new();
+const MAT_COLUMNS: [&str; 2] = ["mat", "maternal"];
+const PAT_COLUMNS: [&str; 2] = ["pat", "paternal"];
+const NAME_COLUMNS: [&str; 3] = ["node", "contig", "name"];
+
+//Non-empty, non-comment row of the marker table (with 1-based line number)
+type MarkerRow = (usize, Vec<String>);
+
+fn marker_rows(path: &PathBuf) -> Result<Vec<MarkerRow>> {
+    let mut rows = Vec::new();
     let file = File::open(path)?;
-    for line in BufReader::new(file).lines() {
+    for (i, line) in BufReader::new(file).lines().enumerate() {
         let l = line?;
-        let split: Vec<&str> = l.trim().split('\t').collect();
-        if &split[0].to_lowercase() != "node" && &split[0].to_lowercase() != "contig" {
-            let node_name = String::from(split[0]);
-            let mat: usize = split[1].parse().expect("Invalid maternal count");
-            let pat: usize = split[2].parse().expect("Invalid paternal count");
-            infos.push(TrioInfo {
-                node_name,
-                mat,
-                pat,
+        let l = l.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        rows.push((i + 1, l.split('\t').map(String::from).collect::<Vec<_>>()));
+    }
+    Ok(rows)
+}
+
+fn is_col(s: &str, names: &[&str], hap_name: Option<&str>) -> bool {
+    names
+        .iter()
+        .chain(hap_name.iter())
+        .any(|n| s.eq_ignore_ascii_case(n))
+}
+
+fn is_name_col(s: &str) -> bool {
+    is_col(s, &NAME_COLUMNS, None)
+}
+
+//Checks if the (split) line of a node table is a header:
+//first column is node/contig/name or one of the other columns names a haplotype
+pub fn is_header_row<S: AsRef<str>>(row: &[S], hap_names: Option<(&str, &str)>) -> bool {
+    row.first().is_some_and(|s| is_name_col(s.as_ref()))
+        || row.iter().skip(1).any(|s| {
+            is_col(s.as_ref(), &MAT_COLUMNS, hap_names.map(|h| h.0))
+                || is_col(s.as_ref(), &PAT_COLUMNS, hap_names.map(|h| h.1))
+        })
+}
+
+//Positions of maternal and paternal count columns (detected from the header if present),
+//header lines are removed from the rows
+fn marker_columns(
+    rows: &mut Vec<MarkerRow>,
+    hap_names: Option<(&str, &str)>,
+) -> std::result::Result<(usize, usize), MarkerIssue> {
+    let is_mat = |s: &str| is_col(s, &MAT_COLUMNS, hap_names.map(|h| h.0));
+    let is_pat = |s: &str| is_col(s, &PAT_COLUMNS, hap_names.map(|h| h.1));
+
+    let mut columns = (1, 2);
+    if let Some((line, header)) = rows.first() {
+        let line = *line;
+        let mat_col = header.iter().skip(1).position(|s| is_mat(s)).map(|p| p + 1);
+        let pat_col = header.iter().skip(1).position(|s| is_pat(s)).map(|p| p + 1);
+        if is_header_row(header, hap_names) {
+            columns = match (mat_col, pat_col) {
+                (Some(m), Some(p)) => (m, p),
+                _ if header.len() == 3 => (1, 2),
+                _ => {
+                    return Err(MarkerIssue {
+                        line,
+                        node_name: header[0].clone(),
+                        kind: MarkerIssueKind::MissingCountColumns,
+                    })
+                }
+            };
+            rows.remove(0);
+        }
+    }
+    //repeated headers (e.g. in concatenated tables) are skipped
+    rows.retain(|(_, split)| !is_name_col(&split[0]));
+    Ok(columns)
+}
+
+pub fn read_trio(path: &PathBuf) -> Result<Vec<TrioInfo>> {
+    read_trio_with(path, &MarkerReadSettings::default())
+}
+
+//Per-node counts of the specified columns, rows referring to the same node are treated according to the policy
+fn collect_counts(
+    rows: Vec<MarkerRow>,
+    columns: &[usize],
+    duplicates: DuplicateMarkers,
+) -> Result<Vec<TrioInfo>> {
+    let mut infos: Vec<TrioInfo> = Vec::new();
+    let mut node_idx = HashMap::new();
+    let max_col = columns.iter().copied().max().unwrap_or(0);
+    for (line, split) in rows {
+        if split.len() <= max_col {
+            return Err(Error::Markers(format!(
+                "expected at least {} columns in line {line}",
+                max_col + 1
+            )));
+        }
+        let counts = columns
+            .iter()
+            .map(|&c| {
+                split[c].parse::<usize>().map_err(|_| {
+                    Error::Markers(format!(
+                        "invalid marker count '{}' in line {line}",
+                        split[c]
+                    ))
+                })
             })
+            .collect::<Result<Vec<_>>>()?;
+        match node_idx.get(&split[0]) {
+            Some(&idx) => {
+                let info: &mut TrioInfo = &mut infos[idx];
+                if duplicates == DuplicateMarkers::Reject {
+                    return Err(Error::Markers(format!(
+                        "node {} is listed in both line {} and line {line}",
+                        info.node_name, info.line
+                    )));
+                }
+                for (h, cnt) in counts.into_iter().enumerate() {
+                    info.add(h, cnt);
+                }
+            }
+            None => {
+                node_idx.insert(split[0].clone(), infos.len());
+                infos.push(TrioInfo::with_counts(split[0].clone(), counts).with_line(line));
+            }
         }
     }
     Ok(infos)
 }
 
+pub fn read_trio_with(path: &PathBuf, settings: &MarkerReadSettings) -> Result<Vec<TrioInfo>> {
+    let mut rows = marker_rows(path)?;
+    let (mat_col, pat_col) = marker_columns(&mut rows, settings.hap_names).map_err(|issue| {
+        Error::Markers(format!("{} (line {})", issue.description(), issue.line))
+    })?;
+    collect_counts(rows, &[mat_col, pat_col], settings.duplicates)
+}
+
+//Reads table with node name in the first column and per-haplotype marker counts (any number of haplotypes).
+//Header line is required, count columns are either specified by name or are all the remaining columns.
+//Returns haplotype names and per-node counts.
+pub fn read_haplotype_markers(
+    path: &PathBuf,
+    hap_names: Option<&[String]>,
+    duplicates: DuplicateMarkers,
+) -> Result<(Vec<String>, Vec<TrioInfo>)> {
+    let mut rows = marker_rows(path)?;
+    if rows.is_empty() {
+        return Err(Error::Markers(String::from("marker table is empty")));
+    }
+    let (line, header) = rows.remove(0);
+    let (names, columns) = match hap_names {
+        Some(names) => {
+            let columns = names
+                .iter()
+                .map(|n| {
+                    header
+                        .iter()
+                        .skip(1)
+                        .position(|s| s.eq_ignore_ascii_case(n))
+                        .map(|p| p + 1)
+                        .ok_or_else(|| {
+                            Error::Markers(format!(
+                                "no column for haplotype {n} in the header (line {line})"
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            (names.to_vec(), columns)
+        }
+        None => (header[1..].to_vec(), (1..header.len()).collect()),
+    };
+    if columns.len() < 2 || columns.len() > HaploSet::MAX_HAPLOTYPES {
+        return Err(Error::Markers(format!(
+            "expected between 2 and {} haplotypes, found {}",
+            HaploSet::MAX_HAPLOTYPES,
+            columns.len()
+        )));
+    }
+    //repeated headers (e.g. in concatenated tables) are skipped
+    rows.retain(|(_, split)| split[0] != header[0]);
+    Ok((names, collect_counts(rows, &columns, duplicates)?))
+}
+
+//Reports all nodes absent from the graph at once
+pub fn check_marker_nodes(g: &Graph, trio_infos: &[TrioInfo]) -> Result<()> {
+    let unknown: Vec<String> = trio_infos
+        .iter()
+        .filter(|ti| g.try_name2id(&ti.node_name).is_none())
+        .map(|ti| match ti.line() {
+            Some(line) => format!("{} (line {line})", ti.node_name),
+            None => ti.node_name.clone(),
+        })
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Markers(format!(
+            "{} nodes are not in the graph: {}",
+            unknown.len(),
+            unknown.join(", ")
+        )))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkerIssueKind {
+    TooFewColumns,
+    InvalidCount(String),
+    UnknownNode,
+    //header doesn't allow to determine maternal and paternal count columns
+    MissingCountColumns,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkerIssue {
+    pub line: usize,
+    pub node_name: String,
+    pub kind: MarkerIssueKind,
+}
+
+impl MarkerIssue {
+    pub fn description(&self) -> String {
+        match &self.kind {
+            MarkerIssueKind::TooFewColumns => String::from("missing marker count columns"),
+            MarkerIssueKind::InvalidCount(c) => format!("invalid marker count '{c}'"),
+            MarkerIssueKind::UnknownNode => String::from("node is not in the graph"),
+            MarkerIssueKind::MissingCountColumns => {
+                String::from("can't find maternal and paternal count columns in the header")
+            }
+        }
+    }
+}
+
+//Reports all problems of the marker table (malformed header and rows, nodes absent from the graph).
+//Count columns are detected as in read_trio_with, only I/O problems are returned as errors.
+pub fn lint_trio(
+    path: &PathBuf,
+    hap_names: Option<(&str, &str)>,
+    is_known: &dyn Fn(&str) -> bool,
+) -> Result<Vec<MarkerIssue>> {
+    let mut rows = marker_rows(path)?;
+    let (mat_col, pat_col) = match marker_columns(&mut rows, hap_names) {
+        Ok(columns) => columns,
+        //rows can't be checked without knowing the columns
+        Err(issue) => return Ok(vec![issue]),
+    };
+    let mut issues = Vec::new();
+    for (line, split) in rows {
+        let issue = |kind| MarkerIssue {
+            line,
+            node_name: split[0].clone(),
+            kind,
+        };
+        if split.len() <= max(mat_col, pat_col) {
+            issues.push(issue(MarkerIssueKind::TooFewColumns));
+            continue;
+        }
+        for cnt in [&split[mat_col], &split[pat_col]] {
+            if cnt.parse::<usize>().is_err() {
+                issues.push(issue(MarkerIssueKind::InvalidCount(cnt.clone())));
+            }
+        }
+        if !is_known(&split[0]) {
+            issues.push(issue(MarkerIssueKind::UnknownNode));
+        }
+    }
+    Ok(issues)
+}
+
 //TODO add template parameter
 #[derive(Clone)]
 pub struct AssignmentStorage {
@@ -142,10 +589,35 @@ impl AssignmentStorage {
             Assignment {
                 group,
                 info: info.into(),
+                confidence: None,
+            },
+        )
+    }
+
+    pub fn assign_with_confidence<S: Into<String>>(
+        &mut self,
+        node_id: usize,
+        group: TrioGroup,
+        info: S,
+        confidence: f64,
+    ) -> Option<Assignment> {
+        self.storage.insert(
+            node_id,
+            Assignment {
+                group,
+                info: info.into(),
+                confidence: Some(confidence),
             },
         )
     }
 
+    //None if the node was assigned without the marker model
+    pub fn confidence(&self, node_id: usize) -> Option<f64> {
+        self.storage
+            .get(&node_id)
+            .and_then(|assign| assign.confidence)
+    }
+
     pub fn update_group(&mut self, node_id: usize, group: TrioGroup) {
         match self.group(node_id) {
             //FIXME how to simultaneously check key and get mutable reference to stored value?
@@ -221,7 +693,7 @@ pub fn assign_parental_groups(
     settings: &GroupAssignmentSettings,
     solid_len: usize,
     solid_cov: f64,
-) -> AssignmentStorage {
+) -> Result<AssignmentStorage> {
     let mut assignments = AssignmentStorage::new();
 
     info!("Running parental group assignment.");
@@ -252,7 +724,9 @@ pub fn assign_parental_groups(
     };
 
     for trio_info in trio_infos {
-        let node_id = g.name2id(&trio_info.node_name);
+        let node_id = g.try_name2id(&trio_info.node_name).ok_or_else(|| {
+            Error::Markers(format!("node {} is not in the graph", trio_info.node_name))
+        })?;
         let node_len = g.node_length(node_id);
         let node_cov = g.node(node_id).coverage;
         debug!(
@@ -263,19 +737,19 @@ pub fn assign_parental_groups(
         );
 
         if issue_node_f(
-            max(trio_info.mat, trio_info.pat),
-            min(trio_info.mat, trio_info.pat),
+            max(trio_info.mat(), trio_info.pat()),
+            min(trio_info.mat(), trio_info.pat()),
             node_len,
         ) {
             debug!("Assigning ISSUE label");
             assignments.assign(node_id, TrioGroup::ISSUE, trio_info.counts_str());
         } else if assign_node_f(
-            max(trio_info.mat, trio_info.pat),
-            min(trio_info.mat, trio_info.pat),
+            max(trio_info.mat(), trio_info.pat()),
+            min(trio_info.mat(), trio_info.pat()),
             node_len,
             node_cov,
         ) {
-            if trio_info.mat >= trio_info.pat {
+            if trio_info.mat() >= trio_info.pat() {
                 debug!("Looks MATERNAL");
                 assignments.assign(node_id, TrioGroup::MATERNAL, trio_info.counts_str());
             } else {
@@ -286,30 +760,175 @@ pub fn assign_parental_groups(
             debug!("Failed to assign label based on marker counts");
         }
     }
-    assignments
+    Ok(assignments)
 }
 
-fn parse_group(group_str: &str) -> TrioGroup {
-    match group_str {
-        "MATERNAL" => TrioGroup::MATERNAL,
-        "PATERNAL" => TrioGroup::PATERNAL,
-        "HOMOZYGOUS" => TrioGroup::HOMOZYGOUS,
-        "ISSUE" => TrioGroup::ISSUE,
-        _ => panic!("Invalid group string {group_str}"),
+#[derive(Clone, Debug)]
+pub struct HaploAssignment {
+    pub set: HaploSet,
+    pub info: String,
+}
+
+pub struct HaploAssignments {
+    hap_cnt: usize,
+    storage: HashMap<usize, HaploAssignment>,
+}
+
+impl HaploAssignments {
+    pub fn new(hap_cnt: usize) -> Result<HaploAssignments> {
+        HaploSet::check_cnt(hap_cnt)?;
+        Ok(HaploAssignments {
+            hap_cnt,
+            storage: HashMap::new(),
+        })
+    }
+
+    pub fn hap_cnt(&self) -> usize {
+        self.hap_cnt
+    }
+
+    pub fn assign<S: Into<String>>(
+        &mut self,
+        node_id: usize,
+        set: HaploSet,
+        info: S,
+    ) -> Result<()> {
+        if set.is_empty() || set.haplotypes().any(|h| h >= self.hap_cnt) {
+            return Err(Error::Assignments(format!(
+                "node {node_id} can't be assigned to haplotype set {:?} ({} haplotypes)",
+                set.haplotypes().collect::<Vec<_>>(),
+                self.hap_cnt
+            )));
+        }
+        self.storage.insert(
+            node_id,
+            HaploAssignment {
+                set,
+                info: info.into(),
+            },
+        );
+        Ok(())
+    }
+
+    //blends with the existing assignment
+    pub fn update(&mut self, node_id: usize, set: HaploSet) -> Result<()> {
+        match self.storage.get_mut(&node_id) {
+            Some(assign) => {
+                assign.set = HaploSet::blend(assign.set, set);
+                Ok(())
+            }
+            None => self.assign(node_id, set, ""),
+        }
+    }
+
+    pub fn get(&self, node_id: usize) -> Option<&HaploAssignment> {
+        self.storage.get(&node_id)
+    }
+
+    pub fn set(&self, node_id: usize) -> Option<HaploSet> {
+        self.storage.get(&node_id).map(|a| a.set)
+    }
+
+    pub fn assigned(&self) -> impl Iterator<Item = usize> + '_ {
+        self.storage.keys().copied()
+    }
+
+    //Two-haplotype view of the assignments from the perspective of a single haplotype
+    pub fn project(&self, hap: usize) -> AssignmentStorage {
+        let mut projected = AssignmentStorage::new();
+        for (&node_id, assign) in &self.storage {
+            projected.assign(node_id, assign.set.project(hap), assign.info.clone());
+        }
+        projected
     }
 }
 
-pub fn parse_node_assignments(
+pub struct HaploAssignmentSettings {
+    //minimal total number of markers
+    pub assign_cnt: usize,
+    //require at least (node_length / <value>) markers within the node
+    pub assign_sparsity: usize,
+    //haplotypes with marker counts <value> times below the maximal one are excluded
+    pub assign_ratio: f64,
+}
+
+//Assigns every node with enough markers to the set of haplotypes, which counts are comparable with the maximal one.
+//Nodes with comparable counts for all haplotypes are left unassigned.
+pub fn assign_haplo_sets(
     g: &Graph,
-    assignments_fn: &str,
-) -> std::io::Result<AssignmentStorage> {
+    infos: &[TrioInfo],
+    hap_cnt: usize,
+    settings: &HaploAssignmentSettings,
+) -> Result<HaploAssignments> {
+    check_marker_nodes(g, infos)?;
+    let mut assignments = HaploAssignments::new(hap_cnt)?;
+    for info in infos {
+        if info.hap_cnt() != hap_cnt {
+            return Err(Error::Markers(format!(
+                "node {} has marker counts for {} haplotypes instead of {hap_cnt}",
+                info.node_name,
+                info.hap_cnt()
+            )));
+        }
+        let node_id = g.name2id(&info.node_name);
+        let total = info.total();
+        if total < settings.assign_cnt || g.node_length(node_id) > total * settings.assign_sparsity
+        {
+            continue;
+        }
+        let max_cnt = *info.counts().iter().max().unwrap() as f64;
+        let mut set = HaploSet::empty();
+        for (h, &cnt) in info.counts().iter().enumerate() {
+            if (cnt as f64) * settings.assign_ratio > max_cnt - 1e-6 {
+                set = set.union(HaploSet::single(h)?);
+            }
+        }
+        let counts_str = info
+            .counts()
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(":");
+        if set == HaploSet::all(hap_cnt)? {
+            debug!(
+                "Node {} has comparable marker counts {} for all haplotypes",
+                info.node_name, counts_str
+            );
+            continue;
+        }
+        assignments.assign(node_id, set, counts_str)?;
+    }
+    Ok(assignments)
+}
+
+fn parse_group(group_str: &str) -> Option<TrioGroup> {
+    match group_str {
+        "MATERNAL" => Some(TrioGroup::MATERNAL),
+        "PATERNAL" => Some(TrioGroup::PATERNAL),
+        "HOMOZYGOUS" => Some(TrioGroup::HOMOZYGOUS),
+        "ISSUE" => Some(TrioGroup::ISSUE),
+        _ => None,
+    }
+}
+
+pub fn parse_node_assignments(g: &Graph, assignments_fn: &str) -> Result<AssignmentStorage> {
     let mut assignments = AssignmentStorage::new();
-    for line in std::fs::read_to_string(assignments_fn)?.lines() {
+    for (i, line) in std::fs::read_to_string(assignments_fn)?.lines().enumerate() {
         let split: Vec<&str> = line.trim().split('\t').collect();
         if &split[0].to_lowercase() != "node" && &split[0].to_lowercase() != "contig" {
             let node_name = split[0];
-            let group = parse_group(split[1]);
-            assignments.update_group(g.name2id(node_name), group);
+            let group = split.get(1).and_then(|s| parse_group(s)).ok_or_else(|| {
+                Error::Table(format!(
+                    "{assignments_fn} line {}: expected node name and group",
+                    i + 1
+                ))
+            })?;
+            let node_id = g.try_name2id(node_name).ok_or_else(|| {
+                Error::Assignments(format!(
+                    "node {node_name} from {assignments_fn} is not in the graph"
+                ))
+            })?;
+            assignments.update_group(node_id, group);
         }
     }
     Ok(assignments)
@@ -3,7 +3,7 @@ use crate::graph_algos::only_or_none;
 use crate::graph_algos::*;
 use crate::trio::*;
 use itertools::Itertools;
-use log::{debug, warn};
+use log::{debug, info, warn};
 use std::collections::{HashMap, HashSet};
 
 //FIXME move to dfs.rs
@@ -27,16 +27,16 @@ pub fn reachable_between(
     if fwd_dfs.boundary().contains(&w) {
         let fwd_visited = fwd_dfs.visited();
         reachable_between.insert(v);
-        reachable_between.insert(w);
 
-        let mut bwd_dfs = DFS::new(g, TraversalDirection::REVERSE, node_f);
+        //backward search never leaves the vertices reached by the forward one
+        let within_fwd = |x: Vertex| fwd_visited.contains(&x);
+        let mut bwd_dfs = DFS::new(g, TraversalDirection::REVERSE, Some(&within_fwd));
         bwd_dfs.set_max_node_len(node_len_thr);
         bwd_dfs.extend_blocked(std::iter::once(v));
         bwd_dfs.run_from(w);
-        let bwd_visited = bwd_dfs.visited();
         assert!(bwd_dfs.boundary().contains(&v));
 
-        reachable_between.extend(fwd_visited.intersection(&bwd_visited).copied());
+        reachable_between.extend(bwd_dfs.exit_order().iter().copied());
     }
     reachable_between
 }
@@ -58,13 +58,45 @@ fn considered_extensions(
     }
 }
 
+//Item, which support exceeds support of every alternative at least ratio times.
+//None if ratio is non-positive or support of some item is not known
+fn dominant_by_support<T: Copy>(
+    items: &[T],
+    support: impl Fn(&T) -> Option<usize>,
+    ratio: f64,
+) -> Option<T> {
+    if ratio <= 0. || items.len() < 2 {
+        return None;
+    }
+    let supports: Vec<usize> = items.iter().map(support).collect::<Option<_>>()?;
+    let (best_idx, &best) = supports.iter().enumerate().max_by_key(|(_, &s)| s)?;
+    if supports
+        .iter()
+        .enumerate()
+        .all(|(i, &s)| i == best_idx || (best > s && best as f64 >= ratio * s as f64))
+    {
+        Some(items[best_idx])
+    } else {
+        None
+    }
+}
+
 pub struct ExtensionHelper<'a> {
     g: &'a Graph,
     assignments: &'a AssignmentStorage,
     allow_unassigned: bool,
+    min_link_support: usize,
+    link_support_ratio: f64,
 }
 
 impl<'a> ExtensionHelper<'a> {
+    //links with known support below the threshold are vetoed
+    fn supported(&self, l: &Link) -> bool {
+        self.g
+            .link_support(l)
+            .is_none_or(|s| s >= self.min_link_support)
+    }
+
     fn compatible_assignment(&self, node_id: usize, target_group: TrioGroup) -> bool {
         match self.assignments.group(node_id) {
             Some(group) => TrioGroup::compatible(group, target_group),
@@ -130,13 +162,16 @@ impl<'a> ExtensionHelper<'a> {
         consider_vertex_f: Option<&dyn Fn(Vertex) -> bool>,
     ) -> Option<Link> {
         //If only extension exists it is always ok if it is unassigned
-        let filtered_outgoing = considered_extensions(self.g, v, consider_vertex_f);
+        let filtered_outgoing = considered_extensions(self.g, v, consider_vertex_f)
+            .into_iter()
+            .filter(|l| self.supported(l))
+            .collect_vec();
         if filtered_outgoing.len() == 1 {
             let l = filtered_outgoing[0];
             if self
                 .assignments
                 .group(l.end.node_id)
-                .map_or(true, |g| TrioGroup::compatible(g, group))
+                .is_none_or(|g| TrioGroup::compatible(g, group))
             {
                 debug!("Candidate unambiguous extension {}", self.g.v_str(l.end));
                 return Some(l);
@@ -147,6 +182,24 @@ impl<'a> ExtensionHelper<'a> {
         let ext = self.only_compatible_of_bearable_link(&filtered_outgoing, group);
         if let Some(l) = ext {
             debug!("Candidate adjacent extension {}", self.g.v_str(l.end));
+            return ext;
+        }
+
+        //prefer strongly supported link among compatible ones
+        let compatible = filtered_outgoing
+            .into_iter()
+            .filter(|l| self.compatible_assignment(l.end.node_id, group))
+            .collect_vec();
+        let ext = dominant_by_support(
+            &compatible,
+            |l| self.g.link_support(l),
+            self.link_support_ratio,
+        );
+        if let Some(l) = ext {
+            debug!(
+                "Candidate extension by link support {}",
+                self.g.v_str(l.end)
+            );
         }
         ext
     }
@@ -159,10 +212,13 @@ impl<'a> ExtensionHelper<'a> {
             Some(&check_unassigned),
         );
         dfs.set_max_node_len(solid_len);
-        dfs.run_from(v);
 
         //could be if solid unassigned node is in the boundary
-        if dfs.boundary().iter().any(|&x| check_unassigned(x)) {
+        if dfs.run_from_with(v, |e| match e {
+            dfs::DfsEvent::Boundary(x) if check_unassigned(x) => dfs::DfsControl::Stop,
+            _ => dfs::DfsControl::Continue,
+        }) == dfs::DfsControl::Stop
+        {
             return None;
         }
 
@@ -259,6 +315,15 @@ pub struct HaploSearchSettings {
     pub skippable_tangle_size: usize,
     pub min_gap_size: i64,
     pub default_gap_size: i64,
+
+    //configuring usage of link read support (if available)
+    //links with lower support are not used for extension (0 disables)
+    pub min_link_support: usize,
+    //prefer link (bubble side) if its support exceeds all alternatives this many times (0. disables)
+    pub link_support_ratio: f64,
+
+    //nodes assigned by the marker model with lower posterior are not used to launch the search
+    pub min_seed_confidence: f64,
 }
 
 impl Default for HaploSearchSettings {
@@ -278,6 +343,9 @@ impl Default for HaploSearchSettings {
             skippable_tangle_size: 1_000_000,
             min_gap_size: 1000,
             default_gap_size: 5000,
+            min_link_support: 0,
+            link_support_ratio: 0.,
+            min_seed_confidence: 0.,
         }
     }
 }
@@ -301,10 +369,32 @@ pub struct HaploSearcher<'a> {
     used: AssignmentStorage,
     small_tangle_index: HashMap<Vertex, scc::LocalizedTangle>,
     raw_cnts: Option<&'a HashMap<usize, TrioInfo>>,
+    //paths to be kept intact and extended before the regular search
+    seeds: Vec<Path>,
 }
 
 pub type HaploPath = (Path, usize, TrioGroup);
 
+//Haplo-paths for every haplotype of the set-valued assignments (launched from long nodes assigned only to the haplotype).
+//Nodes shared with other haplotypes are treated the way homozygous nodes are treated in the trio case.
+pub fn haplotype_paths(
+    g: &Graph,
+    assignments: &HaploAssignments,
+    settings: HaploSearchSettings,
+) -> Vec<Vec<(Path, usize)>> {
+    (0..assignments.hap_cnt())
+        .map(|hap| {
+            info!("Searching haplo-paths for haplotype {hap}");
+            let projected = assignments.project(hap);
+            HaploSearcher::new(g, &projected, settings, None)
+                .find_group(TrioGroup::MATERNAL)
+                .into_iter()
+                .map(|(path, node_id, _)| (path, node_id))
+                .collect()
+        })
+        .collect()
+}
+
 impl<'a> HaploSearcher<'a> {
     pub fn new(
         g: &'a Graph,
@@ -321,6 +411,8 @@ impl<'a> HaploSearcher<'a> {
                 g,
                 assignments,
                 allow_unassigned: settings.allow_unassigned,
+                min_link_support: settings.min_link_support,
+                link_support_ratio: settings.link_support_ratio,
             },
             small_tangle_index: HashMap::from_iter(
                 scc::find_small_localized(
@@ -332,9 +424,18 @@ impl<'a> HaploSearcher<'a> {
                 .map(|s| (s.entrance.start, s)),
             ),
             raw_cnts,
+            seeds: Vec::new(),
         }
     }
 
+    //gaps of unknown size within the seeds are set to the default gap size
+    pub fn set_seeds(&mut self, seeds: Vec<Path>) {
+        self.seeds = seeds
+            .iter()
+            .map(|p| p.fill_unknown_gap_sizes(self.g, self.settings.default_gap_size))
+            .collect();
+    }
+
     pub fn used(&self) -> &AssignmentStorage {
         &self.used
     }
@@ -345,7 +446,53 @@ impl<'a> HaploSearcher<'a> {
 
     //TODO maybe use single length threshold?
     pub fn find_all(&mut self) -> Vec<HaploPath> {
+        self.find(None)
+    }
+
+    //Haplo-paths of a single definite group, seeds and long nodes of the other groups are ignored
+    pub fn find_group(&mut self, group: TrioGroup) -> Vec<HaploPath> {
+        assert!(group.is_definite());
+        self.find(Some(group))
+    }
+
+    fn find(&mut self, target: Option<TrioGroup>) -> Vec<HaploPath> {
         let mut answer = Vec::new();
+        for seed in std::mem::take(&mut self.seeds) {
+            let group = match self.seed_group(&seed) {
+                Some(group) => group,
+                None => {
+                    warn!(
+                        "Couldn't determine haplotype of seed path {}, ignoring",
+                        seed.print(self.g)
+                    );
+                    continue;
+                }
+            };
+            if target.is_some_and(|t| t != group) {
+                continue;
+            }
+            if !seed
+                .vertices()
+                .iter()
+                .all(|v| self.check_available(v.node_id, group))
+            {
+                warn!(
+                    "Seed path {} conflicts with assignments or previous paths, ignoring",
+                    seed.print(self.g)
+                );
+                continue;
+            }
+            let node_id = seed
+                .vertices()
+                .iter()
+                .map(|v| v.node_id)
+                .max_by_key(|&n| self.g.node_length(n))
+                .unwrap();
+            let path = self.extend_seed(seed, group);
+            self.mark_used(&path, group);
+            answer.push((path, node_id, group));
+        }
+
         let mut nodes = self.g.all_nodes().enumerate().collect_vec();
         nodes.sort_by_key(|(_, n)| n.length);
 
@@ -354,23 +501,53 @@ impl<'a> HaploSearcher<'a> {
             if !self.used.contains(node_id)
                 && self.long_node(node_id)
                 && self.assignments.is_definite(node_id)
+                && target.is_none_or(|t| self.assignments.group(node_id) == Some(t))
+                && self
+                    .assignments
+                    .confidence(node_id)
+                    .is_none_or(|p| p >= self.settings.min_seed_confidence)
             {
                 let group = self.assignments.get(node_id).unwrap().group;
                 let path = self.haplo_path(Vertex::forward(node_id), group);
-                self.used
-                    .update_all(path.vertices().iter().map(|v| v.node_id), group);
-                self.used.get_mut(path.start().node_id).unwrap().info =
-                    String::from("path_boundary");
-                self.used.get_mut(path.end().node_id).unwrap().info = String::from("path_boundary");
+                self.mark_used(&path, group);
                 answer.push((path, node_id, group));
             }
         }
         answer
     }
 
+    fn mark_used(&mut self, path: &Path, group: TrioGroup) {
+        self.used
+            .update_all(path.vertices().iter().map(|v| v.node_id), group);
+        self.used.get_mut(path.start().node_id).unwrap().info = String::from("path_boundary");
+        self.used.get_mut(path.end().node_id).unwrap().info = String::from("path_boundary");
+    }
+
+    //the only definite group among the seed nodes,
+    //None if there is none, several or some of the nodes are assigned to ISSUE
+    fn seed_group(&self, seed: &Path) -> Option<TrioGroup> {
+        let mut seed_group = None;
+        for v in seed.vertices() {
+            match self.assignments.group(v.node_id) {
+                Some(TrioGroup::ISSUE) => return None,
+                Some(group) if group.is_definite() => {
+                    if seed_group.is_some_and(|g| g != group) {
+                        return None;
+                    }
+                    seed_group = Some(group);
+                }
+                _ => {}
+            }
+        }
+        seed_group
+    }
+
     fn haplo_path(&self, v: Vertex, group: TrioGroup) -> Path {
         assert!(self.assignments.group(v.node_id) == Some(group));
-        let mut path = Path::new(v);
+        self.extend_seed(Path::new(v), group)
+    }
+
+    fn extend_seed(&self, mut path: Path, group: TrioGroup) -> Path {
         self.grow_forward(&mut path, group);
         path = path.reverse_complement();
         self.grow_forward(&mut path, group);
@@ -573,6 +750,7 @@ impl<'a> HaploSearcher<'a> {
     fn unguided_next_or_gap(&self, v: Vertex, group: TrioGroup) -> Option<Path> {
         self.local_next(v, group, None)
             .or_else(|| self.assigned_aimed_ext(v, group))
+            .or_else(|| self.graph_gap_ahead(v, group))
             .or_else(|| self.gap_patch(v, group, self.settings.trusted_len))
             //FIXME this one might lead to interesting non-trivial issues
             .or_else(|| self.gap_patch(v, group, 0))
@@ -588,13 +766,13 @@ impl<'a> HaploSearcher<'a> {
 
         //dead-end case
         if self.g.outgoing_edge_cnt(v) == 0 {
-            let component =
-                dfs::ShortNodeComponent::back_from_long(self.g, v, short_node_threshold);
-
             //think of maybe relaxing
-            if !component.simple_boundary() {
-                return None;
-            }
+            let component = dfs::ShortNodeComponent::back_from_long_with(
+                self.g,
+                v,
+                short_node_threshold,
+                dfs::simple_boundary_guard(),
+            )?;
 
             only_or_none(component.sinks.iter().copied().filter(|&s| s != v)).map(|alt| {
                 (
@@ -638,18 +816,26 @@ impl<'a> HaploSearcher<'a> {
             "Searching for short-node component ahead of {}",
             self.g.v_str(alt)
         );
-        let component = dfs::ShortNodeComponent::ahead_from_long(self.g, alt, short_node_len);
-
         //think of maybe relaxing
-        if !component.simple_boundary()
-            || !component
-                .sources
-                .iter()
-                .all(|x| self.assignments.is_definite(x.node_id))
-        {
-            debug!("Bad component");
-            return None;
-        }
+        let component = match dfs::ShortNodeComponent::ahead_from_long_with(
+            self.g,
+            alt,
+            short_node_len,
+            dfs::simple_boundary_guard(),
+        ) {
+            Some(component)
+                if component
+                    .sources
+                    .iter()
+                    .all(|x| self.assignments.is_definite(x.node_id)) =>
+            {
+                component
+            }
+            _ => {
+                debug!("Bad component");
+                return None;
+            }
+        };
 
         if let Some(&w) = only_or_none(
             component
@@ -711,6 +897,32 @@ impl<'a> HaploSearcher<'a> {
         Some(Path::from_general_link(GeneralizedLink::GAP(gap_info)))
     }
 
+    //gaps provided within the graph (e.g. GFA2 G records) are only considered from dead-ends
+    fn graph_gap_ahead(&self, v: Vertex, group: TrioGroup) -> Option<Path> {
+        if self.g.outgoing_edge_cnt(v) > 0 {
+            return None;
+        }
+        let gap_info = only_or_none(
+            self.g
+                .outgoing_gaps(v)
+                .into_iter()
+                .filter(|gap| self.unassigned_or_compatible(gap.end.node_id, group)),
+        )?;
+        debug!(
+            "Candidate jump across graph gap to {}",
+            self.g.v_str(gap_info.end)
+        );
+        let gap_size = if self.g.gap_size_known(&gap_info) {
+            std::cmp::max(gap_info.gap_size, self.settings.min_gap_size)
+        } else {
+            self.settings.default_gap_size
+        };
+        Some(Path::from_general_link(GeneralizedLink::GAP(GapInfo {
+            gap_size,
+            ..gap_info
+        })))
+    }
+
     fn long_node(&self, node_id: usize) -> bool {
         self.g.node(node_id).length >= self.settings.solid_len
     }
@@ -743,7 +955,7 @@ impl<'a> HaploSearcher<'a> {
     fn raw_marker_excess(&self, v: &Vertex, group: TrioGroup) -> Option<i64> {
         let raw_cnts = self.raw_cnts?;
         let info = raw_cnts.get(&v.node_id)?;
-        let mmp = info.mat as i64 - info.pat as i64;
+        let mmp = info.mat() as i64 - info.pat() as i64;
         match group {
             TrioGroup::MATERNAL => Some(mmp),
             TrioGroup::PATERNAL => Some(-mmp),
@@ -788,13 +1000,30 @@ impl<'a> HaploSearcher<'a> {
             //Filling the bubble
             let mut direct_connectors = considered_extensions(self.g, v, consider_vertex_f)
                 .into_iter()
+                .filter(|l1| self.extension_helper.supported(l1))
                 .filter_map(|l1| self.g.connector(l1.end, w))
+                .filter(|l2| self.extension_helper.supported(l2))
                 .map(|l2| l2.start)
                 .filter(|tc_v| self.unassigned_or_compatible(tc_v.node_id, group))
                 .collect_vec();
 
+            //weakest link support along the bubble side
+            let side_support = |c: &Vertex| {
+                Some(std::cmp::min(
+                    self.g.link_support(&self.g.connector(v, *c)?)?,
+                    self.g.link_support(&self.g.connector(*c, w)?)?,
+                ))
+            };
+
             if !direct_connectors.is_empty() {
-                let c = if self.homozygous_bubble(v, w) {
+                let c = if let Some(c) = dominant_by_support(
+                    &direct_connectors,
+                    side_support,
+                    self.settings.link_support_ratio,
+                ) {
+                    debug!("Bubble side {} chosen by link support", self.g.v_str(c));
+                    c
+                } else if self.homozygous_bubble(v, w) {
                     let filtered_connectors = direct_connectors
                         .iter()
                         .filter(|&c| !self.used.contains(c.node_id))
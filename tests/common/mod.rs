@@ -0,0 +1,45 @@
+//Helpers shared by the integration tests (not every test uses all of them)
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static FILE_CNT: AtomicUsize = AtomicUsize::new(0);
+
+//Uniquely named path within the temporary directory (tests run in parallel),
+//the file is removed when the guard is dropped, even if the test fails
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    //file isn't created until written
+    pub fn new(suffix: &str) -> TempFile {
+        let path = std::env::temp_dir().join(format!(
+            "rukki_test_{}_{}{}",
+            std::process::id(),
+            FILE_CNT.fetch_add(1, Ordering::Relaxed),
+            suffix
+        ));
+        TempFile { path }
+    }
+
+    pub fn write<C: AsRef<[u8]>>(&self, content: C) {
+        std::fs::write(&self.path, content).unwrap();
+    }
+}
+
+impl Deref for TempFile {
+    type Target = PathBuf;
+
+    fn deref(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
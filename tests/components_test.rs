@@ -0,0 +1,73 @@
+use rukki::graph_algos::components::*;
+use rukki::*;
+
+fn three_components() -> Graph {
+    let s = "
+S a ACGT
+S b ACGT
+S c ACGT
+S d ACGT
+S e ACGT
+S f ACGTACGT
+L a + b + 0M
+L d + b - 0M
+L c + c - 1M RC:i:3
+J f + f + 10
+P p1 a+,b+,d- *
+";
+    Graph::read_from(
+        s.replace(' ', "\t").as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn split_components() {
+    let g = three_components();
+    assert_eq!(component_ids(&g), vec![0, 0, 1, 0, 2, 3]);
+    let parts = g.split_components();
+    assert_eq!(parts.len(), 4);
+    let (abd, ids) = &parts[0];
+    assert_eq!(ids, &vec![0, 1, 3]);
+    assert_eq!(abd.node_cnt(), 3);
+    assert_eq!(abd.link_cnt(), 2);
+    assert_eq!(abd.paths().len(), 1);
+    assert_eq!(abd.paths()[0].1.print(abd), "a+,b+,d-");
+    assert_eq!(abd.sequence(abd.name2id("d")), Some("ACGT"));
+
+    let (c, ids) = &parts[1];
+    assert_eq!(ids, &vec![2]);
+    let l = c.all_links().next().unwrap();
+    assert_eq!(c.l_str(l), "c+->c-");
+    assert_eq!(c.link_support(&l), Some(3));
+
+    let (f, ids) = &parts[3];
+    assert_eq!(ids, &vec![5]);
+    assert_eq!(f.gaps().len(), 1);
+    assert_eq!(f.link_cnt(), 0);
+}
+
+#[test]
+fn parallel_components() {
+    let g = three_components();
+    for threads in [1, 2, 8] {
+        let mut results = process_components(&g, threads, |part| {
+            part.all_nodes().map(|n| n.name.clone()).collect::<Vec<_>>()
+        });
+        //parts are grouped by length, so sorting for comparison
+        results.sort_by_key(|(_, ids)| ids[0]);
+        for (names, ids) in &results {
+            assert_eq!(names.len(), ids.len());
+            for (name, &node_id) in names.iter().zip(ids) {
+                assert_eq!(name, g.name(node_id));
+            }
+        }
+        let mut all_ids: Vec<usize> = results.into_iter().flat_map(|(_, ids)| ids).collect();
+        all_ids.sort();
+        assert_eq!(all_ids, (0..g.node_cnt()).collect::<Vec<_>>());
+    }
+}
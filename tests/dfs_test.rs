@@ -0,0 +1,185 @@
+use itertools::Itertools;
+use rukki::graph_algos::dfs::*;
+use rukki::graph_algos::scc;
+use rukki::*;
+
+fn chain(n: usize, cyclic: bool) -> Graph {
+    let mut g = Graph::new();
+    for i in 0..n {
+        g.add_node(Node {
+            name: format!("n{i}"),
+            length: 100,
+            coverage: 0.,
+        });
+    }
+    for i in 1..n {
+        g.add_link(Link {
+            start: Vertex::forward(i - 1),
+            end: Vertex::forward(i),
+            overlap: 10,
+        });
+    }
+    if cyclic {
+        g.add_link(Link {
+            start: Vertex::forward(n - 1),
+            end: Vertex::forward(0),
+            overlap: 10,
+        });
+    }
+    g.compact();
+    g
+}
+
+#[test]
+fn long_chain() {
+    let n = 100_000;
+    let g = chain(n, true);
+    let mut dfs = DFS::new_forward(&g);
+    dfs.run_from(Vertex::forward(0));
+    assert_eq!(dfs.exit_order().len(), n);
+    assert_eq!(dfs.exit_order()[0], Vertex::forward(n - 1));
+    assert_eq!(
+        dfs.boundary().iter().copied().collect_vec(),
+        vec![Vertex::forward(0)]
+    );
+
+    let sccs = scc::strongly_connected(&g);
+    assert_eq!(sccs.len(), 2);
+    assert!(sccs.iter().all(|c| c.len() == n));
+}
+
+#[test]
+fn exit_order() {
+    let s = "
+S a * LN:i:100
+S b * LN:i:100
+S c * LN:i:100
+S d * LN:i:1000
+L a + b + 10M
+L a + c + 10M
+L b + d + 10M
+L c + d + 10M
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let v = |name: &str| Vertex::forward(g.name2id(name));
+
+    let mut dfs = DFS::new_forward(&g);
+    dfs.set_max_node_len(500);
+    dfs.run_from(v("a"));
+    assert_eq!(dfs.exit_order(), &vec![v("b"), v("c"), v("a")]);
+    assert_eq!(dfs.boundary().len(), 1);
+    assert!(dfs.boundary().contains(&v("d")));
+
+    let mut dfs = DFS::new_reverse(&g);
+    dfs.run_from(v("d"));
+    assert_eq!(dfs.exit_order(), &vec![v("a"), v("b"), v("c"), v("d")]);
+    assert_eq!(dfs.dead_ends(), vec![v("a")]);
+
+    let mut events = Vec::new();
+    let mut dfs = DFS::new_forward(&g);
+    let control = dfs.run_from_with(v("a"), |e| {
+        events.push(e);
+        match e {
+            DfsEvent::Enter(x) if x == v("d") => DfsControl::Stop,
+            _ => DfsControl::Continue,
+        }
+    });
+    assert_eq!(control, DfsControl::Stop);
+    assert_eq!(
+        events,
+        vec![
+            DfsEvent::Enter(v("a")),
+            DfsEvent::Enter(v("b")),
+            DfsEvent::Enter(v("d"))
+        ]
+    );
+    assert!(dfs.exit_order().is_empty());
+}
+
+#[test]
+fn short_node_component() {
+    let s = "
+S a * LN:i:1000
+S b * LN:i:100
+S c * LN:i:1000
+L a + b + 10M
+L b + c + 10M
+L b + a - 10M
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let v = |name: &str| Vertex::forward(g.name2id(name));
+    let component = ShortNodeComponent::ahead_from_long(&g, v("a"), 500);
+    assert!(!component.simple_boundary());
+    assert!(component.sinks.contains(&v("a").rc()));
+    assert!(component.inner.contains(&v("b")));
+    assert!(
+        ShortNodeComponent::ahead_from_long_with(&g, v("a"), 500, simple_boundary_guard())
+            .is_none()
+    );
+
+    assert!(
+        ShortNodeComponent::back_from_long_with(&g, v("c"), 500, simple_boundary_guard()).is_none()
+    );
+
+    let g = Graph::read(&s.replace(' ', "\t").replace("L\tb\t+\ta\t-\t10M\n", ""));
+    let component =
+        ShortNodeComponent::back_from_long_with(&g, v("c"), 500, simple_boundary_guard()).unwrap();
+    assert_eq!(component.sinks.len(), 1);
+    assert!(component.sources.contains(&v("a")));
+    assert!(!component.has_deadends);
+}
+
+#[test]
+fn neighborhood_radius() {
+    use rukki::graph_algos::neighborhood::*;
+    let mut g = chain(10, false);
+    //making n7 long
+    let mut long = g.node(7).clone();
+    long.length = 10_000;
+    let mut h = Graph::new();
+    for i in 0..g.node_cnt() {
+        h.add_node(if i == 7 {
+            long.clone()
+        } else {
+            g.node(i).clone()
+        });
+    }
+    for l in g.all_links() {
+        h.add_link(l);
+    }
+    g = h;
+
+    assert_eq!(neighborhood(&g, &[4], Radius::Hops(0), usize::MAX), vec![4]);
+    assert_eq!(
+        neighborhood(&g, &[4], Radius::Hops(2), usize::MAX),
+        vec![2, 3, 4, 5, 6]
+    );
+    //lengths of nodes in between are counted
+    assert_eq!(
+        neighborhood(&g, &[4], Radius::Bases(99), usize::MAX),
+        vec![3, 4, 5]
+    );
+    assert_eq!(
+        neighborhood(&g, &[4], Radius::Bases(100), usize::MAX),
+        vec![2, 3, 4, 5, 6]
+    );
+    assert_eq!(
+        neighborhood(&g, &[1], Radius::Unbounded, usize::MAX),
+        (0..10).collect_vec()
+    );
+    //long node is included, but not passed
+    assert_eq!(
+        neighborhood(&g, &[4], Radius::Unbounded, 1000),
+        (0..8).collect_vec()
+    );
+    assert_eq!(neighborhood(&g, &[7], Radius::Hops(1), 1000), vec![6, 7, 8]);
+    //long seed is passed
+    assert_eq!(
+        neighborhood(&g, &[7], Radius::Unbounded, 1000),
+        (0..10).collect_vec()
+    );
+    assert_eq!(
+        neighborhood(&g, &[9], Radius::Bases(1000), 1000),
+        vec![7, 8, 9]
+    );
+}
@@ -1,3 +1,4 @@
+use itertools::Itertools;
 use rukki::*;
 
 #[test]
@@ -34,13 +35,56 @@ L a + a + 10M
 }
 
 #[test]
-#[should_panic]
 fn nontrivial_cigar() {
     let s = "
 S a * LN:i:100
+S b * LN:i:100
+S c * LN:i:100
 L a + a + 1D10M1I
+L a + b + 10M1I5M
+L b + c + 3S4=1X2D
 ";
-    Graph::read(&s.replace(' ', "\t"));
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let overlaps = g.all_links().map(|l| l.overlap).collect_vec();
+    assert_eq!(overlaps, vec![11, 16, 8]);
+    assert!(g.all_links().all(|l| g.overlap_known(&l)));
+
+    for cigar in ["10", "M", "10Q", "1M2"] {
+        let e = Graph::try_read(
+            &format!("S a * LN:i:100\nL a + a + {cigar}").replace(' ', "\t"),
+            false,
+            false,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(e.kind, GfaErrorKind::InvalidOverlap);
+    }
+}
+
+#[test]
+fn unknown_overlap() {
+    let s = "
+S a * LN:i:100
+S b * LN:i:100
+S c * LN:i:100
+L a + b + *
+L b + c + 10M
+P p a+,b+,c+ 5M,10M
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let a = Vertex::forward(g.name2id("a"));
+    let b = Vertex::forward(g.name2id("b"));
+    let c = Vertex::forward(g.name2id("c"));
+    let l_ab = g.connector(a, b).unwrap();
+    assert!(!g.overlap_known(&l_ab));
+    assert!(!g.overlap_known(&l_ab.rc()));
+    assert!(g.overlap_known(&g.connector(b, c).unwrap()));
+    assert!(g.as_gfa().contains("L\ta\t+\tb\t+\t*\n"));
+
+    let p = &g.paths()[0].1;
+    assert!(p.has_unknown_overlaps(&g));
+    assert_eq!(p.total_length(&g), 290);
+    assert!(!Path::from_link(g.connector(b, c).unwrap()).has_unknown_overlaps(&g));
 }
 
 #[test]
@@ -162,3 +206,661 @@ L b - a - 50M
     assert_eq!(1, g.link_cnt());
     assert_eq!(99, g.all_links().next().unwrap().overlap);
 }
+
+#[test]
+fn read_errors() {
+    let try_read = |s: &str| Graph::try_read(&s.replace(' ', "\t"), false, false).err();
+
+    let e = try_read("S a * LN:i:100\nL a + b + 10M").unwrap();
+    assert_eq!(e.line, 2);
+    assert_eq!(e.record, "L");
+    assert_eq!(e.field, "b");
+    assert_eq!(e.kind, GfaErrorKind::UnknownSegment);
+
+    let e = try_read("S a * LN:i:abc").unwrap();
+    assert_eq!((e.line, e.field.as_str()), (1, "LN:i:abc"));
+    assert_eq!(e.kind, GfaErrorKind::InvalidTag);
+
+    let e = try_read("S a *").unwrap();
+    assert_eq!(e.kind, GfaErrorKind::MissingLength);
+
+    let e = try_read("S a * LN:i:0").unwrap();
+    assert_eq!(e.kind, GfaErrorKind::ZeroLength);
+
+    let e = try_read("S a * LN:i:100\nS a * LN:i:100").unwrap();
+    assert_eq!((e.line, e.kind), (2, GfaErrorKind::DuplicateSegment));
+
+    let e = try_read("S a * LN:i:100\nL a + a x 10M").unwrap();
+    assert_eq!(
+        (e.field.as_str(), e.kind),
+        ("x", GfaErrorKind::InvalidDirection)
+    );
+
+    let e = try_read("S a * LN:i:100\nL a + a +").unwrap();
+    assert_eq!(e.kind, GfaErrorKind::TooFewColumns(6));
+
+    let e = try_read("S a * LN:i:100\nL a + a + 10Q").unwrap();
+    assert_eq!(
+        (e.field.as_str(), e.kind),
+        ("10Q", GfaErrorKind::InvalidOverlap)
+    );
+
+    let e = try_read("S a * LN:i:100\nS b * LN:i:200\nL a + b + 100M").unwrap();
+    assert_eq!((e.line, e.kind), (3, GfaErrorKind::OverlapTooLong(99)));
+}
+
+#[test]
+fn streaming_read() {
+    let s = "
+L a + b + 10M
+S a ACGTACGTACGT
+S b * LN:i:200
+L b + c - 5M
+S c ACGTAC RC:i:60
+";
+    let s = s.replace(' ', "\t");
+    let g = Graph::read_from(s.as_bytes(), &GfaReadSettings::default()).unwrap();
+    assert_eq!(3, g.node_cnt());
+    assert_eq!(2, g.link_cnt());
+    assert_eq!(12, g.node_by_name("a").length);
+    assert_eq!(10., g.node_by_name("c").coverage);
+    assert_eq!(None, g.sequence(g.name2id("a")));
+    let l = g
+        .connector(
+            Vertex::forward(g.name2id("a")),
+            Vertex::forward(g.name2id("b")),
+        )
+        .unwrap();
+    assert_eq!(10, l.overlap);
+
+    let g = Graph::read_from(
+        s.as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(Some("ACGTACGTACGT"), g.sequence(g.name2id("a")));
+    assert_eq!(None, g.sequence(g.name2id("b")));
+
+    let e = Graph::read_from(
+        "L\ta\t+\tb\t+\t10M\nS\ta\t*\tLN:i:100\n".as_bytes(),
+        &GfaReadSettings::default(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!((e.line, e.field.as_str()), (1, "b"));
+    assert_eq!(e.kind, GfaErrorKind::UnknownSegment);
+}
+
+#[test]
+fn gfa2_read() {
+    let s = "
+H VN:Z:2.0
+S a 100 * RC:i:1000
+S b 200 *
+S c 300 *
+E e1 a+ b+ 90 100$ 0 10 10M
+E e2 b+ c- 195 200$ 295 300$ *
+E e3 a+ c+ 10 20 10 20 *
+E * c+ a- 0 5 0 5 *
+G g1 b- a+ 1000 *
+";
+    //group references are separated by spaces
+    let s = s.replace(' ', "\t") + "O\to1\ta+ b+ c-\nU\tu1\te1 g1 b\n";
+    let g = Graph::read(&s);
+    assert_eq!(3, g.node_cnt());
+    assert_eq!(10., g.node_by_name("a").coverage);
+    assert_eq!(300, g.node_by_name("c").length);
+    let links = g.all_links().map(|l| (g.l_str(l), l.overlap)).collect_vec();
+    assert_eq!(
+        links,
+        vec![
+            (String::from("c-->a+"), 5),
+            (String::from("a+->b+"), 10),
+            (String::from("b+->c-"), 5)
+        ]
+    );
+
+    let a = Vertex::forward(g.name2id("a"));
+    let b = Vertex::forward(g.name2id("b"));
+    assert_eq!(1, g.gaps().len());
+    let gaps = g.outgoing_gaps(a.rc());
+    assert_eq!(1, gaps.len());
+    assert_eq!((gaps[0].start, gaps[0].end), (a.rc(), b));
+    assert_eq!((gaps[0].gap_size, gaps[0].info.as_str()), (1000, "g1"));
+    assert!(g.outgoing_gaps(b).is_empty());
+    assert_eq!(g.outgoing_gaps(b.rc()), vec![gaps[0].rc()]);
+
+    let o1 = g.node_group("o1").unwrap();
+    assert!(o1.ordered);
+    assert_eq!(o1.node_ids, vec![0, 1, 2]);
+    let u1 = g.node_group("u1").unwrap();
+    assert!(!u1.ordered);
+    assert_eq!(u1.node_ids, vec![0, 1]);
+}
+
+#[test]
+fn path_sequence() {
+    let s = "
+S a AACCG
+S b CGTTT
+S c GGGAA
+L a + b + 2M
+L b + c - 1M
+";
+    let g = Graph::read_from(
+        s.replace(' ', "\t").as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap();
+    let a = Vertex::forward(g.name2id("a"));
+    let b = Vertex::forward(g.name2id("b"));
+    let c = Vertex::forward(g.name2id("c"));
+    assert_eq!(Some(String::from("TTCCC")), g.vertex_sequence(c.rc()));
+
+    let mut p = Path::from_link(g.connector(a, b).unwrap());
+    p.append(g.connector(b, c.rc()).unwrap());
+    assert_eq!(Some(String::from("AACCGTTTTCCC")), p.sequence(&g));
+    assert_eq!(p.total_length(&g), p.sequence(&g).unwrap().len());
+    assert_eq!(
+        Some(String::from("GGGAAAACGGTT")),
+        p.clone().reverse_complement().sequence(&g)
+    );
+
+    let mut p = Path::new(a);
+    p.append_general(GeneralizedLink::GAP(GapInfo {
+        start: a,
+        end: c,
+        gap_size: 3,
+        info: String::from("test"),
+    }));
+    assert_eq!(Some(String::from("AACCGNNNGGGAA")), p.sequence(&g));
+
+    let g = Graph::read(&s.replace(' ', "\t"));
+    assert_eq!(None, Path::new(a).sequence(&g));
+}
+
+#[test]
+fn paths_read() {
+    let s = "
+S a * LN:i:100
+S b * LN:i:100
+S c * LN:i:100
+L a + b + 10M
+L b + c - 5M
+J b - a + 100
+P p1 a+,b+,c- 10M,5M
+P p2 c+,b-;a+ *
+P loop a+,b+,a+ *
+W sample 1 chr1 0 290 >a>b<c
+W sample 2 chr1 10 100 >c<b
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let names = g.paths().iter().map(|(n, _)| n.as_str()).collect_vec();
+    assert_eq!(
+        names,
+        vec!["p1", "p2", "sample#1#chr1", "sample#2#chr1:10-100"]
+    );
+    let printed = g.paths().iter().map(|(_, p)| p.print(&g)).collect_vec();
+    assert_eq!(printed[0], "a+,b+,c-");
+    assert_eq!(printed[1], "c+,b-,[N100N:gfa_gap],a+");
+    assert_eq!(printed[2], "a+,b+,c-");
+    assert_eq!(printed[3], "c+,b-");
+    assert_eq!(g.paths()[0].1.total_length(&g), 285);
+
+    let try_read = |s: &str| Graph::try_read(&s.replace(' ', "\t"), false, false).err();
+    let e = try_read("S a * LN:i:100\nS b * LN:i:100\nP p a+,b+ *").unwrap();
+    assert_eq!(
+        (e.line, e.field.as_str(), e.kind),
+        (3, "a+,b+", GfaErrorKind::MissingLink)
+    );
+    let e = try_read("S a * LN:i:100\nS b * LN:i:100\nL a + b + 10M\nP p a+,b+ 20M").unwrap();
+    assert_eq!((e.line, e.kind), (4, GfaErrorKind::OverlapMismatch(10)));
+    let e = try_read("S a * LN:i:100\nW s 1 chr 0 100 >a>c").unwrap();
+    assert_eq!(
+        (e.field.as_str(), e.kind),
+        ("c", GfaErrorKind::UnknownSegment)
+    );
+}
+
+#[test]
+fn gfa_roundtrip() {
+    let s = "
+S a ACGTACGT
+S b CCCCAAAA
+S c GGGG
+S d TTTT
+L a + b + 2M
+L b + c - 0M
+J c - d - 10
+J a - d + *
+P p1 a+,b+,c-;d- *
+";
+    let settings = GfaReadSettings {
+        keep_sequences: true,
+        ..GfaReadSettings::default()
+    };
+    let g = Graph::read_from(s.replace(' ', "\t").as_bytes(), &settings).unwrap();
+    let gfa = g.as_gfa();
+    assert!(gfa.contains("S\ta\tACGTACGT\tLN:i:8"));
+    assert!(gfa.contains("J\tc\t-\td\t-\t10\n"));
+    //unknown distance is kept unknown
+    assert!(gfa.contains("J\ta\t-\td\t+\t*\n"));
+    assert!(g.gap_size_known(&g.gaps()[0]));
+    assert!(!g.gap_size_known(&g.gaps()[1]));
+    assert!(gfa.contains("P\tp1\ta+,b+,c-;d-\t*\n"));
+
+    let g2 = Graph::read_from(gfa.as_bytes(), &settings).unwrap();
+    assert_eq!(g2.node_cnt(), 4);
+    assert_eq!(g2.link_cnt(), 2);
+    assert_eq!(g2.sequence(g2.name2id("b")), Some("CCCCAAAA"));
+    assert_eq!(g2.gaps().len(), 2);
+    assert!(!g2.gap_size_known(&g2.gaps()[1]));
+    assert_eq!(g2.paths().len(), 1);
+    assert_eq!(g2.paths()[0].1.print(&g2), "a+,b+,c-,[N10N:gfa_gap],d-");
+}
+
+#[test]
+fn link_support() {
+    let s = "
+S a * LN:i:100
+S b * LN:i:100
+S c * LN:i:100
+L a + b + 10M RC:i:5
+L b - a - 10M RC:i:3
+L b + c + 10M ex:i:7
+L a + c + 10M
+";
+    let g = Graph::read_sanitize(&s.replace(' ', "\t"));
+    let a = Vertex::forward(g.name2id("a"));
+    let b = Vertex::forward(g.name2id("b"));
+    let c = Vertex::forward(g.name2id("c"));
+    let l_ab = g.connector(a, b).unwrap();
+    assert_eq!(g.link_support(&l_ab), Some(8));
+    assert_eq!(g.link_support(&l_ab.rc()), Some(8));
+    assert_eq!(g.link_support(&g.connector(b, c).unwrap()), Some(7));
+    assert_eq!(g.link_support(&g.connector(a, c).unwrap()), None);
+    assert!(g.as_gfa().contains("L\ta\t+\tb\t+\t10M\tRC:i:8\n"));
+
+    let e = Graph::try_read(
+        &"S a * LN:i:100\nL a + a + 10M RC:i:x".replace(' ', "\t"),
+        false,
+        false,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        (e.field.as_str(), e.kind),
+        ("RC:i:x", GfaErrorKind::InvalidTag)
+    );
+}
+
+#[test]
+fn compact_adjacency() {
+    let s = "
+S a * LN:i:100
+S b * LN:i:100
+S c * LN:i:100
+L a + b + 10M
+L a + c - 5M
+L c + a - 7M
+L b + b - 3M
+";
+    let mut g = Graph::read(&s.replace(' ', "\t"));
+    let a = Vertex::forward(g.name2id("a"));
+    let b = Vertex::forward(g.name2id("b"));
+    let c = Vertex::forward(g.name2id("c"));
+    assert_eq!(
+        g.outgoing_neighbors(a),
+        &[
+            Neighbor {
+                end: b,
+                overlap: 10
+            },
+            Neighbor {
+                end: c.rc(),
+                overlap: 5
+            },
+            Neighbor {
+                end: c.rc(),
+                overlap: 7
+            }
+        ]
+    );
+    assert_eq!(
+        g.outgoing_neighbors(b),
+        &[Neighbor {
+            end: b.rc(),
+            overlap: 3
+        }]
+    );
+    assert_eq!(g.incoming_edge_iter(c.rc()).count(), 2);
+    assert_eq!(g.link_cnt(), 4);
+    let links = g.all_links().collect_vec();
+
+    //adding links and nodes after reading
+    let d = Vertex::forward(g.add_node(Node {
+        name: String::from("d"),
+        length: 100,
+        coverage: 0.,
+    }));
+    g.add_link(Link {
+        start: d,
+        end: a,
+        overlap: 1,
+    });
+    assert_eq!(
+        g.incoming_edges(a),
+        vec![Link {
+            start: d,
+            end: a,
+            overlap: 1
+        }]
+    );
+    assert_eq!(
+        g.outgoing_edges(a.rc()),
+        vec![Link {
+            start: a.rc(),
+            end: d.rc(),
+            overlap: 1
+        }]
+    );
+    g.compact();
+    assert_eq!(g.link_cnt(), 5);
+    let new_links = g.all_links().collect_vec();
+    assert!(links.iter().all(|l| new_links.contains(l)));
+}
+
+#[test]
+fn remove_nodes_and_links() {
+    let s = "
+S a ACGTACGTAC
+S b ACGTACGTAC
+S c ACGTACGTAC
+S d ACGTACGTAC
+L a + b + 2M RC:i:5
+L b + c + 2M
+L c + d - *
+L a + a - 1M
+P p1 a+,b+,c+ *
+P p2 c+,d- *
+";
+    let mut g = Graph::read_from(
+        s.replace(' ', "\t").as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap();
+    let a = Vertex::forward(g.name2id("a"));
+    let b = Vertex::forward(g.name2id("b"));
+    let hairpin = Link {
+        start: a,
+        end: a.rc(),
+        overlap: 1,
+    };
+    assert!(g.remove_link(&hairpin));
+    assert!(!g.remove_link(&hairpin));
+    assert_eq!(g.link_cnt(), 3);
+    assert!(g.outgoing_edges(a).iter().all(|l| l.end == b));
+
+    let id_map = g.remove_node(g.name2id("b"));
+    assert_eq!(id_map, vec![Some(0), None, Some(1), Some(2)]);
+    assert_eq!(g.node_cnt(), 3);
+    assert_eq!(g.name2id("c"), 1);
+    assert_eq!(g.link_cnt(), 1);
+    let l = g.all_links().next().unwrap();
+    assert_eq!(g.l_str(l), "c+->d-");
+    assert!(!g.overlap_known(&l));
+    assert_eq!(g.paths().len(), 1);
+    assert_eq!(g.paths()[0].0, "p2");
+    assert_eq!(g.sequence(g.name2id("d")), Some("ACGTACGTAC"));
+    assert_eq!(g.outgoing_edge_cnt(Vertex::forward(g.name2id("a"))), 0);
+
+    let id_map = g.remove_nodes(&[g.name2id("a"), g.name2id("d")]);
+    assert_eq!(id_map, vec![None, Some(0), None]);
+    assert_eq!(g.node_cnt(), 1);
+    assert_eq!(g.name2id("c"), 0);
+    assert_eq!(g.link_cnt(), 0);
+}
+
+#[test]
+fn unitig_compaction() {
+    let s = "
+S a ACGTA
+S b CGGGC
+S c TTTTT
+S d AAAAA
+S e AAACC
+L a + b + 1M RC:i:3
+L b + c - 0M
+L c - d + 0M
+L c - e + 0M
+J e + a + 10
+";
+    let g = Graph::read_from(
+        s.replace(' ', "\t").as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap();
+    let (cg, unitigs) = g.compact_unitigs();
+    //a-b-c- merged, d and e stay as is
+    assert_eq!(cg.node_cnt(), 3);
+    assert_eq!(unitigs.len(), 3);
+    let abc = cg.name2id("a_c");
+    assert_eq!(unitigs[abc].print(&g), "a+,b+,c-");
+    let n = cg.node(abc);
+    assert_eq!(n.length, 14);
+    assert_eq!(cg.sequence(abc), Some("ACGTAGGGCAAAAA"));
+    assert_eq!(cg.link_cnt(), 2);
+    let d = Vertex::forward(cg.name2id("d"));
+    let e = Vertex::forward(cg.name2id("e"));
+    assert_eq!(
+        cg.outgoing_edges(Vertex::forward(abc))
+            .iter()
+            .map(|l| l.end)
+            .collect_vec(),
+        vec![d, e]
+    );
+    assert_eq!(cg.gaps().len(), 1);
+    assert_eq!(cg.gaps()[0].start, e);
+    assert_eq!(cg.gaps()[0].end, Vertex::forward(abc));
+
+    let mut compacted_path = Path::new(e);
+    compacted_path.append_general(GeneralizedLink::GAP(cg.gaps()[0].clone()));
+    let expanded = g.expand_unitig_path(&compacted_path, &unitigs).unwrap();
+    assert!(g.expand_unitig_path(&compacted_path, &[]).is_err());
+    assert_eq!(expanded.print(&g), "e+,[N10N:gfa_gap],a+,b+,c-");
+    assert_eq!(expanded.gfa_line(&g, "x"), "P\tx\te+;a+,b+,c-\t*");
+}
+
+#[test]
+fn binary_roundtrip() {
+    let s = "
+S a ACGTACGT
+S b * LN:i:100 RC:i:500
+S c ACGTA
+L a + b - 3M RC:i:7
+L b - c + *
+L c + c - 1M
+J a - c - 20 ID:Z:j1
+J b + a + *
+U grp a
+P p1 a+,b-,c+ *
+P p2 c+;a+ *
+";
+    let g = Graph::read_from(
+        s.replace(' ', "\t").as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap();
+    let source = BinarySource {
+        size: 42,
+        mtime_secs: 1_700_000_000,
+        mtime_nanos: 5,
+    };
+    let mut buf = Vec::new();
+    g.save_binary(&mut buf, true, Some(&source)).unwrap();
+
+    let header = Graph::binary_header(&mut buf.as_slice()).unwrap();
+    assert_eq!(
+        header,
+        BinaryHeader {
+            version: BINARY_VERSION,
+            has_sequences: true,
+            source: Some(source),
+        }
+    );
+    let loaded = Graph::load_binary(&mut buf.as_slice(), true).unwrap();
+    assert_eq!(loaded.as_gfa(), g.as_gfa());
+    assert!(!loaded.gap_size_known(&loaded.gaps()[1]));
+    assert_eq!(loaded.node_groups().len(), 1);
+    assert_eq!(loaded.node_groups()[0].node_ids, vec![0]);
+    let l = loaded.all_links().find(|l| l.overlap == 3).unwrap();
+    assert_eq!(loaded.link_support(&l), Some(7));
+
+    //sequences can be skipped
+    let loaded = Graph::load_binary(&mut buf.as_slice(), false).unwrap();
+    assert_eq!(loaded.sequence(0), None);
+    assert_eq!(loaded.node(0).length, 8);
+
+    //corrupted or truncated input
+    assert!(Graph::load_binary(&mut &buf[..buf.len() - 20], true).is_err());
+    let mut wrong_version = buf.clone();
+    wrong_version[4] = 100;
+    assert!(Graph::binary_header(&mut wrong_version.as_slice()).is_err());
+    assert!(Graph::load_binary(&mut &b"S\ta\t*"[..], true).is_err());
+}
+
+#[test]
+fn gfa_lint() {
+    let s = "
+S a * LN:i:100
+S b * LN:i:10
+S a * LN:i:50
+S c *
+L a + b + 20M
+L a + x - 5M
+L b - a - 5M
+L a + b + 5M
+L a + c + 5Q
+J y + a + 10
+S d ACGT
+";
+    let (issues, names) = Graph::lint_gfa(s.replace(' ', "\t").as_bytes());
+    let brief = issues
+        .iter()
+        .map(|e| (e.line, e.kind.clone()))
+        .collect_vec();
+    assert_eq!(
+        brief,
+        vec![
+            (4, GfaErrorKind::DuplicateSegment),
+            (5, GfaErrorKind::MissingLength),
+            (6, GfaErrorKind::OverlapTooLong(9)),
+            (7, GfaErrorKind::UnknownSegment),
+            (8, GfaErrorKind::ConflictingOverlap(20)),
+            (9, GfaErrorKind::ConflictingOverlap(20)),
+            (10, GfaErrorKind::InvalidOverlap),
+            (11, GfaErrorKind::UnknownSegment),
+        ]
+    );
+    assert_eq!(issues[3].field, "x");
+    assert_eq!(
+        (issues[7].record.as_str(), issues[7].field.as_str()),
+        ("J", "y")
+    );
+    //malformed segments are still named
+    assert_eq!(
+        names.iter().sorted().collect_vec(),
+        vec!["a", "b", "c", "d"]
+    );
+    assert!(
+        Graph::lint_gfa("S\ta\tACGT\nL\ta\t+\ta\t-\t1M\n".as_bytes())
+            .0
+            .is_empty()
+    );
+}
+
+#[test]
+fn node_splitting() {
+    let s = "
+S a ACGTACGTAC
+S b GGGG
+S c TTTT
+L b + a + 0M
+L a + c - 0M
+P p b+,a+,c- *
+P q c+,a-,b- *
+";
+    let g = Graph::read_from(
+        s.replace(' ', "\t").as_bytes(),
+        &GfaReadSettings {
+            keep_sequences: true,
+            ..GfaReadSettings::default()
+        },
+    )
+    .unwrap();
+    let splits = std::collections::HashMap::from([(g.name2id("a"), vec![7, 3, 0, 10])]);
+    let (sg, pieces) = g.split_nodes(&splits);
+    assert_eq!(sg.node_cnt(), 5);
+    assert_eq!(pieces[g.name2id("a")].len(), 3);
+    assert_eq!(pieces[g.name2id("b")], vec![sg.name2id("b")]);
+    assert_eq!(
+        pieces[g.name2id("a")]
+            .iter()
+            .map(|&n| (sg.name(n), sg.node_length(n), sg.sequence(n).unwrap()))
+            .collect_vec(),
+        vec![("a_1", 3, "ACG"), ("a_2", 4, "TACG"), ("a_3", 3, "TAC")]
+    );
+    assert_eq!(sg.link_cnt(), 4);
+    assert_eq!(
+        sg.paths()
+            .iter()
+            .map(|(name, p)| format!("{}:{}", name, p.print(&sg)))
+            .collect_vec(),
+        vec!["p:b+,a_1+,a_2+,a_3+,c-", "q:c+,a_3-,a_2-,a_1-,b-"]
+    );
+
+    //positions within link overlaps (first 2 and last 3 bases of 'a') are skipped
+    let g = Graph::read(
+        &s.replace("0M", "2M")
+            .replace("a + c - 2M", "a + c - 3M")
+            .replace(' ', "\t"),
+    );
+    let splits = std::collections::HashMap::from([(g.name2id("a"), vec![1, 2, 5, 7, 8])]);
+    let (sg, pieces) = g.split_nodes(&splits);
+    assert_eq!(
+        pieces[g.name2id("a")]
+            .iter()
+            .map(|&n| sg.node_length(n))
+            .collect_vec(),
+        vec![2, 3, 2, 3]
+    );
+    assert_eq!(
+        sg.outgoing_edges(Vertex::forward(sg.name2id("b")))
+            .iter()
+            .map(|l| (sg.name(l.end.node_id), l.overlap))
+            .collect_vec(),
+        vec![("a_1", 2)]
+    );
+    assert_eq!(
+        sg.outgoing_edges(Vertex::forward(sg.name2id("a_4")))
+            .iter()
+            .map(|l| (sg.name(l.end.node_id), l.overlap))
+            .collect_vec(),
+        vec![("c", 3)]
+    );
+}
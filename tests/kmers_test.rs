@@ -0,0 +1,120 @@
+extern crate log;
+
+mod common;
+
+use common::TempFile;
+use rukki::kmers::*;
+use rukki::trio::TrioGroup;
+use rukki::*;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn kmer_set(k: usize, kmers: &[&str]) -> KmerSet {
+    let mut set = KmerSet::new(k).unwrap();
+    for kmer in kmers {
+        assert!(set.insert(kmer));
+    }
+    set
+}
+
+#[test]
+fn kmer_hits() {
+    init();
+
+    assert!(KmerSet::new(0).is_err());
+    assert!(KmerSet::new(33).is_err());
+
+    let mut set = kmer_set(4, &["ACGG", "TTAA"]);
+    //canonical k-mers are stored
+    assert_eq!(set.len(), 2);
+    assert!(set.contains("CCGT"));
+    assert!(!set.insert("ACG"));
+    assert!(!set.insert("ACNG"));
+    assert!(set.insert("ccgt"));
+    assert_eq!(set.len(), 2);
+
+    //both orientations, overlapping hits and k-mers spanning non-ACGT characters
+    assert_eq!(set.hits("ACGGTTAAN"), vec![0, 4]);
+    assert_eq!(set.hits("CCGTT"), vec![0]);
+    assert_eq!(set.hits("TTAAA"), vec![0]);
+    assert_eq!(set.hits("ACGNG"), Vec::<usize>::new());
+    assert_eq!(set.hits("TTTAAA"), vec![1]);
+
+    let set = kmer_set(32, &["ACGTACGTACGTACGTACGTACGTACGTACGG"]);
+    assert_eq!(set.hits("TCCGTACGTACGTACGTACGTACGTACGTACGTA"), vec![1]);
+}
+
+#[test]
+fn kmer_list_reading() {
+    init();
+
+    let text_fn = TempFile::new(".txt");
+    text_fn.write("# meryl print output\nACGG\t12\n\nttaa\t3\n");
+    let set = KmerSet::read(&text_fn, 4).unwrap();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains("CCGT") && set.contains("TTAA"));
+
+    text_fn.write("ACGG\t12\nACGGT\t3\n");
+    assert!(matches!(
+        KmerSet::read(&text_fn, 4),
+        Err(Error::Table(s)) if s.contains("line 2")
+    ));
+
+    let bin_fn = TempFile::new(".rkk");
+    let mut output = Vec::new();
+    set.write_binary(&mut output).unwrap();
+    bin_fn.write(output);
+    let loaded = KmerSet::read(&bin_fn, 4).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert!(loaded.contains("CCGT") && loaded.contains("TTAA"));
+    assert!(matches!(KmerSet::read(&bin_fn, 5), Err(Error::Settings(_))));
+}
+
+#[test]
+fn node_marker_counts() {
+    init();
+
+    let s = "
+S a ACGGTTAAACGG
+S b CCGTA
+S c * LN:i:10
+L a + b + 1M
+";
+    let settings = GfaReadSettings {
+        keep_sequences: true,
+        ..GfaReadSettings::default()
+    };
+    let g = Graph::read_from(s.replace(' ', "\t").as_bytes(), &settings).unwrap();
+    let mat = kmer_set(4, &["ACGG"]);
+    let pat = kmer_set(4, &["TTAA"]);
+    //missing sequence
+    assert!(matches!(
+        count_hits(&g, &mat, &pat),
+        Err(Error::Gfa(e)) if e.kind == GfaErrorKind::MissingSequence && e.field == "c"
+    ));
+
+    let g = Graph::read_from(
+        s.replace("S c * LN:i:10\n", "")
+            .replace(' ', "\t")
+            .as_bytes(),
+        &settings,
+    )
+    .unwrap();
+    let hits = count_hits(&g, &mat, &pat).unwrap();
+    let a = hits[g.name2id("a")].trio_info(&g);
+    assert_eq!((a.node_name.as_str(), a.mat(), a.pat()), ("a", 2, 1));
+    assert_eq!(
+        hits[g.name2id("a")].positions(),
+        vec![
+            (0, TrioGroup::MATERNAL),
+            (4, TrioGroup::PATERNAL),
+            (8, TrioGroup::MATERNAL)
+        ]
+    );
+    let b = hits[g.name2id("b")].trio_info(&g);
+    assert_eq!((b.mat(), b.pat()), (1, 0));
+
+    assert!(count_hits(&g, &mat, &kmer_set(5, &[])).is_err());
+}
@@ -0,0 +1,161 @@
+extern crate log;
+
+mod common;
+use itertools::Itertools;
+
+use common::TempFile;
+use rukki::trio::*;
+use rukki::trio_walk::{haplotype_paths, HaploSearchSettings};
+use rukki::*;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn single(hap: usize) -> HaploSet {
+    HaploSet::single(hap).unwrap()
+}
+
+#[test]
+fn haplo_set_rules() {
+    let all = HaploSet::all(4).unwrap();
+    let s01 = single(0).union(single(1));
+    assert_eq!(all.len(), 4);
+    assert_eq!(s01.haplotypes().collect_vec(), vec![0, 1]);
+    assert_eq!(single(2).definite(), Some(2));
+    assert_eq!(s01.definite(), None);
+    assert!(HaploSet::compatible(s01, single(1)));
+    assert!(HaploSet::incompatible(s01, single(3)));
+    assert!(HaploSet::compatible(all, single(3)));
+    assert_eq!(HaploSet::blend(s01, single(3)).len(), 3);
+    //trio groups are the diploid case
+    assert_eq!(
+        TrioGroup::HOMOZYGOUS.haplo_set(),
+        Some(HaploSet::all(2).unwrap())
+    );
+    assert_eq!(TrioGroup::ISSUE.haplo_set(), None);
+    assert_eq!(single(1).trio_group(), Some(TrioGroup::PATERNAL));
+    assert_eq!(s01.union(single(2)).trio_group(), None);
+    assert_eq!(
+        TrioGroup::blend(TrioGroup::MATERNAL, TrioGroup::PATERNAL),
+        TrioGroup::HOMOZYGOUS
+    );
+    assert!(TrioGroup::incompatible(
+        TrioGroup::MATERNAL,
+        TrioGroup::PATERNAL
+    ));
+    assert!(TrioGroup::compatible(
+        TrioGroup::HOMOZYGOUS,
+        TrioGroup::PATERNAL
+    ));
+    assert!(TrioGroup::incompatible(
+        TrioGroup::ISSUE,
+        TrioGroup::HOMOZYGOUS
+    ));
+    assert_eq!(s01.project(0), TrioGroup::HOMOZYGOUS);
+    assert_eq!(s01.project(2), TrioGroup::PATERNAL);
+    assert_eq!(single(2).project(2), TrioGroup::MATERNAL);
+
+    let names = ["h1", "h2", "h3", "h4"].map(String::from);
+    assert_eq!(s01.format(&names), "h1+h2");
+    assert_eq!(all.format(&names), "all");
+
+    assert!(HaploSet::single(HaploSet::MAX_HAPLOTYPES).is_err());
+    assert_eq!(HaploSet::all(HaploSet::MAX_HAPLOTYPES).unwrap().len(), 64);
+    assert!(HaploSet::all(HaploSet::MAX_HAPLOTYPES + 1).is_err());
+    assert!(HaploAssignments::new(HaploSet::MAX_HAPLOTYPES + 1).is_err());
+    let mut assignments = HaploAssignments::new(2).unwrap();
+    assert!(assignments.assign(0, single(1), "").is_ok());
+    assert!(assignments.assign(0, single(2), "").is_err());
+    assert!(assignments.assign(0, HaploSet::empty(), "").is_err());
+}
+
+#[test]
+fn tetraploid_paths() {
+    init();
+
+    let graph_s = "
+S\tx\t*\tLN:i:600000
+S\ty\t*\tLN:i:100000
+S\tz\t*\tLN:i:600000
+S\tw\t*\tLN:i:600000
+S\tv\t*\tLN:i:600000
+S\tu\t*\tLN:i:600000
+L\tx\t+\ty\t+\t0M
+L\ty\t+\tz\t+\t0M
+L\tw\t+\ty\t+\t0M
+L\ty\t+\tv\t+\t0M
+"
+    .trim()
+    .to_string();
+    let g = Graph::read(&graph_s);
+
+    let markers_fn = TempFile::new(".tsv");
+    markers_fn.write(
+        "node\th1\th2\th3\th4\nx\t100\t0\t1\t0\ny\t50\t40\t0\t2\nz\t100\t3\t0\t0\nw\t0\t100\t0\t0\nv\t0\t90\t0\t0\nu\t30\t30\t30\t30\n",
+    );
+    let (names, infos) = read_haplotype_markers(&markers_fn, None, DuplicateMarkers::Sum).unwrap();
+    assert_eq!(names, vec!["h1", "h2", "h3", "h4"]);
+
+    let assignments = assign_haplo_sets(
+        &g,
+        &infos,
+        names.len(),
+        &HaploAssignmentSettings {
+            assign_cnt: 10,
+            assign_sparsity: 10_000,
+            assign_ratio: 5.,
+        },
+    )
+    .unwrap();
+    let sets = |n: &str| assignments.set(g.name2id(n)).map(|s| s.format(&names));
+    assert_eq!(sets("x").as_deref(), Some("h1"));
+    assert_eq!(sets("y").as_deref(), Some("h1+h2"));
+    assert_eq!(sets("w").as_deref(), Some("h2"));
+    //comparable counts for all haplotypes
+    assert_eq!(sets("u"), None);
+
+    let hap_paths = haplotype_paths(&g, &assignments, HaploSearchSettings::default());
+    let printed = |hap: usize| {
+        hap_paths[hap]
+            .iter()
+            .map(|(p, _)| p.print(&g))
+            .sorted()
+            .collect_vec()
+    };
+    assert_eq!(printed(0), vec!["x+,y+,z+"]);
+    assert_eq!(printed(1), vec!["w+,y+,v+"]);
+    assert!(hap_paths[2].is_empty() && hap_paths[3].is_empty());
+
+    let selected = ["h4", "h1"].map(String::from);
+    let (_, infos) =
+        read_haplotype_markers(&markers_fn, Some(&selected), DuplicateMarkers::Sum).unwrap();
+    assert_eq!(infos[0].counts(), [0, 100]);
+
+    let settings = HaploAssignmentSettings {
+        assign_cnt: 10,
+        assign_sparsity: 10_000,
+        assign_ratio: 5.,
+    };
+    //all unknown nodes are reported at once
+    markers_fn.write("node\th1\th2\nx\t1\t2\na\t0\t1\n# comment\nb\t3\t0\nx\t4\t4\n");
+    let (names, infos) = read_haplotype_markers(&markers_fn, None, DuplicateMarkers::Sum).unwrap();
+    assert_eq!(infos[0].counts(), [5, 6]);
+    assert_eq!(
+        assign_haplo_sets(&g, &infos, names.len(), &settings)
+            .err()
+            .unwrap()
+            .to_string(),
+        "Invalid markers: 2 nodes are not in the graph: a (line 3), b (line 5)"
+    );
+    assert!(matches!(
+        assign_haplo_sets(&g, &infos[..1], 4, &settings),
+        Err(Error::Markers(_))
+    ));
+    assert_eq!(
+        read_haplotype_markers(&markers_fn, None, DuplicateMarkers::Reject)
+            .unwrap_err()
+            .to_string(),
+        "Invalid markers: node x is listed in both line 2 and line 6"
+    );
+}
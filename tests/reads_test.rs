@@ -0,0 +1,99 @@
+extern crate log;
+
+mod common;
+
+use common::TempFile;
+use rukki::reads::*;
+use rukki::trio::{assign_parental_groups, GroupAssignmentSettings, TrioGroup};
+use rukki::*;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn gaf_line(read: &str, path: &str, path_len: usize, start: usize, end: usize) -> String {
+    format!(
+        "{read}\t{0}\t0\t{0}\t+\t{path}\t{path_len}\t{start}\t{end}\t{0}\t{0}\t60\n",
+        end - start
+    )
+}
+
+#[test]
+fn read_haplotype_aggregation() {
+    init();
+
+    let s = "
+S a * LN:i:5000
+S b * LN:i:3000
+S c * LN:i:4000
+L a + b + 1000M
+L b + c - 0M
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+
+    let haps_fn = TempFile::new(".tsv");
+    haps_fn.write("read\thaplotype\nr1\tmat\nr2\tpat\nr3\tunknown\nr4\tmaternal\nr1\tmat\n");
+    let haps = read_read_haplotypes(&haps_fn, ("mat", "pat")).unwrap();
+    assert_eq!(haps.len(), 3);
+    assert_eq!(haps["r4"], TrioGroup::MATERNAL);
+
+    let gaf_fn = TempFile::new(".gaf");
+    //a+b path is 7000 bases long (overlap trimmed), b starts at 4000
+    let gaf = [
+        gaf_line("r1", ">a>b", 7000, 2000, 6000),
+        gaf_line("r2", "<c", 4000, 0, 4000),
+        gaf_line("r3", ">a", 5000, 0, 5000),
+        gaf_line("r4", "a", 5000, 0, 2500),
+        gaf_line("r5", ">b", 3000, 0, 3000),
+        gaf_line("r2", ">b<c", 7000, 2000, 4600),
+    ]
+    .concat();
+    gaf_fn.write(&gaf);
+
+    let infos =
+        aggregate_read_haplotypes(&g, &gaf_fn, &haps, &ReadAggregationSettings::default()).unwrap();
+    let counts: Vec<(&str, usize, usize)> = infos
+        .iter()
+        .map(|i| (i.node_name.as_str(), i.mat(), i.pat()))
+        .collect();
+    //a: 3000 + 2500 maternal bases, b: 2000 maternal and 1000 paternal, c: 4000 + 1600 paternal
+    assert_eq!(counts, vec![("a", 6, 0), ("b", 2, 1), ("c", 0, 6)]);
+
+    let settings = ReadAggregationSettings {
+        min_mapq: 61,
+        ..ReadAggregationSettings::default()
+    };
+    assert!(aggregate_read_haplotypes(&g, &gaf_fn, &haps, &settings)
+        .unwrap()
+        .is_empty());
+
+    //derived counts feed the regular assignment
+    let assignments = assign_parental_groups(
+        &g,
+        &infos,
+        &GroupAssignmentSettings {
+            assign_cnt: 5,
+            assign_sparsity: 1000,
+            assign_ratio: 5.,
+            ..GroupAssignmentSettings::default()
+        },
+        1000,
+        0.,
+    )
+    .unwrap();
+    assert_eq!(assignments.group(g.name2id("a")), Some(TrioGroup::MATERNAL));
+    assert_eq!(assignments.group(g.name2id("c")), Some(TrioGroup::PATERNAL));
+    assert_eq!(assignments.group(g.name2id("b")), None);
+
+    gaf_fn.write(gaf_line("r1", ">a>x", 7000, 0, 10));
+    assert!(matches!(
+        aggregate_read_haplotypes(&g, &gaf_fn, &haps, &ReadAggregationSettings::default()),
+        Err(Error::Markers(s)) if s.contains("node x")
+    ));
+
+    haps_fn.write("r1\tmat\nr1\tpat\n");
+    assert!(matches!(
+        read_read_haplotypes(&haps_fn, ("mat", "pat")),
+        Err(Error::Markers(s)) if s.contains("line 1 and line 2")
+    ));
+}
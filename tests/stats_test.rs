@@ -0,0 +1,72 @@
+use rukki::graph_algos::components::weakly_connected;
+use rukki::stats::*;
+use rukki::*;
+
+#[test]
+fn basic_stats() {
+    let s = "
+S a * LN:i:100 RC:i:1000
+S b * LN:i:20
+S c * LN:i:30
+S d * LN:i:100 RC:i:3000
+S e * LN:i:50
+S f * LN:i:10
+L a + b + 0M
+L a + c + 0M
+L b + d + 0M
+L c + d + 0M
+L e + e + 0M
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    assert_eq!(
+        weakly_connected(&g),
+        vec![vec![0, 1, 2, 3], vec![4], vec![5]]
+    );
+    let stats = GraphStats::compute(
+        &g,
+        &StatsParams {
+            solid_len: 100,
+            ..StatsParams::default()
+        },
+    );
+    assert_eq!(stats.node_cnt, 6);
+    assert_eq!(stats.link_cnt, 5);
+    assert_eq!(stats.total_length, 310);
+    assert_eq!(stats.n50, 100);
+    //a-, d+ and both orientations of f
+    assert_eq!(stats.dead_end_cnt, 4);
+    assert_eq!(
+        stats
+            .components
+            .iter()
+            .map(|c| (c.node_cnt, c.length))
+            .collect::<Vec<_>>(),
+        vec![(4, 250), (1, 50), (1, 10)]
+    );
+    //loop e+ -> e+ and its reverse-complement
+    assert_eq!(stats.scc_sizes, vec![1, 1]);
+    assert_eq!(stats.bubble_chains.len(), 1);
+    let chain = &stats.bubble_chains[0];
+    assert_eq!(chain.bubble_cnt, 1);
+    assert_eq!((chain.min_length, chain.max_length), (220, 230));
+    assert!((stats.solid_cov - 20.).abs() < 1e-6);
+
+    let mut tsv = Vec::new();
+    stats.write_tsv(&mut tsv).unwrap();
+    let tsv = String::from_utf8(tsv).unwrap();
+    assert!(tsv.contains("n50\t100\n"));
+    assert!(tsv.contains("component_lengths\t250,50,10\n"));
+
+    let mut json = Vec::new();
+    stats.write_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains("\"components\": [{\"nodes\": 4, \"length\": 250}, "));
+    assert!(json.trim_end().ends_with('}'));
+}
+
+#[test]
+fn n50_values() {
+    assert_eq!(n50([10, 20, 30, 40].into_iter()), 30);
+    assert_eq!(n50([5].into_iter()), 5);
+    assert_eq!(n50(std::iter::empty()), 0);
+}
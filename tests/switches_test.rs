@@ -0,0 +1,114 @@
+extern crate log;
+
+mod common;
+use itertools::Itertools;
+
+use common::TempFile;
+use rukki::switches::*;
+use rukki::trio::TrioGroup;
+use rukki::*;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn markers(groups: &str) -> Vec<PositionalMarker> {
+    groups
+        .chars()
+        .enumerate()
+        .map(|(i, c)| PositionalMarker {
+            pos: 100 * i,
+            group: if c == 'm' {
+                TrioGroup::MATERNAL
+            } else {
+                TrioGroup::PATERNAL
+            },
+            line: i + 1,
+        })
+        .collect()
+}
+
+#[test]
+fn marker_segmentation() {
+    init();
+
+    let settings = SegmentationSettings::default();
+    //isolated disagreeing markers do not cause switches
+    let segments = segment_markers(&markers("mmmmpmmmmmmpmmmm"), &settings);
+    assert_eq!(segments.len(), 1);
+    assert_eq!(
+        (segments[0].group, segments[0].mat, segments[0].pat),
+        (TrioGroup::MATERNAL, 14, 2)
+    );
+
+    let segments = segment_markers(&markers("mmmmmmmmmmpmppppppppp"), &settings);
+    assert_eq!(
+        segments
+            .iter()
+            .map(|s| (s.group, s.first_pos, s.last_pos))
+            .collect_vec(),
+        vec![
+            (TrioGroup::MATERNAL, 0, 900),
+            (TrioGroup::PATERNAL, 1000, 2000)
+        ]
+    );
+
+    assert!(segment_markers(&[], &settings).is_empty());
+}
+
+#[test]
+fn switch_detection() {
+    init();
+
+    let g = Graph::read(&"S a * LN:i:10000\nS b * LN:i:5000".replace(' ', "\t"));
+    let markers_fn = TempFile::new(".tsv");
+    let mut table = String::from("node\tpos\thap\n");
+    for i in 0..10 {
+        table += &format!("a\t{}\tmat\n", 1000 + 100 * i);
+        table += &format!("a\t{}\tpaternal\n", 6000 + 100 * i);
+        table += &format!("b\t{}\tpat\n", 100 * i);
+    }
+    markers_fn.write(&table);
+    let markers = read_positional_markers(&markers_fn, ("mat", "pat")).unwrap();
+    assert_eq!(markers["a"].len(), 20);
+    assert!(markers["a"].windows(2).all(|w| w[0].pos <= w[1].pos));
+
+    let segmentations = segment_nodes(&g, &markers, &SegmentationSettings::default()).unwrap();
+    let switches = segmentations
+        .iter()
+        .flat_map(|s| s.switches())
+        .collect_vec();
+    assert_eq!(
+        switches,
+        vec![Switch {
+            node_id: g.name2id("a"),
+            last_pos: 1900,
+            next_pos: 6000,
+            from: TrioGroup::MATERNAL,
+            to: TrioGroup::PATERNAL,
+        }]
+    );
+    assert_eq!(switches[0].split_pos(), 3950);
+
+    let mut bed = Vec::new();
+    write_switches(&g, &switches, ("mat", "pat"), &mut bed).unwrap();
+    assert_eq!(String::from_utf8(bed).unwrap(), "a\t1900\t6001\tmat>pat\n");
+
+    let counts = piece_counts(&markers["a"], &[3950], &["a_1", "a_2"]);
+    assert_eq!(
+        counts.iter().map(|ti| (ti.mat(), ti.pat())).collect_vec(),
+        vec![(10, 0), (0, 10)]
+    );
+
+    markers_fn.write("x\t10\tmat\na\t5\tfoo\n");
+    assert!(matches!(
+        read_positional_markers(&markers_fn, ("mat", "pat")),
+        Err(Error::Markers(_))
+    ));
+    markers_fn.write("x\t10\tmat\n");
+    let markers = read_positional_markers(&markers_fn, ("mat", "pat")).unwrap();
+    assert!(matches!(
+        segment_nodes(&g, &markers, &SegmentationSettings::default()),
+        Err(Error::Markers(_))
+    ));
+}
@@ -1,6 +1,9 @@
 extern crate log;
+
+mod common;
 use itertools::Itertools;
 
+use common::TempFile;
 use rukki::trio::*;
 use rukki::*;
 use std::fs;
@@ -32,3 +35,296 @@ fn homozygous_assignment() {
         &["utig4-1237", "utig4-1552", "utig4-1826", "utig4-2589"]
     );
 }
+
+#[test]
+fn coverage_table() {
+    init();
+
+    let s = "
+S a * LN:i:100 RC:i:1000
+S b * LN:i:100 RC:i:2000
+S c * LN:i:100
+";
+    let mut g = Graph::read(&s.replace(' ', "\t"));
+    let coverage_fn = TempFile::new(".tsv");
+    coverage_fn.write("node\tcoverage\nb\t35.5\nc\t12\nx\t1\n");
+    let coverages = read_coverage(&coverage_fn).unwrap();
+    assert_eq!(coverages.len(), 3);
+    apply_coverage(&mut g, &coverages);
+    assert_eq!(g.node(g.name2id("a")).coverage, 10.);
+    assert_eq!(g.node(g.name2id("b")).coverage, 35.5);
+    assert_eq!(g.node(g.name2id("c")).coverage, 12.);
+
+    //per-haplotype split
+    coverage_fn.write("a\t7\t8.5\n");
+    let coverages = read_coverage(&coverage_fn).unwrap();
+    assert_eq!(coverages[0].coverage, 15.5);
+    apply_coverage(&mut g, &coverages);
+    assert_eq!(g.node(g.name2id("a")).coverage, 15.5);
+
+    //comments and header with the node name column called as in the marker table
+    coverage_fn.write("# coverage by haplotype\nname\tmat\tpat\nb\t1\t2\n#c\t3\n");
+    let coverages = read_coverage(&coverage_fn).unwrap();
+    assert_eq!(coverages.len(), 1);
+    assert_eq!(
+        (coverages[0].node_name.as_str(), coverages[0].coverage),
+        ("b", 3.)
+    );
+    coverage_fn.write("name\tcoverage\nc\t4\n");
+    assert_eq!(read_coverage(&coverage_fn).unwrap()[0].coverage, 4.);
+
+    for invalid in ["a\tx\n", "a\t-1\n", "a\n", "a\t1\t2\t3\n"] {
+        coverage_fn.write(invalid);
+        assert!(read_coverage(&coverage_fn).is_err());
+    }
+}
+
+#[test]
+fn marker_lint() {
+    init();
+
+    let markers_fn = TempFile::new(".tsv");
+    markers_fn.write("node\tmat\tpat\na\t10\t0\nx\t1\t2\nb\t1.5\t-2\nc\t3\n");
+    let is_known = |name: &str| ["a", "b", "c"].contains(&name);
+    let issues = lint_trio(&markers_fn, None, &is_known).unwrap();
+    assert_eq!(
+        issues
+            .iter()
+            .map(|i| (i.line, i.kind.clone()))
+            .collect_vec(),
+        vec![
+            (3, MarkerIssueKind::UnknownNode),
+            (4, MarkerIssueKind::InvalidCount(String::from("1.5"))),
+            (4, MarkerIssueKind::InvalidCount(String::from("-2"))),
+            (5, MarkerIssueKind::TooFewColumns),
+        ]
+    );
+
+    //count columns are detected as for reading
+    markers_fn.write("# counts\nnode\tlen\thap1\thap2\na\t100\t1\t10\n");
+    let issues = lint_trio(&markers_fn, None, &is_known).unwrap();
+    assert_eq!(
+        issues
+            .iter()
+            .map(|i| (i.line, i.kind.clone()))
+            .collect_vec(),
+        vec![(2, MarkerIssueKind::MissingCountColumns)]
+    );
+    assert!(lint_trio(&markers_fn, Some(("hap2", "hap1")), &is_known)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn input_errors() {
+    init();
+
+    let markers_fn = TempFile::new(".tsv");
+    markers_fn.write("node\tmat\tpat\na\t10\t0\nb\tten\t2\n");
+    let err = read_trio(&markers_fn).unwrap_err();
+    assert!(matches!(err, Error::Markers(_)));
+    assert_eq!(err.exit_code(), 4);
+    assert_eq!(
+        err.to_string(),
+        "Invalid markers: invalid marker count 'ten' in line 3"
+    );
+
+    markers_fn.write("a\tMATERNAL\nb\tMATERNAL\n");
+    let g = Graph::read("S\ta\t*\tLN:i:100\n");
+    assert!(matches!(
+        parse_node_assignments(&g, markers_fn.to_str().unwrap()),
+        Err(Error::Assignments(_))
+    ));
+
+    let err = read_trio(&TempFile::new(".tsv")).unwrap_err();
+    assert!(matches!(err, Error::Io(_)));
+    assert_eq!(err.exit_code(), 2);
+}
+
+#[test]
+fn marker_table_reading() {
+    init();
+
+    let markers_fn = TempFile::new(".tsv");
+    markers_fn.write(
+        "# k-mer counts\ncontig\tlen\tpaternal\tmaternal\n\na\t100\t1\t10\nb\t50\t7\t0\n#a\t1\t1\t1\na\t100\t2\t5\n",
+    );
+    let infos = read_trio(&markers_fn).unwrap();
+    assert_eq!(
+        infos
+            .iter()
+            .map(|ti| (ti.node_name.as_str(), ti.mat(), ti.pat(), ti.line()))
+            .collect_vec(),
+        vec![("a", 15, 3, Some(4)), ("b", 0, 7, Some(5))]
+    );
+
+    let reject = MarkerReadSettings {
+        duplicates: DuplicateMarkers::Reject,
+        ..MarkerReadSettings::default()
+    };
+    assert_eq!(
+        read_trio_with(&markers_fn, &reject)
+            .unwrap_err()
+            .to_string(),
+        "Invalid markers: node a is listed in both line 4 and line 7"
+    );
+
+    markers_fn.write("node\tlen\thap1\thap2\na\t100\t1\t10\n");
+    assert!(matches!(read_trio(&markers_fn), Err(Error::Markers(_))));
+    let named = MarkerReadSettings {
+        hap_names: Some(("hap2", "hap1")),
+        ..MarkerReadSettings::default()
+    };
+    let infos = read_trio_with(&markers_fn, &named).unwrap();
+    assert_eq!((infos[0].mat(), infos[0].pat()), (10, 1));
+
+    markers_fn.write("a\t1\t10\nx\t0\t0\nb\t2\t2\ny\t5\t5\n");
+    let g = Graph::read("S\ta\t*\tLN:i:100\nS\tb\t*\tLN:i:100\n");
+    assert_eq!(
+        check_marker_nodes(&g, &read_trio(&markers_fn).unwrap())
+            .unwrap_err()
+            .to_string(),
+        "Invalid markers: 2 nodes are not in the graph: x (line 2), y (line 4)"
+    );
+}
+
+#[test]
+fn marker_model_posteriors() {
+    init();
+
+    use rukki::marker_model::*;
+    //1 marker per kb, 1% errors
+    let model = MarkerModel::new(1e-3, 0.01).unwrap();
+
+    let p = model.posterior(100, 1, 100_000);
+    assert_eq!(p.best().0, TrioGroup::MATERNAL);
+    assert!(p.maternal > 0.999);
+    assert!((p.maternal + p.paternal + p.homozygous + p.issue - 1.).abs() < 1e-9);
+
+    //short node with a few markers is not confidently assigned
+    let p = model.posterior(2, 0, 2_000);
+    assert_eq!(p.best().0, TrioGroup::MATERNAL);
+    assert!(p.maternal < 0.95);
+
+    //long node without markers
+    assert_eq!(
+        model.posterior(1, 0, 100_000).best().0,
+        TrioGroup::HOMOZYGOUS
+    );
+    //balanced marker counts
+    assert_eq!(model.posterior(50, 45, 100_000).best().0, TrioGroup::ISSUE);
+    assert_eq!(
+        model.posterior(3, 95, 100_000).best().0,
+        TrioGroup::PATERNAL
+    );
+
+    assert!(matches!(
+        MarkerModel::new(0., 0.01),
+        Err(Error::Settings(_))
+    ));
+
+    let g = Graph::read(
+        &"S a * LN:i:1000000\nS b * LN:i:1000000\nS c * LN:i:2000\nS d * LN:i:2000"
+            .replace(' ', "\t"),
+    );
+    let infos = |v: &[(&str, usize, usize)]| {
+        v.iter()
+            .map(|&(n, mat, pat)| TrioInfo::new(n, mat, pat))
+            .collect_vec()
+    };
+    let trio_infos = infos(&[("a", 990, 10), ("b", 0, 1010), ("c", 3, 0), ("d", 1, 1)]);
+    let model = MarkerModel::estimate(&g, &trio_infos, 500_000, None, None).unwrap();
+    assert!((model.density - 1e-3).abs() < 1e-9);
+    assert!((model.error_rate - 0.005).abs() < 1e-9);
+
+    let assignments = assign_by_model(&g, &trio_infos, &model, 0.9, 50_000).unwrap();
+    assert_eq!(assignments.group(g.name2id("a")), Some(TrioGroup::MATERNAL));
+    assert_eq!(assignments.group(g.name2id("b")), Some(TrioGroup::PATERNAL));
+    assert!(assignments.confidence(g.name2id("a")).unwrap() > 0.9);
+    assert!(!assignments.contains(g.name2id("d")));
+}
+
+#[test]
+fn threshold_calibration() {
+    use rukki::marker_model::*;
+
+    assert_eq!(
+        MarkerThresholds::calibrated(&MarkerModel::new(1e-3, 0.05).unwrap()),
+        MarkerThresholds {
+            marker_cnt: 10,
+            marker_sparsity: 4_000,
+            marker_ratio: 5.,
+        }
+    );
+    //low error rate and sparse markers
+    assert_eq!(
+        MarkerThresholds::calibrated(&MarkerModel::new(1e-5, 0.001).unwrap()),
+        MarkerThresholds {
+            marker_cnt: 20,
+            marker_sparsity: 400_000,
+            marker_ratio: 20.,
+        }
+    );
+
+    //node 'c' is not clearly phased and is ignored by the calibration fit
+    let g = Graph::read(
+        &"S a * LN:i:1000000\nS b * LN:i:1000000\nS c * LN:i:1000000".replace(' ', "\t"),
+    );
+    let trio_infos = [("a", 1900, 100), ("b", 0, 2000), ("c", 1000, 1000)]
+        .map(|(n, mat, pat)| TrioInfo::new(n, mat, pat));
+    let model = MarkerModel::fit_phased(&g, &trio_infos, 500_000, None, None).unwrap();
+    assert!((model.density - 1.95e-3).abs() < 1e-9);
+    assert!((model.error_rate - 100. / 3900.).abs() < 1e-9);
+    //but is used by the marker model estimate
+    let model = MarkerModel::estimate(&g, &trio_infos, 500_000, None, None).unwrap();
+    assert!((model.density - 4900. / 3e6).abs() < 1e-9);
+    assert!((model.error_rate - 1100. / 4900.).abs() < 1e-9);
+}
+
+#[derive(clap::Parser)]
+struct SubgraphCli {
+    #[command(flatten)]
+    settings: SubgraphSettings,
+}
+
+#[test]
+fn subgraph_marker_restriction() {
+    use clap::Parser;
+    init();
+
+    let graph_fn = TempFile::new(".gfa");
+    graph_fn.write("S\ta\t*\tLN:i:100\nS\tb\t*\tLN:i:100\nS\tc\t*\tLN:i:100\nL\ta\t+\tb\t+\t0M\n");
+    let markers_fn = TempFile::new(".tsv");
+    //columns in non-default order, header has to be kept
+    markers_fn.write("name\tpat\tmat\na\t10\t1\nb\t0\t7\nc\t5\t5\n");
+    let out_fn = TempFile::new(".gfa");
+    let markers_out_fn = TempFile::new(".tsv");
+    let cli = SubgraphCli::parse_from([
+        "subgraph",
+        "--graph",
+        graph_fn.to_str().unwrap(),
+        "--nodes",
+        "a",
+        "--output",
+        out_fn.to_str().unwrap(),
+        "--markers",
+        markers_fn.to_str().unwrap(),
+        "--markers-out",
+        markers_out_fn.to_str().unwrap(),
+    ]);
+    run_subgraph_extraction(&cli.settings).unwrap();
+
+    let counts = |path: &std::path::PathBuf| {
+        read_trio(path)
+            .unwrap()
+            .iter()
+            .map(|ti| (ti.node_name.clone(), ti.mat(), ti.pat()))
+            .collect_vec()
+    };
+    let restricted = counts(&markers_out_fn);
+    assert_eq!(
+        restricted,
+        vec![(String::from("a"), 1, 10), (String::from("b"), 7, 0)]
+    );
+    assert_eq!(&counts(&markers_fn)[..2], &restricted[..]);
+}
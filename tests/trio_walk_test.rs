@@ -1,6 +1,9 @@
 extern crate log;
+
+mod common;
 use itertools::Itertools;
 
+use common::TempFile;
 use rukki::trio::*;
 use rukki::trio_walk::{HaploSearchSettings, HaploSearcher};
 use rukki::*;
@@ -45,7 +48,7 @@ fn haplo_paths() {
     let assignments = trio::parse_node_assignments(&g, assignments_fn).unwrap();
 
     let settings = trio_walk::HaploSearchSettings::default();
-    let augment_assign = augment_by_path_search(&g, assignments, settings);
+    let augment_assign = augment_by_path_search(&g, assignments, settings).unwrap();
 
     let mut haplo_searcher = build_searcher(settings, &g, &augment_assign);
     let mut answer = haplo_searcher
@@ -73,7 +76,7 @@ fn augment_by_search() {
     let settings = trio_walk::HaploSearchSettings::default();
     assert_eq!(assignments.assigned().count(), 14);
 
-    let augment_assign = augment_by_path_search(&g, assignments, settings);
+    let augment_assign = augment_by_path_search(&g, assignments, settings).unwrap();
 
     assert_eq!(augment_assign.assigned().count(), 17);
     assert_eq!(
@@ -119,7 +122,7 @@ fn bubble_filling() {
     };
     assert_eq!(assignments.assigned().count(), 26);
 
-    let augment_assign = augment_by_path_search(&g, assignments, settings);
+    let augment_assign = augment_by_path_search(&g, assignments, settings).unwrap();
 
     assert_eq!(augment_assign.assigned().count(), 28);
     assert_eq!(
@@ -162,7 +165,7 @@ fn haplo_paths_2() {
     let settings = trio_walk::HaploSearchSettings::default();
     assert_eq!(assignments.assigned().count(), 42);
 
-    let augment_assign = augment_by_path_search(&g, assignments, settings);
+    let augment_assign = augment_by_path_search(&g, assignments, settings).unwrap();
 
     assert_eq!(
         augment_assign.group(g.name2id("utig4-414")),
@@ -205,7 +208,7 @@ fn haplo_paths_3() {
     let settings = trio_walk::HaploSearchSettings::default();
     assert_eq!(assignments.assigned().count(), 76);
 
-    let augment_assign = augment_by_path_search(&g, assignments, settings);
+    let augment_assign = augment_by_path_search(&g, assignments, settings).unwrap();
 
     assert_eq!(
         augment_assign.group(g.name2id("utig4-1404")),
@@ -232,3 +235,177 @@ fn haplo_paths_3() {
         (TrioGroup::PATERNAL,
             String::from("utig4-3455-,utig4-3445-,utig4-3447+,utig4-1410-,utig4-1408-,utig4-1404-,utig4-1402+,utig4-1405+,utig4-1795-,utig4-1452-,utig4-1450-,utig4-1394-,utig4-1392-,utig4-1388-,utig4-1387-,utig4-1021-,utig4-1019+,utig4-1023+,utig4-1024+,utig4-1026+,utig4-3630-,utig4-3626-,utig4-3627+,utig4-1257-,utig4-1253-,utig4-1249-,utig4-1251+,utig4-1476-,utig4-1478+,utig4-3650-,utig4-68-,utig4-64-,utig4-66+,utig4-1617-,utig4-1618+,utig4-1896-,utig4-1596-,utig4-1595-,utig4-927-,utig4-923-,utig4-924+,utig4-1892+,utig4-1530-,utig4-1529+,utig4-1532+,utig4-1534+,utig4-3593-,utig4-3591-,utig4-3589-,[N34594N:alt-utig4-3587],utig4-3384+"))]);
 }
+
+#[test]
+fn graph_gap_jump() {
+    init();
+
+    let s = "
+S a 600000 *
+S b 600000 *
+S c 700000 *
+G g1 a+ b+ 500 *
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let mut assignments = AssignmentStorage::new();
+    for name in ["a", "b", "c"] {
+        assignments.assign(g.name2id(name), TrioGroup::MATERNAL, "");
+    }
+    let mut haplo_searcher = build_searcher(HaploSearchSettings::default(), &g, &assignments);
+    let mut answer = haplo_searcher
+        .find_all()
+        .into_iter()
+        .map(|(p, _, _)| p.print(&g))
+        .collect_vec();
+    answer.sort();
+    assert_eq!(answer, vec!["a+,[N1000N:g1],b+", "c+"]);
+
+    //jump of unknown size gets the default gap size
+    let g = Graph::read(
+        &s.replace("G g1 a+ b+ 500 *", "J a + b + *")
+            .replace(' ', "\t"),
+    );
+    let mut haplo_searcher = build_searcher(HaploSearchSettings::default(), &g, &assignments);
+    let mut answer = haplo_searcher
+        .find_all()
+        .into_iter()
+        .map(|(p, _, _)| p.print(&g))
+        .collect_vec();
+    answer.sort();
+    assert_eq!(answer, vec!["a+,[N5000N:gfa_gap],b+", "c+"]);
+}
+
+#[test]
+fn gfa_path_seed() {
+    init();
+
+    let s = "
+S a * LN:i:700000
+S x1 * LN:i:10000
+S x2 * LN:i:10000
+S b * LN:i:600000
+S c * LN:i:600000
+L a + x1 + 100M
+L a + x2 + 100M
+L x1 + b + 100M
+L x2 + b + 100M
+L b + c + 100M
+P s1 a+,x2+,b+ 100M,100M
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let mut assignments = AssignmentStorage::new();
+    for name in ["a", "b", "c"] {
+        assignments.assign(g.name2id(name), TrioGroup::MATERNAL, "");
+    }
+    let mut haplo_searcher = build_searcher(HaploSearchSettings::default(), &g, &assignments);
+    haplo_searcher.set_seeds(g.paths().iter().map(|(_, p)| p.clone()).collect());
+    let answer = haplo_searcher
+        .find_all()
+        .into_iter()
+        .map(|(p, node_id, group)| (p.print(&g), g.name(node_id).to_string(), group))
+        .collect_vec();
+    assert_eq!(
+        answer,
+        vec![(
+            String::from("a+,x2+,b+,c+"),
+            String::from("a"),
+            TrioGroup::MATERNAL
+        )]
+    );
+}
+
+#[test]
+fn annotated_gfa_output() {
+    init();
+
+    let s = "
+S a 600000 *
+S b 600000 *
+S c 700000 *
+G g1 a+ b+ 500 *
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let mut assignments = AssignmentStorage::new();
+    for name in ["a", "b"] {
+        assignments.assign(g.name2id(name), TrioGroup::MATERNAL, "");
+    }
+    assignments.assign(g.name2id("c"), TrioGroup::PATERNAL, "");
+    let mut haplo_searcher = build_searcher(HaploSearchSettings::default(), &g, &assignments);
+    let haplo_paths = haplo_searcher.find_all();
+    let node_usage = haplo_searcher.take_used();
+    let hap_names = ("mat", "pat");
+    let records = haplo_path_records(&g, haplo_paths, &assignments, &node_usage, &hap_names);
+
+    let output = TempFile::new(".gfa");
+    write_gfa(&g, &records, &assignments, &output, &hap_names).unwrap();
+    let gfa = fs::read_to_string(&*output).unwrap();
+    //segment sequences are not available
+    assert!(matches!(
+        write_fasta(&g, &records, &TempFile::new(".fasta")),
+        Err(Error::Gfa(e)) if e.kind == GfaErrorKind::MissingSequence && e.line == 0
+    ));
+    let lines = gfa.lines().collect_vec();
+    assert!(lines.contains(&"S\ta\t*\tLN:i:600000\tRC:i:0\tll:f:0.0\tCL:z:#FF8888\tHP:Z:MAT"));
+    assert!(lines.contains(&"S\tc\t*\tLN:i:700000\tRC:i:0\tll:f:0.0\tCL:z:#8888FF\tHP:Z:PAT"));
+    //haplo-path gap between the same vertices is backed by the graph gap
+    assert_eq!(lines.iter().filter(|l| l.starts_with("J\t")).count(), 1);
+    assert!(lines.contains(&"J\ta\t+\tb\t+\t500"));
+    assert!(lines.contains(&"P\tmat_from_b\ta+;b+\t*"));
+    assert!(lines.contains(&"P\tpat_from_c\tc+\t*"));
+}
+
+#[test]
+fn link_support_bubble_fill() {
+    init();
+
+    let s = "
+S a * LN:i:700000 ll:f:10
+S x1 * LN:i:10000 ll:f:20
+S x2 * LN:i:10000 ll:f:10
+S b * LN:i:600000 ll:f:10
+L a + x1 + 100M RC:i:2
+L a + x2 + 100M RC:i:40
+L x1 + b + 100M ex:i:1
+L x2 + b + 100M ex:i:30
+";
+    let g = Graph::read(&s.replace(' ', "\t"));
+    let mut assignments = AssignmentStorage::new();
+    for name in ["a", "b"] {
+        assignments.assign(g.name2id(name), TrioGroup::MATERNAL, "");
+    }
+    let find_paths = |settings: HaploSearchSettings| {
+        build_searcher(settings, &g, &assignments)
+            .find_all()
+            .into_iter()
+            .map(|(p, _, _)| p.print(&g))
+            .collect_vec()
+    };
+
+    //by default side with higher coverage is chosen
+    assert_eq!(
+        find_paths(HaploSearchSettings::default()),
+        vec!["a+,x1+,b+"]
+    );
+    assert_eq!(
+        find_paths(HaploSearchSettings {
+            link_support_ratio: 10.,
+            ..HaploSearchSettings::default()
+        }),
+        vec!["a+,x2+,b+"]
+    );
+    //weakest link support of x2 side (30) is not high enough
+    assert_eq!(
+        find_paths(HaploSearchSettings {
+            link_support_ratio: 50.,
+            ..HaploSearchSettings::default()
+        }),
+        vec!["a+,x1+,b+"]
+    );
+    assert_eq!(
+        find_paths(HaploSearchSettings {
+            min_link_support: 5,
+            ..HaploSearchSettings::default()
+        }),
+        vec!["a+,x2+,b+"]
+    );
+}